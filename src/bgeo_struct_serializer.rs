@@ -1,185 +1,377 @@
-/// for now it implements only little endian bgeos
-/// not sure why or when would i want to save big endians instead
+//! bgeo writer, little endian by default, like houdini writes it
+//! big endian is there for tools that still want it
 
+use std::collections::HashMap;
 use std::io::Write;
 use crate::geo_struct::{ReaderElement, UniformArrayType};
 use crate::bgeo_constants::*;
+use crate::compression::{BloscWriter, GzipWriter};
+use crate::f16_half::{half_to_be_bytes, half_to_le_bytes};
+
+/// byte order of numbers in written bgeo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn from_name(name: &str) -> Option<Endianness> {
+        match name {
+            "little" | "le" => Some(Endianness::Little),
+            "big" | "be" => Some(Endianness::Big),
+            _ => None,
+        }
+    }
+}
 
 pub fn to_bjson(element: &ReaderElement, output: &mut dyn Write) {
-    let mut buff = [0_u8; 5];
-    buff[0] = JID_MAGIC;
-    buff[1..5].copy_from_slice(&BINARY_MAGIC_SWAP);
-    output.write(&buff).expect(ERRMSG);
+    to_bjson_endian(element, output, Endianness::Little);
+}
+
+/// same as to_bjson, but wraps the stream into blosc chunks, like houdini's .bgeo.sc
+pub fn to_bjson_sc(element: &ReaderElement, output: &mut dyn Write) {
+    to_bjson_sc_endian(element, output, Endianness::Little);
+}
+
+/// same as to_bjson, but gzipped, like houdini's .bgeo.gz
+pub fn to_bjson_gz(element: &ReaderElement, output: &mut dyn Write) {
+    to_bjson_gz_endian(element, output, Endianness::Little);
+}
+
+pub fn to_bjson_endian(element: &ReaderElement, output: &mut dyn Write, endianness: Endianness) {
+    let mut writer = match endianness {
+        Endianness::Little => BgeoWriter::new_le(output, element),
+        Endianness::Big => BgeoWriter::new_be(output, element),
+    };
+    writer.write_magic(endianness);
+    writer.write_element(element);
+}
+
+pub fn to_bjson_sc_endian(element: &ReaderElement, output: &mut dyn Write, endianness: Endianness) {
+    let mut compressed = BloscWriter::new(output);
+    to_bjson_endian(element, &mut compressed, endianness);
+    compressed.finish().expect(ERRMSG);
+}
 
-    write_element(output, element);
+pub fn to_bjson_gz_endian(element: &ReaderElement, output: &mut dyn Write, endianness: Endianness) {
+    let mut compressed = GzipWriter::new(output);
+    to_bjson_endian(element, &mut compressed, endianness);
+    compressed.finish().expect(ERRMSG);
 }
 
 //
 const ERRMSG: &str = "unexpected end of stream while reading length";
 
+/// strings that occur more than once are written as tokens, like houdini does:
+/// defined once with JID_TOKENDEF, and then referenced with JID_TOKENREF by id
+struct TokenTable<'a> {
+    occurrences: HashMap<&'a str, usize>,
+    ids: HashMap<&'a str, usize>,
+}
 
-fn write_length(output: &mut dyn Write, len: usize) {
-    let mut buff = [0_u8; 9];
-    match len {
-        x if x < 0xf1 => {
-            buff[0] = x as u8;
-            output.write(&buff[..1]).expect(ERRMSG);
-        }
-        x if x <= 0xffff => {
-            buff[0] = 0xf0 + 2;
-            buff[1..3].copy_from_slice(&(x as u16).to_le_bytes());
-            output.write(&buff[..3]).expect(ERRMSG);
+impl<'a> TokenTable<'a> {
+    fn new(root: &'a ReaderElement) -> TokenTable<'a> {
+        let mut occurrences = HashMap::new();
+        count_strings(root, &mut occurrences);
+        TokenTable {
+            occurrences,
+            ids: HashMap::new(),
         }
-        x if x <= 0xffffffff => {
-            buff[0] = 0xf0 + 4;
-            buff[1..5].copy_from_slice(&(x as u32).to_le_bytes());
-            output.write(&buff[..5]).expect(ERRMSG);
-        }
-        x if x <= 0xffffffffffffffff => {
-            buff[0] = 0xf0 + 8;
-            buff[1..9].copy_from_slice(&(x as u64).to_le_bytes());
-            output.write(&buff[..9]).expect(ERRMSG);
+    }
+
+    /// None if string is to be written inline,
+    /// otherwise token id and if it's the first use, so token needs to be defined
+    fn token(&mut self, text: &'a str) -> Option<(usize, bool)> {
+        if self.occurrences.get(text).copied().unwrap_or(0) < 2 {
+            return None;
         }
-        _ => {
-            panic!("len is too be to be saved!");
+        match self.ids.get(text) {
+            Some(id) => Some((*id, false)),
+            None => {
+                let id = self.ids.len();
+                self.ids.insert(text, id);
+                Some((id, true))
+            }
         }
     }
 }
 
-fn write_string(output: &mut dyn Write, string: &String) {
-    let bytes = string.as_bytes();
-    write_length(output, bytes.len());
-    output.write(bytes).expect(ERRMSG);
-}
-
-// TODO make into a trait that both geo/bgeo serializers implement
-fn write_element(output: &mut dyn Write, elem: &ReaderElement) {
-    let mut buf = [0_u8; 9];
-
+fn count_strings<'a>(elem: &'a ReaderElement, occurrences: &mut HashMap<&'a str, usize>) {
     match elem {
+        ReaderElement::Text(text) => *occurrences.entry(text.as_str()).or_insert(0) += 1,
         ReaderElement::Array(arr) => {
-            buf[0] = JID_ARRAY_BEGIN;
-            buf[1] = JID_ARRAY_END;
-            output.write(&buf[..1]).expect(ERRMSG);  // write begin
             for subelem in arr {
-                write_element(output, subelem)
+                count_strings(subelem, occurrences);
             }
-            output.write(&buf[1..2]).expect(ERRMSG);  // write end
         }
         ReaderElement::KeyValueObject(kvo) => {
-            buf[0] = JID_MAP_BEGIN;
-            buf[1] = JID_MAP_END;
-            buf[2] = JID_STRING;
-            output.write(&buf[..1]).expect(ERRMSG);  // write begin
             for (key, val) in kvo.iter() {
-                output.write(&buf[2..3]).expect(ERRMSG);  // write string jid
-                write_string(output, key);
-                write_element(output, val);
+                *occurrences.entry(key.as_str()).or_insert(0) += 1;
+                count_strings(val, occurrences);
             }
-            output.write(&buf[1..2]).expect(ERRMSG);  // write end
         }
-        ReaderElement::UniformArray(uarr) => {
-            buf[0] = JID_UNIFORM_ARRAY;
-            output.write(&buf[..1]).expect(ERRMSG);
-            match uarr {
-                UniformArrayType::UniformArrayTu8(vec) => {
-                    buf[1] = JID_UINT8;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &u8::to_le_bytes);
-                }
-                UniformArrayType::UniformArrayTu16(vec) => {
-                    buf[1] = JID_UINT16;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &u16::to_le_bytes);
-                }
-                UniformArrayType::UniformArrayTi8(vec) => {
-                    buf[1] = JID_INT8;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &i8::to_le_bytes);
+        _ => (),
+    }
+}
+
+struct BgeoWriter<'a, 'w> {
+    output: &'w mut dyn Write,
+    tokens: TokenTable<'a>,
+    u16_to_bytes: &'static dyn Fn(u16) -> [u8; 2],
+    u32_to_bytes: &'static dyn Fn(u32) -> [u8; 4],
+    u64_to_bytes: &'static dyn Fn(u64) -> [u8; 8],
+    i16_to_bytes: &'static dyn Fn(i16) -> [u8; 2],
+    i32_to_bytes: &'static dyn Fn(i32) -> [u8; 4],
+    i64_to_bytes: &'static dyn Fn(i64) -> [u8; 8],
+    f16_32_to_bytes: &'static dyn Fn(f32) -> [u8; 2],
+    f32_to_bytes: &'static dyn Fn(f32) -> [u8; 4],
+    f64_to_bytes: &'static dyn Fn(f64) -> [u8; 8],
+    big_endian: bool,
+}
+
+impl<'a, 'w> BgeoWriter<'a, 'w> {
+    fn new_be(output: &'w mut dyn Write, root: &'a ReaderElement) -> BgeoWriter<'a, 'w> {
+        BgeoWriter {
+            output,
+            tokens: TokenTable::new(root),
+            u16_to_bytes: &u16::to_be_bytes,
+            u32_to_bytes: &u32::to_be_bytes,
+            u64_to_bytes: &u64::to_be_bytes,
+            i16_to_bytes: &i16::to_be_bytes,
+            i32_to_bytes: &i32::to_be_bytes,
+            i64_to_bytes: &i64::to_be_bytes,
+            f16_32_to_bytes: &half_to_be_bytes,
+            f32_to_bytes: &f32::to_be_bytes,
+            f64_to_bytes: &f64::to_be_bytes,
+            big_endian: true,
+        }
+    }
+
+    fn new_le(output: &'w mut dyn Write, root: &'a ReaderElement) -> BgeoWriter<'a, 'w> {
+        BgeoWriter {
+            output,
+            tokens: TokenTable::new(root),
+            u16_to_bytes: &u16::to_le_bytes,
+            u32_to_bytes: &u32::to_le_bytes,
+            u64_to_bytes: &u64::to_le_bytes,
+            i16_to_bytes: &i16::to_le_bytes,
+            i32_to_bytes: &i32::to_le_bytes,
+            i64_to_bytes: &i64::to_le_bytes,
+            f16_32_to_bytes: &half_to_le_bytes,
+            f32_to_bytes: &f32::to_le_bytes,
+            f64_to_bytes: &f64::to_le_bytes,
+            big_endian: false,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.output.write_all(bytes).expect(ERRMSG);
+    }
+
+    /// magic is the same 4 bytes, but reader sees them swapped when endianness does not match
+    fn write_magic(&mut self, endianness: Endianness) {
+        self.write(&[JID_MAGIC]);
+        match endianness {
+            Endianness::Little => self.write(&BINARY_MAGIC_SWAP),
+            Endianness::Big => self.write(&BINARY_MAGIC),
+        }
+    }
+
+    fn write_length(&mut self, len: usize) {
+        match len {
+            x if x < 0xf1 => {
+                self.write(&[x as u8]);
+            }
+            x if x <= 0xffff => {
+                self.write(&[0xf0 + 2]);
+                self.write(&(self.u16_to_bytes)(x as u16));
+            }
+            x if x <= 0xffffffff => {
+                self.write(&[0xf0 + 4]);
+                self.write(&(self.u32_to_bytes)(x as u32));
+            }
+            x => {
+                self.write(&[0xf0 + 8]);
+                self.write(&(self.u64_to_bytes)(x as u64));
+            }
+        }
+    }
+
+    fn write_string(&mut self, string: &str) {
+        let bytes = string.as_bytes();
+        self.write_length(bytes.len());
+        self.write(bytes);
+    }
+
+    /// write a string element, as a token if it repeats
+    fn write_text(&mut self, text: &'a str) {
+        match self.tokens.token(text) {
+            None => {
+                self.write(&[JID_STRING]);
+                self.write_string(text);
+            }
+            Some((id, define)) => {
+                if define {
+                    self.write(&[JID_TOKENDEF]);
+                    self.write_length(id);
+                    self.write_string(text);
                 }
-                UniformArrayType::UniformArrayTi16(vec) => {
-                    buf[1] = JID_INT16;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &i16::to_le_bytes);
+                self.write(&[JID_TOKENREF]);
+                self.write_length(id);
+            }
+        }
+    }
+
+    // TODO make into a trait that both geo/bgeo serializers implement
+    fn write_element(&mut self, elem: &'a ReaderElement) {
+        match elem {
+            ReaderElement::Array(arr) => {
+                // homogeneous arrays of numbers, like topology indices built by hand, are better written as uniform arrays
+                if let Some(uarr) = UniformArrayType::from_elements(arr) {
+                    self.write_uniform(&uarr);
+                    return;
                 }
-                UniformArrayType::UniformArrayTi32(vec) => {
-                    buf[1] = JID_INT32;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &i32::to_le_bytes);
+                self.write(&[JID_ARRAY_BEGIN]);
+                for subelem in arr {
+                    self.write_element(subelem)
                 }
-                UniformArrayType::UniformArrayTi64(vec) => {
-                    buf[1] = JID_INT64;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &i64::to_le_bytes);
+                self.write(&[JID_ARRAY_END]);
+            }
+            ReaderElement::KeyValueObject(kvo) => {
+                self.write(&[JID_MAP_BEGIN]);
+                for (key, val) in kvo.iter() {
+                    self.write_text(key);
+                    self.write_element(val);
                 }
-                UniformArrayType::UniformArrayTf16(vec) => {
-                    // TODO: implement actual f16 writing !
-                    buf[1] = JID_REAL32;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &f32::to_le_bytes);
+                self.write(&[JID_MAP_END]);
+            }
+            ReaderElement::UniformArray(uarr) => {
+                self.write_uniform(uarr);
+            }
+            ReaderElement::TupleArray(tuples) => {
+                self.write(&[JID_ARRAY_BEGIN]);
+                for i in 0..tuples.len() {
+                    self.write_uniform(&tuples.tuple(i));
                 }
-                UniformArrayType::UniformArrayTf32(vec) => {
-                    buf[1] = JID_REAL32;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &f32::to_le_bytes);
+                self.write(&[JID_ARRAY_END]);
+            }
+            ReaderElement::LazyUniformArray(lazy) if lazy.is_big_endian() == self.big_endian => {
+                // data in the file is already in the right form, no need to decode it
+                self.write(&[JID_UNIFORM_ARRAY, lazy.array_type()]);
+                self.write_length(lazy.len());
+                self.write(lazy.raw_bytes());
+            }
+            ReaderElement::LazyUniformArray(lazy) => {
+                if let ReaderElement::UniformArray(uarr) = lazy.to_element().as_ref() {
+                    self.write_uniform(uarr);
                 }
-                UniformArrayType::UniformArrayTf64(vec) => {
-                    buf[1] = JID_REAL64;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_uniform_array(output, vec, &f64::to_le_bytes);
+            }
+            ReaderElement::Int(i) => {
+                self.write_int(*i);
+            }
+            ReaderElement::Float(f) => {
+                self.write(&[JID_REAL64]);
+                self.write(&(self.f64_to_bytes)(*f));
+            }
+            ReaderElement::Text(t) => {
+                self.write_text(t);
+            }
+            ReaderElement::Bool(b) => {
+                self.write(&[JID_BOOL, *b as u8]);
+            }
+            ReaderElement::None => {
+                self.write(&[JID_NULL]);
+            }
+        };
+    }
+
+    /// ints are written with the narrowest type that fits the value
+    fn write_int(&mut self, value: i64) {
+        if let Ok(x) = i8::try_from(value) {
+            self.write(&[JID_INT8]);
+            self.write(&x.to_le_bytes());
+        } else if let Ok(x) = u8::try_from(value) {
+            self.write(&[JID_UINT8, x]);
+        } else if let Ok(x) = i16::try_from(value) {
+            self.write(&[JID_INT16]);
+            self.write(&(self.i16_to_bytes)(x));
+        } else if let Ok(x) = u16::try_from(value) {
+            self.write(&[JID_UINT16]);
+            self.write(&(self.u16_to_bytes)(x));
+        } else if let Ok(x) = i32::try_from(value) {
+            self.write(&[JID_INT32]);
+            self.write(&(self.i32_to_bytes)(x));
+        } else {
+            self.write(&[JID_INT64]);
+            self.write(&(self.i64_to_bytes)(value));
+        }
+    }
+
+    fn write_uniform(&mut self, uarr: &UniformArrayType) {
+        self.write(&[JID_UNIFORM_ARRAY]);
+        match uarr {
+            UniformArrayType::UniformArrayTu8(vec) => {
+                self.write(&[JID_UINT8]);
+                self.write_uniform_array(vec, &u8::to_le_bytes);
+            }
+            UniformArrayType::UniformArrayTu16(vec) => {
+                self.write(&[JID_UINT16]);
+                self.write_uniform_array(vec, self.u16_to_bytes);
+            }
+            UniformArrayType::UniformArrayTi8(vec) => {
+                self.write(&[JID_INT8]);
+                self.write_uniform_array(vec, &i8::to_le_bytes);
+            }
+            UniformArrayType::UniformArrayTi16(vec) => {
+                self.write(&[JID_INT16]);
+                self.write_uniform_array(vec, self.i16_to_bytes);
+            }
+            UniformArrayType::UniformArrayTi32(vec) => {
+                self.write(&[JID_INT32]);
+                self.write_uniform_array(vec, self.i32_to_bytes);
+            }
+            UniformArrayType::UniformArrayTi64(vec) => {
+                self.write(&[JID_INT64]);
+                self.write_uniform_array(vec, self.i64_to_bytes);
+            }
+            UniformArrayType::UniformArrayTf16(vec) => {
+                self.write(&[JID_REAL16]);
+                self.write_uniform_array(vec, self.f16_32_to_bytes);
+            }
+            UniformArrayType::UniformArrayTf32(vec) => {
+                self.write(&[JID_REAL32]);
+                self.write_uniform_array(vec, self.f32_to_bytes);
+            }
+            UniformArrayType::UniformArrayTf64(vec) => {
+                self.write(&[JID_REAL64]);
+                self.write_uniform_array(vec, self.f64_to_bytes);
+            }
+            UniformArrayType::UniformArrayTstring(vec) => {
+                // elements are not tagged, so they can't be tokens and are always written inline
+                self.write(&[JID_STRING]);
+                self.write_length(vec.len());
+                for string in vec {
+                    self.write_string(string);
                 }
-                UniformArrayType::UniformArrayTbool(vec) => {
-                    buf[1] = JID_BOOL;
-                    output.write(&buf[1..2]).expect(ERRMSG);
-                    write_length(output, vec.len());
-                    let mut packed = 0_u32;
-                    let mut i = 0;
-                    for val in vec {
-                        packed |= (*val as u32) << i;
-                        i += 1;
-                        if i == 32 {
-                            output.write(&packed.to_le_bytes()).expect(ERRMSG);
-                            i = 0;
-                            packed = 0;
-                        }
-                    }
-                    if i != 32 {  // so if last packed was not written
-                        output.write(&packed.to_le_bytes()).expect(ERRMSG);
-                    }
+            }
+            UniformArrayType::UniformArrayTbool(vec) => {
+                self.write(&[JID_BOOL]);
+                self.write_length(vec.len());
+                // packed blocks of 32 bits
+                for block in vec.chunks(32) {
+                    let packed = block.iter().enumerate().fold(0_u32, |packed, (i, val)| packed | (*val as u32) << i);
+                    self.write(&(self.u32_to_bytes)(packed));
                 }
             }
         }
-        ReaderElement::Int(i) => {
-            buf[0] = JID_INT64;
-            buf[1..9].copy_from_slice(&i.to_le_bytes());
-            output.write(&buf[..9]).expect(ERRMSG);
-        }
-        ReaderElement::Float(f) => {
-            buf[0] = JID_REAL64;
-            buf[1..9].copy_from_slice(&f.to_le_bytes());
-            output.write(&buf[..9]).expect(ERRMSG);
-        }
-        ReaderElement::Text(t) => {
-            buf[0] = JID_STRING;
-            output.write(&buf[..1]).expect(ERRMSG);
-            write_string(output, t);
-        }
-        ReaderElement::Bool(b) => {
-            buf[0] = JID_BOOL;
-            buf[1] = *b as u8;
-            output.write(&buf[..2]).expect(ERRMSG);
-        }
-        ReaderElement::None => {
-            buf[0] = JID_NULL;
-            output.write(&buf[..1]).expect(ERRMSG);
-        }
-    };
-}
-
+    }
 
-fn write_uniform_array<T: Copy, const N: usize>(output: &mut dyn Write, array: &Vec<T>, tobytes_func: & dyn Fn(T) -> [u8; N]) {
-    write_length(output, array.len());
-    for el in array {
-        output.write(&(tobytes_func(*el))).expect(ERRMSG);
+    fn write_uniform_array<T: Copy, const N: usize>(&mut self, array: &[T], tobytes_func: &dyn Fn(T) -> [u8; N]) {
+        self.write_length(array.len());
+        for el in array {
+            self.write(&tobytes_func(*el));
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,438 @@
+/// c-blosc chunk container
+///
+/// houdini's .sc files are a plain sequence of blosc chunks,
+/// each chunk starts with a 16 byte header:
+///  0      - blosc format version
+///  1      - codec format version
+///  2      - flags (shuffle, memcpyed, split, codec in upper 3 bits)
+///  3      - typesize
+///  4..8   - uncompressed size of the chunk
+///  8..12  - block size
+///  12..16 - compressed size of the chunk, including header
+///
+/// see https://github.com/Blosc/c-blosc/blob/main/README_CHUNK_FORMAT.rst
+use std::io::{self, Read, Write};
+
+use super::{blosclz, lz4};
+
+pub const BLOSC_HEADER_SIZE: usize = 16;
+
+const BLOSC_DOSHUFFLE: u8 = 0x01;
+const BLOSC_MEMCPYED: u8 = 0x02;
+const BLOSC_DOBITSHUFFLE: u8 = 0x04;
+const BLOSC_DONT_SPLIT: u8 = 0x10;
+
+const BLOSC_MAX_SPLITS: usize = 16;
+const BLOSC_MIN_BUFFERSIZE: usize = 128;
+
+const BLOSC_BLOSCLZ_FORMAT: u8 = 0;
+const BLOSC_LZ4_FORMAT: u8 = 1;
+
+const BLOSC_VERSION_FORMAT: u8 = 2;
+const BLOSC_BLOSCLZ_VERSION_FORMAT: u8 = 1;
+
+// sizes of chunks and blocks we produce when compressing
+const WRITER_CHUNK_SIZE: usize = 1 << 20;
+const WRITER_BLOCK_SIZE: usize = 1 << 17;
+
+struct BloscHeader {
+    flags: u8,
+    typesize: usize,
+    nbytes: usize,
+    blocksize: usize,
+    cbytes: usize,
+}
+
+impl BloscHeader {
+    fn from_bytes(header: &[u8]) -> BloscHeader {
+        BloscHeader {
+            flags: header[2],
+            typesize: header[3] as usize,
+            nbytes: u32::from_le_bytes(header[4..8].try_into().expect("impossible!")) as usize,
+            blocksize: u32::from_le_bytes(header[8..12].try_into().expect("impossible!")) as usize,
+            cbytes: u32::from_le_bytes(header[12..16].try_into().expect("impossible!")) as usize,
+        }
+    }
+
+    fn codec(&self) -> u8 {
+        self.flags >> 5
+    }
+}
+
+/// check if given bytes look like a start of a blosc chunk
+///
+/// there is no magic in blosc format, so we can only check that header values are sane
+pub fn is_blosc_header(header: &[u8]) -> bool {
+    if header.len() < BLOSC_HEADER_SIZE {
+        return false;
+    }
+    let version = header[0];
+    if version == 0 || version > 4 {
+        return false;
+    }
+    let h = BloscHeader::from_bytes(header);
+    if h.typesize == 0 || h.cbytes < BLOSC_HEADER_SIZE || h.codec() > 4 {
+        return false;
+    }
+    if h.flags & BLOSC_MEMCPYED != 0 {
+        return h.cbytes == h.nbytes + BLOSC_HEADER_SIZE;
+    }
+    h.nbytes == 0 || h.blocksize > 0
+}
+
+/// decompress one whole chunk (header included) and append result to output
+fn decompress_chunk(chunk: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
+    let header = BloscHeader::from_bytes(chunk);
+    let out_start = output.len();
+    output.resize(out_start + header.nbytes, 0);
+    let out = &mut output[out_start..];
+
+    if header.flags & BLOSC_MEMCPYED != 0 {
+        if chunk.len() < BLOSC_HEADER_SIZE + header.nbytes {
+            return Err("memcpyed blosc chunk is truncated".to_owned());
+        }
+        out.copy_from_slice(&chunk[BLOSC_HEADER_SIZE..BLOSC_HEADER_SIZE + header.nbytes]);
+        return Ok(());
+    }
+    if header.nbytes == 0 {
+        return Ok(());
+    }
+    if header.flags & BLOSC_DOBITSHUFFLE != 0 {
+        return Err("bitshuffled blosc chunks are not supported".to_owned());
+    }
+    let codec_decompress = match header.codec() {
+        BLOSC_BLOSCLZ_FORMAT => blosclz::decompress,
+        BLOSC_LZ4_FORMAT => lz4::decompress,
+        x => {
+            return Err(format!("blosc codec {} is not supported, only blosclz and lz4 are", x));
+        }
+    };
+
+    let typesize = header.typesize;
+    let blocksize = header.blocksize;
+    let leftover = header.nbytes % blocksize;
+    let nblocks = header.nbytes.div_ceil(blocksize);
+    let shuffled = header.flags & BLOSC_DOSHUFFLE != 0 && typesize > 1;
+
+    let bstarts_end = BLOSC_HEADER_SIZE + nblocks * 4;
+    if chunk.len() < bstarts_end {
+        return Err("blosc chunk is truncated in block offsets".to_owned());
+    }
+    let mut tmp = if shuffled { vec![0_u8; blocksize] } else { Vec::new() };
+
+    for block_i in 0..nblocks {
+        let is_leftover = leftover > 0 && block_i == nblocks - 1;
+        let bsize = if is_leftover { leftover } else { blocksize };
+        let nsplits = if header.flags & BLOSC_DONT_SPLIT == 0
+            && !is_leftover
+            && typesize <= BLOSC_MAX_SPLITS
+            && blocksize / typesize >= BLOSC_MIN_BUFFERSIZE
+        {
+            typesize
+        } else {
+            1
+        };
+        let neblock = bsize / nsplits;
+
+        let bstart_pos = BLOSC_HEADER_SIZE + block_i * 4;
+        let mut ip = u32::from_le_bytes(chunk[bstart_pos..bstart_pos + 4].try_into().expect("impossible!")) as usize;
+
+        let dest = &mut out[block_i * blocksize..block_i * blocksize + bsize];
+        let block_out: &mut [u8] = if shuffled { &mut tmp[..bsize] } else { dest };
+
+        for split_i in 0..nsplits {
+            if ip + 4 > chunk.len() {
+                return Err("blosc chunk is truncated in split size".to_owned());
+            }
+            let split_cbytes = u32::from_le_bytes(chunk[ip..ip + 4].try_into().expect("impossible!")) as usize;
+            ip += 4;
+            if ip + split_cbytes > chunk.len() {
+                return Err("blosc chunk is truncated in split data".to_owned());
+            }
+            let split_in = &chunk[ip..ip + split_cbytes];
+            let split_out = &mut block_out[split_i * neblock..(split_i + 1) * neblock];
+            if split_cbytes == neblock {
+                // incompressible split is stored as is
+                split_out.copy_from_slice(split_in);
+            } else {
+                codec_decompress(split_in, split_out)?;
+            }
+            ip += split_cbytes;
+        }
+
+        if shuffled {
+            unshuffle(typesize, &tmp[..bsize], &mut out[block_i * blocksize..block_i * blocksize + bsize]);
+        }
+    }
+    Ok(())
+}
+
+/// reverse blosc byte shuffle: byte i of element j was stored at i * element_count + j
+fn unshuffle(typesize: usize, src: &[u8], dest: &mut [u8]) {
+    let element_count = src.len() / typesize;
+    for j in 0..element_count {
+        for i in 0..typesize {
+            dest[j * typesize + i] = src[i * element_count + j];
+        }
+    }
+    // trailing bytes that do not form a whole element are not shuffled
+    let tail = element_count * typesize;
+    dest[tail..].copy_from_slice(&src[tail..]);
+}
+
+/// reads all bytes it can, returns how many were read
+fn read_up_to(src: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match src.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+/// a reader that decompresses a stream of blosc chunks one chunk at a time
+pub struct BloscReader<'a> {
+    src: &'a mut dyn Read,
+    chunk: Vec<u8>,
+    decompressed: Vec<u8>,
+    pos: usize,
+    chunks_read: usize,
+    finished: bool,
+}
+
+impl<'a> BloscReader<'a> {
+    pub fn new(src: &'a mut dyn Read) -> BloscReader<'a> {
+        BloscReader {
+            src,
+            chunk: Vec::new(),
+            decompressed: Vec::new(),
+            pos: 0,
+            chunks_read: 0,
+            finished: false,
+        }
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        self.decompressed.clear();
+        self.pos = 0;
+
+        let mut header = [0_u8; BLOSC_HEADER_SIZE];
+        let header_len = read_up_to(self.src, &mut header)?;
+        if header_len == 0 {
+            self.finished = true;
+            return Ok(());
+        }
+        if header_len < BLOSC_HEADER_SIZE || !is_blosc_header(&header) {
+            if self.chunks_read > 0 {
+                // whatever follows the chunks (like an index) is of no interest to us
+                self.finished = true;
+                return Ok(());
+            }
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bad blosc chunk header"));
+        }
+
+        let cbytes = BloscHeader::from_bytes(&header).cbytes;
+        self.chunk.clear();
+        self.chunk.resize(cbytes, 0);
+        self.chunk[..BLOSC_HEADER_SIZE].copy_from_slice(&header);
+        self.src.read_exact(&mut self.chunk[BLOSC_HEADER_SIZE..])?;
+
+        decompress_chunk(&self.chunk, &mut self.decompressed)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("blosc chunk {}: {}", self.chunks_read, e)))?;
+        self.chunks_read += 1;
+        Ok(())
+    }
+}
+
+impl Read for BloscReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decompressed.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let len = buf.len().min(self.decompressed.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decompressed[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// compress data into a single blosclz chunk, header included
+///
+/// we do not shuffle, as bgeo stream is a mix of all kinds of types
+fn compress_chunk(data: &[u8]) -> Vec<u8> {
+    let nblocks = data.len().div_ceil(WRITER_BLOCK_SIZE);
+    let mut chunk = vec![0_u8; BLOSC_HEADER_SIZE + nblocks * 4];
+    chunk[0] = BLOSC_VERSION_FORMAT;
+    chunk[1] = BLOSC_BLOSCLZ_VERSION_FORMAT;
+    chunk[2] = (BLOSC_BLOSCLZ_FORMAT << 5) | BLOSC_DONT_SPLIT;
+    chunk[3] = 1;
+    chunk[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk[8..12].copy_from_slice(&(WRITER_BLOCK_SIZE as u32).to_le_bytes());
+
+    for (block_i, block) in data.chunks(WRITER_BLOCK_SIZE).enumerate() {
+        let bstart = chunk.len() as u32;
+        chunk[BLOSC_HEADER_SIZE + block_i * 4..BLOSC_HEADER_SIZE + block_i * 4 + 4].copy_from_slice(&bstart.to_le_bytes());
+
+        let compressed = blosclz::compress(block);
+        // decoder treats split of exactly block size as stored as is
+        let stored = if compressed.len() < block.len() { &compressed[..] } else { block };
+        chunk.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(stored);
+    }
+
+    if chunk.len() > data.len() + BLOSC_HEADER_SIZE {
+        // incompressible, fallback to memcpyed chunk
+        chunk.truncate(BLOSC_HEADER_SIZE);
+        chunk[2] |= BLOSC_MEMCPYED;
+        chunk.extend_from_slice(data);
+    }
+    let cbytes = chunk.len() as u32;
+    chunk[12..16].copy_from_slice(&cbytes.to_le_bytes());
+    chunk
+}
+
+/// a writer that compresses everything written into a stream of blosc chunks
+///
+/// finish() must be called in the end to write the last chunk
+pub struct BloscWriter<'a> {
+    dst: &'a mut dyn Write,
+    buff: Vec<u8>,
+}
+
+impl<'a> BloscWriter<'a> {
+    pub fn new(dst: &'a mut dyn Write) -> BloscWriter<'a> {
+        BloscWriter {
+            dst,
+            buff: Vec::with_capacity(WRITER_CHUNK_SIZE),
+        }
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.buff.is_empty() {
+            return Ok(());
+        }
+        self.dst.write_all(&compress_chunk(&self.buff))?;
+        self.buff.clear();
+        Ok(())
+    }
+
+    /// compress and write whatever is left in the buffer
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.dst.flush()
+    }
+}
+
+impl Write for BloscWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(WRITER_CHUNK_SIZE - self.buff.len());
+        self.buff.extend_from_slice(&buf[..len]);
+        if self.buff.len() == WRITER_CHUNK_SIZE {
+            self.write_chunk()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // we do not cut chunks on flush, that is what finish() is for
+        self.dst.flush()
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_header(flags: u8, typesize: u8, nbytes: u32, blocksize: u32, cbytes: u32) -> Vec<u8> {
+        let mut header = vec![2, 1, flags, typesize];
+        header.extend_from_slice(&nbytes.to_le_bytes());
+        header.extend_from_slice(&blocksize.to_le_bytes());
+        header.extend_from_slice(&cbytes.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn memcpyed_chunks() {
+        let mut stream = make_header(BLOSC_MEMCPYED, 1, 5, 5, 21);
+        stream.extend_from_slice(b"hello");
+        stream.extend(make_header(BLOSC_MEMCPYED, 1, 6, 6, 22));
+        stream.extend_from_slice(b" world");
+
+        let mut src = &stream[..];
+        let mut result = Vec::new();
+        BloscReader::new(&mut src).read_to_end(&mut result).expect("failed to decompress");
+        assert_eq!(b"hello world", &result[..]);
+    }
+
+    #[test]
+    fn blosclz_shuffled_chunk() {
+        // 8 u32 elements, shuffled with typesize 4 and compressed with blosclz by hand
+        let expected: Vec<u8> = (0..8_u32).flat_map(|x| x.to_le_bytes()).collect();
+        // shuffled: [0,1,2,3,4,5,6,7] then 24 zeroes
+        let mut compressed = vec![7, 0, 1, 2, 3, 4, 5, 6, 7];
+        // literal zero, then match of 23 bytes at distance 1 (len 23 = 7-1 + 14 + 3)
+        compressed.extend_from_slice(&[0, 0, 0xe0, 14, 0]);
+
+        let mut stream = make_header(BLOSC_DOSHUFFLE | BLOSC_DONT_SPLIT, 4, 32, 32, 0);
+        stream.extend_from_slice(&20_u32.to_le_bytes());
+        stream.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        stream.extend_from_slice(&compressed);
+        let cbytes = stream.len() as u32;
+        stream[12..16].copy_from_slice(&cbytes.to_le_bytes());
+
+        let mut src = &stream[..];
+        let mut result = Vec::new();
+        BloscReader::new(&mut src).read_to_end(&mut result).expect("failed to decompress");
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn writer_roundtrip() {
+        let mut data = Vec::new();
+        for i in 0..(WRITER_CHUNK_SIZE * 2 + 12345) {
+            data.push(((i * 7) % 251) as u8 ^ (i / 4096) as u8);
+        }
+        let mut compressed = Vec::new();
+        let mut writer = BloscWriter::new(&mut compressed);
+        writer.write_all(&data).expect("failed to compress");
+        writer.finish().expect("failed to compress");
+        assert!(compressed.len() < data.len());
+
+        let mut src = &compressed[..];
+        let mut result = Vec::new();
+        BloscReader::new(&mut src).read_to_end(&mut result).expect("failed to decompress");
+        assert_eq!(data, result);
+    }
+
+    #[test]
+    fn lz4_chunk() {
+        let expected = b"abcabcabcabcabcX";
+        // 3 literals, match of 12 at distance 3, then 1 literal
+        let compressed = [0x38, b'a', b'b', b'c', 3, 0, 0x10, b'X'];
+
+        let mut stream = make_header(BLOSC_LZ4_FORMAT << 5, 1, 16, 16, 0);
+        stream.extend_from_slice(&20_u32.to_le_bytes());
+        stream.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        stream.extend_from_slice(&compressed);
+        let cbytes = stream.len() as u32;
+        stream[12..16].copy_from_slice(&cbytes.to_le_bytes());
+
+        let mut src = &stream[..];
+        let mut result = Vec::new();
+        BloscReader::new(&mut src).read_to_end(&mut result).expect("failed to decompress");
+        assert_eq!(&expected[..], &result[..]);
+    }
+}
@@ -0,0 +1,215 @@
+// blosclz is a fastlz derivative used as the default codec by c-blosc
+
+const MAX_DISTANCE: usize = 8191;
+
+/// decompress a single blosclz stream into output
+///
+/// output must already have the exact expected decompressed size
+pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<(), String> {
+    if input.is_empty() {
+        return Err("empty blosclz stream".to_owned());
+    }
+    let mut ip = 0;
+    let mut op = 0;
+    // first instruction is always a literal run, upper bits are reserved
+    let mut ctrl = (input[ip] & 31) as usize;
+    ip += 1;
+
+    loop {
+        if ctrl >= 32 {
+            // back reference
+            let mut len = (ctrl >> 5) - 1;
+            let mut ofs = (ctrl & 31) << 8;
+            if len == 7 - 1 {
+                loop {
+                    let code = *input.get(ip).ok_or("blosclz stream truncated in match length")?;
+                    ip += 1;
+                    len += code as usize;
+                    if code != 255 {
+                        break;
+                    }
+                }
+            }
+            let code = *input.get(ip).ok_or("blosclz stream truncated in match distance")? as usize;
+            ip += 1;
+            len += 3;
+            let mut distance = ofs + code + 1;
+
+            // far distance is marked by saturated short distance
+            if code == 255 && ofs == 31 << 8 {
+                if ip + 1 >= input.len() {
+                    return Err("blosclz stream truncated in far match distance".to_owned());
+                }
+                ofs = (input[ip] as usize) << 8 | input[ip + 1] as usize;
+                ip += 2;
+                distance = ofs + MAX_DISTANCE + 1;
+            }
+
+            if distance > op {
+                return Err(format!("blosclz match distance {} goes before stream start", distance));
+            }
+            if op + len > output.len() {
+                return Err("blosclz match overflows output".to_owned());
+            }
+            // matches may overlap with the bytes being written, so copy byte by byte
+            let from = op - distance;
+            for i in 0..len {
+                output[op + i] = output[from + i];
+            }
+            op += len;
+        } else {
+            // literal run
+            let len = ctrl + 1;
+            if op + len > output.len() {
+                return Err("blosclz literal run overflows output".to_owned());
+            }
+            if ip + len > input.len() {
+                return Err("blosclz stream truncated in literal run".to_owned());
+            }
+            output[op..op + len].copy_from_slice(&input[ip..ip + len]);
+            op += len;
+            ip += len;
+        }
+
+        if ip >= input.len() {
+            break;
+        }
+        ctrl = input[ip] as usize;
+        ip += 1;
+    }
+
+    if op != output.len() {
+        return Err(format!("blosclz stream decompressed into {} bytes, {} expected", op, output.len()));
+    }
+    Ok(())
+}
+
+const MAX_COPY: usize = 32;
+const MAX_FAR_DISTANCE: usize = 65535 + MAX_DISTANCE + 1;
+const HASH_LOG: u32 = 14;
+const MIN_MATCH: usize = 4;
+// real blosclz decoders expect stream to end with literals
+const TAIL_LITERALS: usize = 4;
+
+fn hash(bytes: &[u8]) -> usize {
+    let x = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (x.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+fn write_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for run in literals.chunks(MAX_COPY) {
+        output.push((run.len() - 1) as u8);
+        output.extend_from_slice(run);
+    }
+}
+
+fn write_match(output: &mut Vec<u8>, distance: usize, match_len: usize) {
+    let dist = distance - 1;
+    let len = match_len - 2;
+    let (ofs_high, code, far) = if dist < MAX_DISTANCE {
+        ((dist >> 8) as u8, (dist & 0xff) as u8, None)
+    } else {
+        (31, 255, Some(dist - MAX_DISTANCE))
+    };
+
+    if len < 7 {
+        output.push(((len as u8) << 5) | ofs_high);
+    } else {
+        output.push((7 << 5) | ofs_high);
+        let mut remaining = len - 7;
+        while remaining >= 255 {
+            output.push(255);
+            remaining -= 255;
+        }
+        output.push(remaining as u8);
+    }
+    output.push(code);
+    if let Some(far) = far {
+        output.push((far >> 8) as u8);
+        output.push((far & 0xff) as u8);
+    }
+}
+
+/// compress input into a single blosclz stream
+///
+/// there is no guarantee that result is smaller than input, caller has to check that
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2);
+    if input.len() < MIN_MATCH + TAIL_LITERALS + 1 {
+        write_literals(&mut output, input);
+        return output;
+    }
+
+    let mut hash_table = vec![usize::MAX; 1 << HASH_LOG];
+    let match_limit = input.len() - TAIL_LITERALS;
+    let mut anchor = 0;
+    // first instruction must be a literal run
+    let mut ip = 1;
+
+    while ip + MIN_MATCH <= match_limit {
+        let h = hash(&input[ip..]);
+        let candidate = hash_table[h];
+        hash_table[h] = ip;
+
+        if candidate == usize::MAX
+            || ip - candidate > MAX_FAR_DISTANCE
+            || input[candidate..candidate + MIN_MATCH] != input[ip..ip + MIN_MATCH]
+        {
+            ip += 1;
+            continue;
+        }
+
+        let mut match_len = MIN_MATCH;
+        while ip + match_len < match_limit && input[candidate + match_len] == input[ip + match_len] {
+            match_len += 1;
+        }
+
+        write_literals(&mut output, &input[anchor..ip]);
+        write_match(&mut output, ip - candidate, match_len);
+        ip += match_len;
+        anchor = ip;
+    }
+    write_literals(&mut output, &input[anchor..]);
+
+    output
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(data: &[u8]) {
+        let compressed = compress(data);
+        let mut decompressed = vec![0_u8; data.len()];
+        decompress(&compressed, &mut decompressed).expect("failed to decompress");
+        assert_eq!(data, &decompressed[..]);
+    }
+
+    #[test]
+    fn compress_roundtrip() {
+        roundtrip(b"a");
+        roundtrip(b"abcabcabcabcabcabcabcabc");
+        roundtrip(&[0_u8; 100000]);
+
+        // pseudo random data with repetitions near and far
+        let mut state = 12345_u32;
+        let mut data = Vec::new();
+        for i in 0..200000 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            data.push((state >> 16) as u8);
+            if i % 1000 == 999 {
+                let from = data.len() - (state as usize % data.len().min(70000)) - 1;
+                let repeat = data[from..(from + 300).min(data.len())].to_vec();
+                data.extend(repeat);
+            }
+        }
+        roundtrip(&data);
+        assert!(compress(&data).len() < data.len());
+    }
+}
@@ -0,0 +1,343 @@
+// raw deflate stream encoder (rfc1951)
+//
+// greedy lz77 with hash chains, every block gets its own dynamic huffman codes,
+// or is stored as is if that happens to be smaller
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::deflate_tables::*;
+
+/// max amount of input bytes in one block, so that it can always fall back to a stored block
+pub const MAX_BLOCK_INPUT: usize = 65535;
+
+const HASH_LOG: u32 = 15;
+const MAX_CHAIN: usize = 48;
+const NONE: u32 = u32::MAX;
+
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+
+enum LzToken {
+    Literal(u8),
+    Match { len: u16, dist: u16 },
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, n: u32) {
+        self.bits |= (value as u64) << self.bit_count;
+        self.bit_count += n;
+        while self.bit_count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bit_count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.bits = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// huffman code lengths for given symbol frequencies
+fn huffman_lengths(freqs: &[u64]) -> Vec<u8> {
+    let mut heap = BinaryHeap::new();
+    let mut parents: Vec<usize> = vec![usize::MAX; freqs.len()];
+    for (symbol, &freq) in freqs.iter().enumerate() {
+        if freq > 0 {
+            heap.push(Reverse((freq, symbol)));
+        }
+    }
+    while heap.len() > 1 {
+        let Reverse((freq_a, node_a)) = heap.pop().expect("impossible!");
+        let Reverse((freq_b, node_b)) = heap.pop().expect("impossible!");
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[node_a] = node;
+        parents[node_b] = node;
+        heap.push(Reverse((freq_a + freq_b, node)));
+    }
+
+    // children always have smaller index than their parent, root is last
+    let mut depths = vec![0_u8; parents.len()];
+    for node in (0..parents.len()).rev() {
+        if parents[node] != usize::MAX {
+            depths[node] = depths[parents[node]] + 1;
+        }
+    }
+    depths.truncate(freqs.len());
+    depths
+}
+
+/// huffman code lengths limited by max_len
+///
+/// we always produce a complete code of at least 2 symbols, even if freqs say otherwise,
+/// that keeps all decoders happy
+fn build_lengths(freqs: &[u64], max_len: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+    for symbol in 0..2 {
+        if freqs.iter().filter(|&&f| f > 0).count() < 2 && freqs[symbol] == 0 {
+            freqs[symbol] = 1;
+        }
+    }
+    loop {
+        let lengths = huffman_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+        // flatten distribution until tree fits, it converges to a balanced tree
+        for f in freqs.iter_mut().filter(|f| **f > 0) {
+            *f = f.div_ceil(2);
+        }
+    }
+}
+
+/// run length encode code lengths with code length symbols 16, 17, 18
+/// produces (symbol, extra bits value, extra bits count)
+fn rle_code_lengths(lengths: &[u8]) -> Vec<(u8, u8, u8)> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let len = lengths[i];
+        let mut run = 1;
+        while i + run < lengths.len() && lengths[i + run] == len {
+            run += 1;
+        }
+        i += run;
+
+        if len == 0 {
+            while run >= 11 {
+                let r = run.min(138);
+                result.push((18, (r - 11) as u8, 7));
+                run -= r;
+            }
+            if run >= 3 {
+                result.push((17, (run - 3) as u8, 3));
+                run = 0;
+            }
+        } else {
+            result.push((len, 0, 0));
+            run -= 1;
+            while run >= 3 {
+                let r = run.min(6);
+                result.push((16, (r - 3) as u8, 2));
+                run -= r;
+            }
+        }
+        for _ in 0..run {
+            result.push((len, 0, 0));
+        }
+    }
+    result
+}
+
+fn hash3(buf: &[u8], pos: usize) -> usize {
+    let x = (buf[pos] as u32) << 16 | (buf[pos + 1] as u32) << 8 | buf[pos + 2] as u32;
+    (x.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+fn hash_insert(buf: &[u8], pos: usize, head: &mut [u32], prev: &mut [u32]) {
+    if pos + MIN_MATCH <= buf.len() {
+        let h = hash3(buf, pos);
+        prev[pos] = head[h];
+        head[h] = pos as u32;
+    }
+}
+
+/// raw deflate encoder, keeps window between blocks
+pub struct Deflater {
+    history: Vec<u8>,
+    writer: BitWriter,
+}
+
+impl Deflater {
+    pub fn new() -> Deflater {
+        Deflater {
+            history: Vec::new(),
+            writer: BitWriter {
+                out: Vec::new(),
+                bits: 0,
+                bit_count: 0,
+            },
+        }
+    }
+
+    /// take compressed bytes produced so far
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.writer.out)
+    }
+
+    fn find_matches(&self, buf: &[u8], start: usize) -> Vec<LzToken> {
+        let mut tokens = Vec::with_capacity(buf.len() - start);
+        let mut head = vec![NONE; 1 << HASH_LOG];
+        let mut prev = vec![NONE; buf.len()];
+
+        for pos in 0..start {
+            hash_insert(buf, pos, &mut head, &mut prev);
+        }
+
+        let mut pos = start;
+        while pos < buf.len() {
+            let max_len = MAX_MATCH.min(buf.len() - pos);
+            let mut best_len = 0;
+            let mut best_dist = 0;
+            if max_len >= MIN_MATCH {
+                let mut candidate = head[hash3(buf, pos)];
+                let mut chain = 0;
+                while candidate != NONE && pos - (candidate as usize) <= WINDOW_SIZE && chain < MAX_CHAIN {
+                    let cand = candidate as usize;
+                    let mut len = 0;
+                    while len < max_len && buf[cand + len] == buf[pos + len] {
+                        len += 1;
+                    }
+                    if len > best_len {
+                        best_len = len;
+                        best_dist = pos - cand;
+                        if len == max_len {
+                            break;
+                        }
+                    }
+                    candidate = prev[cand];
+                    chain += 1;
+                }
+            }
+
+            if best_len >= MIN_MATCH {
+                tokens.push(LzToken::Match {
+                    len: best_len as u16,
+                    dist: best_dist as u16,
+                });
+                for p in pos..pos + best_len {
+                    hash_insert(buf, p, &mut head, &mut prev);
+                }
+                pos += best_len;
+            } else {
+                tokens.push(LzToken::Literal(buf[pos]));
+                hash_insert(buf, pos, &mut head, &mut prev);
+                pos += 1;
+            }
+        }
+        tokens
+    }
+
+    /// compress one block of data, data must not be longer than MAX_BLOCK_INPUT
+    pub fn compress_block(&mut self, data: &[u8], last: bool) {
+        let start = self.history.len();
+        let mut buf = std::mem::take(&mut self.history);
+        buf.extend_from_slice(data);
+        let tokens = self.find_matches(&buf, start);
+
+        let mut lit_freqs = [0_u64; 286];
+        let mut dist_freqs = [0_u64; 30];
+        lit_freqs[256] = 1;
+        for token in tokens.iter() {
+            match token {
+                LzToken::Literal(x) => lit_freqs[*x as usize] += 1,
+                LzToken::Match { len, dist } => {
+                    lit_freqs[257 + length_index(*len as usize)] += 1;
+                    dist_freqs[dist_index(*dist as usize)] += 1;
+                }
+            }
+        }
+        let lit_lengths = build_lengths(&lit_freqs, MAX_CODE_LENGTH);
+        let dist_lengths = build_lengths(&dist_freqs, MAX_CODE_LENGTH);
+        let hlit = 257.max(lit_lengths.iter().rposition(|&x| x != 0).unwrap_or(0) + 1);
+        let hdist = 1.max(dist_lengths.iter().rposition(|&x| x != 0).unwrap_or(0) + 1);
+
+        let mut all_lengths = lit_lengths[..hlit].to_vec();
+        all_lengths.extend_from_slice(&dist_lengths[..hdist]);
+        let rle = rle_code_lengths(&all_lengths);
+        let mut cl_freqs = [0_u64; 19];
+        for (symbol, _, _) in rle.iter() {
+            cl_freqs[*symbol as usize] += 1;
+        }
+        let cl_lengths = build_lengths(&cl_freqs, MAX_CODE_LENGTH_CODE_LENGTH);
+        let hclen = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&i| cl_lengths[i] != 0).unwrap_or(0) + 1);
+
+        // estimate block sizes to choose between dynamic and stored block
+        let mut dynamic_bits = 3 + 14 + 3 * hclen as u64 + lit_lengths[256] as u64;
+        for (symbol, _, extra) in rle.iter() {
+            dynamic_bits += (cl_lengths[*symbol as usize] + extra) as u64;
+        }
+        for token in tokens.iter() {
+            dynamic_bits += match token {
+                LzToken::Literal(x) => lit_lengths[*x as usize] as u64,
+                LzToken::Match { len, dist } => {
+                    let li = length_index(*len as usize);
+                    let di = dist_index(*dist as usize);
+                    (lit_lengths[257 + li] + LENGTH_EXTRA[li] + dist_lengths[di] + DIST_EXTRA[di]) as u64
+                }
+            };
+        }
+        let stored_bits = 3 + 7 + 32 + 8 * data.len() as u64;
+
+        let w = &mut self.writer;
+        w.write(last as u32, 1);
+        if stored_bits <= dynamic_bits {
+            w.write(0, 2);
+            w.align_to_byte();
+            w.write(data.len() as u32, 16);
+            w.write(!(data.len() as u32) & 0xffff, 16);
+            w.out.extend_from_slice(data);
+        } else {
+            w.write(2, 2);
+            w.write((hlit - 257) as u32, 5);
+            w.write((hdist - 1) as u32, 5);
+            w.write((hclen - 4) as u32, 4);
+            for &i in CODE_LENGTH_ORDER[..hclen].iter() {
+                w.write(cl_lengths[i] as u32, 3);
+            }
+            let cl_codes = reversed_codes(&cl_lengths);
+            for (symbol, extra_value, extra) in rle.iter() {
+                let s = *symbol as usize;
+                w.write(cl_codes[s], cl_lengths[s] as u32);
+                w.write(*extra_value as u32, *extra as u32);
+            }
+
+            let lit_codes = reversed_codes(&lit_lengths);
+            let dist_codes = reversed_codes(&dist_lengths);
+            for token in tokens.iter() {
+                match token {
+                    LzToken::Literal(x) => {
+                        w.write(lit_codes[*x as usize], lit_lengths[*x as usize] as u32);
+                    }
+                    LzToken::Match { len, dist } => {
+                        let li = length_index(*len as usize);
+                        let di = dist_index(*dist as usize);
+                        w.write(lit_codes[257 + li], lit_lengths[257 + li] as u32);
+                        w.write((*len - LENGTH_BASE[li]) as u32, LENGTH_EXTRA[li] as u32);
+                        w.write(dist_codes[di], dist_lengths[di] as u32);
+                        w.write((*dist - DIST_BASE[di]) as u32, DIST_EXTRA[di] as u32);
+                    }
+                }
+            }
+            w.write(lit_codes[256], lit_lengths[256] as u32);
+        }
+        if last {
+            w.align_to_byte();
+        }
+
+        // keep the window for the next block
+        let keep_from = buf.len().saturating_sub(WINDOW_SIZE);
+        buf.drain(..keep_from);
+        self.history = buf;
+    }
+}
+
+fn reversed_codes(lengths: &[u8]) -> Vec<u32> {
+    canonical_codes(lengths)
+        .iter()
+        .zip(lengths.iter())
+        .map(|(&code, &len)| if len == 0 { 0 } else { reverse_bits(code, len as u32) })
+        .collect()
+}
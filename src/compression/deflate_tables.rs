@@ -0,0 +1,73 @@
+// constants and helpers shared by deflate encoder and decoder
+
+pub const WINDOW_SIZE: usize = 32768;
+pub const MIN_MATCH: usize = 3;
+pub const MAX_MATCH: usize = 258;
+
+pub const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+pub const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+pub const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+/// order in which code length code lengths are stored in dynamic block header
+pub const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// code lengths of fixed huffman block, literal/length and distance ones
+pub fn fixed_code_lengths() -> ([u8; 288], [u8; 30]) {
+    let mut lit = [0_u8; 288];
+    lit[..144].fill(8);
+    lit[144..256].fill(9);
+    lit[256..280].fill(7);
+    lit[280..].fill(8);
+    (lit, [5_u8; 30])
+}
+
+/// assign canonical huffman codes (msb-first) to given code lengths
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut bl_count = [0_u32; 16];
+    for &len in lengths {
+        bl_count[len as usize] += 1;
+    }
+    bl_count[0] = 0;
+
+    let mut next_code = [0_u32; 16];
+    let mut code = 0;
+    for bits in 1..16 {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+/// deflate stores huffman codes starting from msb, while everything else is lsb first
+pub fn reverse_bits(code: u32, len: u32) -> u32 {
+    code.reverse_bits() >> (32 - len)
+}
+
+/// find length symbol index (symbol - 257) for a match length
+pub fn length_index(len: usize) -> usize {
+    LENGTH_BASE.partition_point(|&base| base as usize <= len) - 1
+}
+
+/// find distance symbol for a match distance
+pub fn dist_index(dist: usize) -> usize {
+    DIST_BASE.partition_point(|&base| base as usize <= dist) - 1
+}
@@ -0,0 +1,276 @@
+// gzip container (rfc1952) around raw deflate streams
+
+use std::io::{self, Read, Write};
+
+use super::deflate::{Deflater, MAX_BLOCK_INPUT};
+use super::inflate::{BitReader, Inflater};
+
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+const GZIP_METHOD_DEFLATE: u8 = 8;
+const GZIP_FLAG_HCRC: u8 = 0x02;
+const GZIP_FLAG_EXTRA: u8 = 0x04;
+const GZIP_FLAG_NAME: u8 = 0x08;
+const GZIP_FLAG_COMMENT: u8 = 0x10;
+const GZIP_OS_UNKNOWN: u8 = 255;
+
+const CRC32_TABLE: [u32; 256] = make_crc32_table();
+
+const fn make_crc32_table() -> [u32; 256] {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &byte in data {
+        c = CRC32_TABLE[((c ^ byte as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+fn read_u32_le(bits: &mut BitReader) -> io::Result<u32> {
+    let mut value = 0_u32;
+    for i in 0..4 {
+        value |= (bits.byte()? as u32) << (8 * i);
+    }
+    Ok(value)
+}
+
+fn read_member_header(bits: &mut BitReader) -> io::Result<()> {
+    if [bits.byte()?, bits.byte()?] != GZIP_MAGIC {
+        return Err(invalid_data("bad gzip magic"));
+    }
+    if bits.byte()? != GZIP_METHOD_DEFLATE {
+        return Err(invalid_data("unsupported gzip compression method"));
+    }
+    let flags = bits.byte()?;
+    // mtime, extra flags, os
+    for _ in 0..6 {
+        bits.byte()?;
+    }
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let xlen = bits.byte()? as usize | (bits.byte()? as usize) << 8;
+        for _ in 0..xlen {
+            bits.byte()?;
+        }
+    }
+    for flag in [GZIP_FLAG_NAME, GZIP_FLAG_COMMENT] {
+        if flags & flag != 0 {
+            while bits.byte()? != 0 {}
+        }
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        bits.byte()?;
+        bits.byte()?;
+    }
+    Ok(())
+}
+
+/// a reader that decompresses gzip stream, multi-member streams are supported
+pub struct GzipReader<'a> {
+    inflater: Inflater<'a>,
+    header_read: bool,
+    crc: u32,
+    size: u32,
+    finished: bool,
+}
+
+impl<'a> GzipReader<'a> {
+    pub fn new(src: &'a mut dyn Read) -> GzipReader<'a> {
+        GzipReader {
+            inflater: Inflater::new(BitReader::new(src)),
+            header_read: false,
+            crc: 0,
+            size: 0,
+            finished: false,
+        }
+    }
+
+    fn finish_member(&mut self) -> io::Result<()> {
+        let bits = &mut self.inflater.bits;
+        bits.align_to_byte();
+        let crc = read_u32_le(bits)?;
+        let size = read_u32_le(bits)?;
+        if crc != self.crc || size != self.size {
+            return Err(invalid_data("gzip checksum mismatch"));
+        }
+        self.finished = bits.at_end()?;
+        self.header_read = false;
+        self.crc = 0;
+        self.size = 0;
+        self.inflater.restart();
+        Ok(())
+    }
+}
+
+impl Read for GzipReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.finished {
+            if !self.header_read {
+                read_member_header(&mut self.inflater.bits)?;
+                self.header_read = true;
+            }
+            let len = self.inflater.read(buf)?;
+            if len > 0 {
+                self.crc = crc32_update(self.crc, &buf[..len]);
+                self.size = self.size.wrapping_add(len as u32);
+                return Ok(len);
+            }
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            if self.inflater.is_done() {
+                self.finish_member()?;
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// a writer that compresses everything written into a gzip stream
+///
+/// finish() must be called in the end to write the last block and gzip trailer
+pub struct GzipWriter<'a> {
+    dst: &'a mut dyn Write,
+    deflater: Deflater,
+    buff: Vec<u8>,
+    header_written: bool,
+    crc: u32,
+    size: u32,
+}
+
+impl<'a> GzipWriter<'a> {
+    pub fn new(dst: &'a mut dyn Write) -> GzipWriter<'a> {
+        GzipWriter {
+            dst,
+            deflater: Deflater::new(),
+            buff: Vec::with_capacity(MAX_BLOCK_INPUT),
+            header_written: false,
+            crc: 0,
+            size: 0,
+        }
+    }
+
+    fn write_block(&mut self, last: bool) -> io::Result<()> {
+        if !self.header_written {
+            self.dst.write_all(&GZIP_MAGIC)?;
+            self.dst.write_all(&[GZIP_METHOD_DEFLATE, 0, 0, 0, 0, 0, 0, GZIP_OS_UNKNOWN])?;
+            self.header_written = true;
+        }
+        self.deflater.compress_block(&self.buff, last);
+        self.buff.clear();
+        self.dst.write_all(&self.deflater.take_output())
+    }
+
+    /// compress and write whatever is left in the buffer, and gzip trailer
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_block(true)?;
+        self.dst.write_all(&self.crc.to_le_bytes())?;
+        self.dst.write_all(&self.size.to_le_bytes())?;
+        self.dst.flush()
+    }
+}
+
+impl Write for GzipWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc = crc32_update(self.crc, buf);
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let len = rest.len().min(MAX_BLOCK_INPUT - self.buff.len());
+            self.buff.extend_from_slice(&rest[..len]);
+            rest = &rest[len..];
+            if self.buff.len() == MAX_BLOCK_INPUT {
+                self.write_block(false)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // we do not cut blocks on flush, that is what finish() is for
+        self.dst.flush()
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        let mut writer = GzipWriter::new(&mut compressed);
+        writer.write_all(data).expect("failed to compress");
+        writer.finish().expect("failed to compress");
+        compressed
+    }
+
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut src = data;
+        let mut result = Vec::new();
+        GzipReader::new(&mut src).read_to_end(&mut result).expect("failed to decompress");
+        result
+    }
+
+    #[test]
+    fn crc32() {
+        assert_eq!(0xcbf43926, crc32_update(0, b"123456789"));
+    }
+
+    #[test]
+    fn known_stream() {
+        // `echo hello | gzip -n`
+        let stream = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0xe7, 0x02, 0x00, 0x20,
+            0x30, 0x3a, 0x36, 0x06, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(b"hello\n", &decompress(&stream)[..]);
+
+        // two members concatenated
+        let mut two = stream.to_vec();
+        two.extend_from_slice(&stream);
+        assert_eq!(b"hello\nhello\n", &decompress(&two)[..]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        assert_eq!(b"", &decompress(&compress(b""))[..]);
+        assert_eq!(b"a", &decompress(&compress(b"a"))[..]);
+
+        let mut data = Vec::new();
+        let mut state = 1_u32;
+        for i in 0..300000_u32 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            if i % 3 == 0 {
+                data.extend_from_slice(format!("{:.3},", (state >> 20) as f32 / 7.0).as_bytes());
+            } else {
+                data.push((state >> 24) as u8);
+            }
+        }
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(data, decompress(&compressed));
+    }
+}
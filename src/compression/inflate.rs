@@ -0,0 +1,339 @@
+// raw deflate stream decoder (rfc1951), output is produced lazily as it is read
+
+use std::io::{self, Read};
+
+use super::deflate_tables::*;
+
+const INPUT_BUFFER_SIZE: usize = 1 << 16;
+// amount of output we decode at once when reader asks for more
+const OUTPUT_STEP: usize = 1 << 16;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+/// lsb-first bit reader on top of a byte stream
+pub struct BitReader<'a> {
+    src: &'a mut dyn Read,
+    buff: Vec<u8>,
+    buff_pos: usize,
+    bits: u64,
+    bit_count: u32,
+    // zero bits we had to pad with at the end of stream to be able to peek
+    padded_bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(src: &'a mut dyn Read) -> BitReader<'a> {
+        BitReader {
+            src,
+            buff: Vec::with_capacity(INPUT_BUFFER_SIZE),
+            buff_pos: 0,
+            bits: 0,
+            bit_count: 0,
+            padded_bits: 0,
+        }
+    }
+
+    /// get next input byte, None on end of stream
+    fn next_input_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.buff_pos == self.buff.len() {
+            self.buff.resize(INPUT_BUFFER_SIZE, 0);
+            let read = loop {
+                match self.src.read(&mut self.buff) {
+                    Ok(n) => break n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            self.buff.truncate(read);
+            self.buff_pos = 0;
+            if read == 0 {
+                return Ok(None);
+            }
+        }
+        self.buff_pos += 1;
+        Ok(Some(self.buff[self.buff_pos - 1]))
+    }
+
+    /// make sure at least n bits are available, pads with zeroes after the end of stream
+    fn fill(&mut self, n: u32) -> io::Result<()> {
+        while self.bit_count < n {
+            let byte = match self.next_input_byte()? {
+                Some(x) => x,
+                None => {
+                    self.padded_bits += 8;
+                    0
+                }
+            };
+            self.bits |= (byte as u64) << self.bit_count;
+            self.bit_count += 8;
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self, n: u32) -> io::Result<u32> {
+        self.fill(n)?;
+        Ok((self.bits & ((1_u64 << n) - 1)) as u32)
+    }
+
+    fn consume(&mut self, n: u32) -> io::Result<()> {
+        if self.bit_count < n + self.padded_bits {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "deflate stream is truncated"));
+        }
+        self.bits >>= n;
+        self.bit_count -= n;
+        Ok(())
+    }
+
+    pub fn bits(&mut self, n: u32) -> io::Result<u32> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let x = self.peek(n)?;
+        self.consume(n)?;
+        Ok(x)
+    }
+
+    /// drop bits up to the next byte boundary
+    pub fn align_to_byte(&mut self) {
+        let extra = self.bit_count % 8;
+        self.bits >>= extra;
+        self.bit_count -= extra;
+    }
+
+    /// read a whole byte, stream must be byte aligned
+    pub fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.bits(8)? as u8)
+    }
+
+    /// check if there is no more input, stream must be byte aligned
+    pub fn at_end(&mut self) -> io::Result<bool> {
+        if self.bit_count > self.padded_bits {
+            return Ok(false);
+        }
+        match self.next_input_byte()? {
+            Some(x) => {
+                self.bits = x as u64;
+                self.bit_count = 8;
+                self.padded_bits = 0;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+/// canonical huffman decoding table, indexed by next max_len bits of the stream
+struct HuffmanTable {
+    // symbol << 4 | code length, zero length means invalid code
+    entries: Vec<u16>,
+    max_len: u32,
+}
+
+impl HuffmanTable {
+    fn new(lengths: &[u8]) -> io::Result<HuffmanTable> {
+        let max_len = *lengths.iter().max().unwrap_or(&0) as u32;
+        let mut entries = vec![0_u16; 1 << max_len];
+        if max_len == 0 {
+            return Ok(HuffmanTable { entries, max_len });
+        }
+
+        let codes = canonical_codes(lengths);
+        for (symbol, (&len, &code)) in lengths.iter().zip(codes.iter()).enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = len as u32;
+            if code >> len != 0 {
+                return Err(invalid_data("oversubscribed huffman code"));
+            }
+            let reversed = reverse_bits(code, len);
+            let entry = (symbol as u16) << 4 | len as u16;
+            for fill in 0..(1_u32 << (max_len - len)) {
+                entries[(reversed | fill << len) as usize] = entry;
+            }
+        }
+        Ok(HuffmanTable { entries, max_len })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> io::Result<usize> {
+        let entry = self.entries[bits.peek(self.max_len)? as usize];
+        let len = (entry & 0x0f) as u32;
+        if len == 0 {
+            return Err(invalid_data("invalid huffman code in deflate stream"));
+        }
+        bits.consume(len)?;
+        Ok((entry >> 4) as usize)
+    }
+}
+
+enum BlockState {
+    Header,
+    Stored(usize),
+    Huffman(HuffmanTable, HuffmanTable),
+    Done,
+}
+
+/// raw deflate decoder
+pub struct Inflater<'a> {
+    pub bits: BitReader<'a>,
+    state: BlockState,
+    last_block: bool,
+    // decoded output, including up to WINDOW_SIZE of already consumed history
+    window: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> Inflater<'a> {
+    pub fn new(bits: BitReader<'a>) -> Inflater<'a> {
+        Inflater {
+            bits,
+            state: BlockState::Header,
+            last_block: false,
+            window: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, BlockState::Done) && self.pos == self.window.len()
+    }
+
+    /// start decoding next deflate stream, keeping the bit reader
+    pub fn restart(&mut self) {
+        self.state = BlockState::Header;
+        self.last_block = false;
+        self.window.clear();
+        self.pos = 0;
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        if self.last_block {
+            self.state = BlockState::Done;
+            return Ok(());
+        }
+        self.last_block = self.bits.bits(1)? == 1;
+        self.state = match self.bits.bits(2)? {
+            0 => {
+                self.bits.align_to_byte();
+                let len = self.bits.bits(16)?;
+                let nlen = self.bits.bits(16)?;
+                if len != !nlen & 0xffff {
+                    return Err(invalid_data("stored deflate block length mismatch"));
+                }
+                BlockState::Stored(len as usize)
+            }
+            1 => {
+                let (lit_lengths, dist_lengths) = fixed_code_lengths();
+                BlockState::Huffman(HuffmanTable::new(&lit_lengths)?, HuffmanTable::new(&dist_lengths)?)
+            }
+            2 => self.read_dynamic_tables()?,
+            _ => {
+                return Err(invalid_data("invalid deflate block type"));
+            }
+        };
+        Ok(())
+    }
+
+    fn read_dynamic_tables(&mut self) -> io::Result<BlockState> {
+        let hlit = self.bits.bits(5)? as usize + 257;
+        let hdist = self.bits.bits(5)? as usize + 1;
+        let hclen = self.bits.bits(4)? as usize + 4;
+
+        let mut code_length_lengths = [0_u8; 19];
+        for &i in CODE_LENGTH_ORDER[..hclen].iter() {
+            code_length_lengths[i] = self.bits.bits(3)? as u8;
+        }
+        let code_length_table = HuffmanTable::new(&code_length_lengths)?;
+
+        let mut lengths = Vec::with_capacity(hlit + hdist);
+        while lengths.len() < hlit + hdist {
+            let (value, repeat) = match code_length_table.decode(&mut self.bits)? {
+                x @ 0..=15 => (x as u8, 1),
+                16 => {
+                    let prev = *lengths.last().ok_or_else(|| invalid_data("repeat of no code length"))?;
+                    (prev, 3 + self.bits.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.bits.bits(3)? as usize),
+                _ => (0, 11 + self.bits.bits(7)? as usize),
+            };
+            if lengths.len() + repeat > hlit + hdist {
+                return Err(invalid_data("too many code lengths in deflate block"));
+            }
+            lengths.extend(std::iter::repeat_n(value, repeat));
+        }
+        if lengths[256] == 0 {
+            return Err(invalid_data("deflate block has no end of block code"));
+        }
+
+        Ok(BlockState::Huffman(HuffmanTable::new(&lengths[..hlit])?, HuffmanTable::new(&lengths[hlit..])?))
+    }
+
+    /// decode at least OUTPUT_STEP more bytes, or until the end of stream
+    fn decode_some(&mut self) -> io::Result<()> {
+        let target = self.window.len() + OUTPUT_STEP;
+        while self.window.len() < target {
+            match &mut self.state {
+                BlockState::Done => break,
+                BlockState::Header => self.read_block_header()?,
+                BlockState::Stored(remaining) => {
+                    if *remaining == 0 {
+                        self.state = BlockState::Header;
+                        continue;
+                    }
+                    *remaining -= 1;
+                    let byte = self.bits.byte()?;
+                    self.window.push(byte);
+                }
+                BlockState::Huffman(lit_table, dist_table) => {
+                    let symbol = lit_table.decode(&mut self.bits)?;
+                    if symbol < 256 {
+                        self.window.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        self.state = BlockState::Header;
+                        continue;
+                    }
+                    let len_i = symbol - 257;
+                    if len_i >= LENGTH_BASE.len() {
+                        return Err(invalid_data("invalid length symbol in deflate stream"));
+                    }
+                    let len = LENGTH_BASE[len_i] as usize + self.bits.bits(LENGTH_EXTRA[len_i] as u32)? as usize;
+                    let dist_i = dist_table.decode(&mut self.bits)?;
+                    if dist_i >= DIST_BASE.len() {
+                        return Err(invalid_data("invalid distance symbol in deflate stream"));
+                    }
+                    let dist = DIST_BASE[dist_i] as usize + self.bits.bits(DIST_EXTRA[dist_i] as u32)? as usize;
+                    if dist > self.window.len() {
+                        return Err(invalid_data("deflate distance goes before stream start"));
+                    }
+                    let from = self.window.len() - dist;
+                    for i in 0..len {
+                        let byte = self.window[from + i];
+                        self.window.push(byte);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for Inflater<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.window.len() {
+            // drop history we will never reference again
+            if self.pos > WINDOW_SIZE {
+                self.window.drain(..self.pos - WINDOW_SIZE);
+                self.pos = WINDOW_SIZE;
+            }
+            self.decode_some()?;
+        }
+        let len = buf.len().min(self.window.len() - self.pos);
+        buf[..len].copy_from_slice(&self.window[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,75 @@
+// lz4 block format, as used inside blosc chunks (no lz4 frame headers)
+
+const MIN_MATCH: usize = 4;
+
+fn read_extended_length(input: &[u8], ip: &mut usize, mut len: usize) -> Result<usize, String> {
+    loop {
+        let byte = *input.get(*ip).ok_or("lz4 stream truncated in length")?;
+        *ip += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+/// decompress a single lz4 block into output
+///
+/// output must already have the exact expected decompressed size
+pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<(), String> {
+    let mut ip = 0;
+    let mut op = 0;
+
+    loop {
+        let token = *input.get(ip).ok_or("lz4 stream truncated")?;
+        ip += 1;
+
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 {
+            literal_len = read_extended_length(input, &mut ip, literal_len)?;
+        }
+        if ip + literal_len > input.len() {
+            return Err("lz4 stream truncated in literals".to_owned());
+        }
+        if op + literal_len > output.len() {
+            return Err("lz4 literals overflow output".to_owned());
+        }
+        output[op..op + literal_len].copy_from_slice(&input[ip..ip + literal_len]);
+        ip += literal_len;
+        op += literal_len;
+
+        // last sequence has literals only
+        if ip == input.len() {
+            break;
+        }
+
+        if ip + 1 >= input.len() {
+            return Err("lz4 stream truncated in match offset".to_owned());
+        }
+        let distance = u16::from_le_bytes([input[ip], input[ip + 1]]) as usize;
+        ip += 2;
+        if distance == 0 || distance > op {
+            return Err(format!("bad lz4 match offset {}", distance));
+        }
+
+        let mut match_len = (token & 0x0f) as usize;
+        if match_len == 15 {
+            match_len = read_extended_length(input, &mut ip, match_len)?;
+        }
+        match_len += MIN_MATCH;
+        if op + match_len > output.len() {
+            return Err("lz4 match overflows output".to_owned());
+        }
+        // matches may overlap with the bytes being written, so copy byte by byte
+        let from = op - distance;
+        for i in 0..match_len {
+            output[op + i] = output[from + i];
+        }
+        op += match_len;
+    }
+
+    if op != output.len() {
+        return Err(format!("lz4 block decompressed into {} bytes, {} expected", op, output.len()));
+    }
+    Ok(())
+}
@@ -0,0 +1,10 @@
+mod blosc;
+mod blosclz;
+mod deflate;
+mod deflate_tables;
+mod gzip;
+mod inflate;
+mod lz4;
+
+pub use blosc::{is_blosc_header, BloscReader, BloscWriter, BLOSC_HEADER_SIZE};
+pub use gzip::{GzipReader, GzipWriter, GZIP_MAGIC};
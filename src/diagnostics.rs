@@ -0,0 +1,97 @@
+// library warnings and notes go through here instead of being printed to stdout,
+// as stdout may as well be where the converted geometry is written to
+//
+// by default warnings are printed to stderr and infos are dropped,
+// callers may install their own handler to redirect or silence them
+
+use std::fmt;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticLevel {
+    /// something normal, but possibly interesting, like absence of some optional data
+    Info,
+    /// something in the input was skipped or could not be handled
+    Warning,
+}
+
+impl fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticLevel::Info => write!(f, "info"),
+            DiagnosticLevel::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+type Handler = Box<dyn Fn(DiagnosticLevel, &str) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+fn default_handler(level: DiagnosticLevel, message: &str) {
+    if level >= DiagnosticLevel::Warning {
+        eprintln!("{}: {}", level, message);
+    }
+}
+
+/// replace diagnostics handler, it is global for the whole process
+pub fn set_handler(handler: impl Fn(DiagnosticLevel, &str) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(handler));
+}
+
+/// drop all diagnostics
+pub fn silence() {
+    set_handler(|_, _| {});
+}
+
+/// go back to default handler, that prints warnings to stderr
+pub fn reset_handler() {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn emit(level: DiagnosticLevel, message: &str) {
+    match HANDLER.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(handler) => handler(level, message),
+        None => default_handler(level, message),
+    }
+}
+
+pub(crate) fn info(message: &str) {
+    emit(DiagnosticLevel::Info, message);
+}
+
+pub(crate) fn warning(message: &str) {
+    emit(DiagnosticLevel::Warning, message);
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, HoudiniGeoSchemaParser};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn custom_handler() {
+        let collected = Arc::new(Mutex::new(Vec::new()));
+        let collected_in_handler = collected.clone();
+        set_handler(move |level, message| {
+            collected_in_handler.lock().expect("poisoned").push((level, message.to_owned()));
+        });
+
+        let elem = parse(&mut std::fs::File::open("tests/box.geo").expect("failed to open test file"));
+        let mut parser = HoudiniGeoSchemaParser::new(&elem).expect("bad schema");
+        parser.parse_primitive_attributes().expect("bad schema");
+        reset_handler();
+
+        assert!(collected
+            .lock()
+            .expect("poisoned")
+            .contains(&(DiagnosticLevel::Info, "no primitiveattributes attributes!".to_owned())));
+    }
+}
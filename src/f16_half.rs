@@ -18,8 +18,18 @@ pub fn half_from_le_bytes(bytes: [u8; 2]) -> f32 {
 }
 
 
+pub fn half_to_be_bytes(val: f32) -> [u8; 2] {
+    f32_to_half_bits(val).to_be_bytes()
+}
+
+
+pub fn half_to_le_bytes(val: f32) -> [u8; 2] {
+    f32_to_half_bits(val).to_le_bytes()
+}
+
+
 fn sem_to_f32(s: u32, e: u32, m: u32) -> f32 {
-    let mut res = 0_u32;
+    let res;
     let mut e = e;
     let mut m = m;
 
@@ -27,17 +37,18 @@ fn sem_to_f32(s: u32, e: u32, m: u32) -> f32 {
         if m == 0 {
             res = s << 31;
         } else {
+            // denormalized half, renormalize it, exponent goes below what half could represent
+            let mut e_shift = 0;
             while (m & 0x0400) == 0 {
                 m <<= 1;
-                e -= 1;
+                e_shift += 1;
             }
-            e += 1;
             m &= !0x0400;
-            e += 127 - 15;
+            e = 127 - 15 + 1 - e_shift;
             m <<= 13;
             res = s << 31 | e << 23 | m;
         }
-    } else if e == 23 {
+    } else if e == 31 {
         if m == 0 {
             res = s << 31 | 0x7f800000;
         } else {
@@ -49,5 +60,88 @@ fn sem_to_f32(s: u32, e: u32, m: u32) -> f32 {
         res = s << 31 | e << 23 | m;
     }
 
-    f32::from_be_bytes(res.to_be_bytes())
+    f32::from_bits(res)
+}
+
+
+/// rounds to nearest, ties to even, same as houdini does
+///
+/// values too big for a half become infinity, values too small - zero
+fn f32_to_half_bits(val: f32) -> u16 {
+    let i = val.to_bits() as i32;
+    let s = (i >> 16) & 0x8000;
+    let mut e = ((i >> 23) & 0xff) - (127 - 15);
+    let mut m = i & 0x007fffff;
+
+    if e <= 0 {
+        if e < -10 {
+            // too small even for a denormalized half
+            return s as u16;
+        }
+        // denormalized half, add the implicit leading 1 and shift it into mantissa
+        m |= 0x00800000;
+        let t = 14 - e;
+        let a = (1 << (t - 1)) - 1;
+        let b = (m >> t) & 1;
+        m = (m + a + b) >> t;
+        (s | m) as u16
+    } else if e == 0xff - (127 - 15) {
+        if m == 0 {
+            (s | 0x7c00) as u16
+        } else {
+            // keep nan a nan, even if its mantissa bits get all shifted out
+            m >>= 13;
+            (s | 0x7c00 | m | (m == 0) as i32) as u16
+        }
+    } else {
+        m = m + 0x0fff + ((m >> 13) & 1);
+        if m & 0x00800000 != 0 {
+            // mantissa overflowed while rounding
+            m = 0;
+            e += 1;
+        }
+        if e > 30 {
+            return (s | 0x7c00) as u16;
+        }
+        (s | (e << 10) | (m >> 13)) as u16
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_encoding() {
+        assert_eq!(0x3c00, f32_to_half_bits(1.0));
+        assert_eq!(0xc000, f32_to_half_bits(-2.0));
+        assert_eq!(0x7bff, f32_to_half_bits(65504.0));
+        assert_eq!(0x7c00, f32_to_half_bits(65520.0));
+        assert_eq!(0xfc00, f32_to_half_bits(f32::NEG_INFINITY));
+        assert_eq!(0x0001, f32_to_half_bits(5.9604645e-8));
+        assert_eq!(0x0000, f32_to_half_bits(1e-8));
+        assert_eq!(0x8000, f32_to_half_bits(-0.0));
+        assert!(half_from_le_bytes(half_to_le_bytes(f32::NAN)).is_nan());
+        // ties go to even
+        assert_eq!(0x3c00, f32_to_half_bits(1.0 + 2_f32.powi(-11)));
+        assert_eq!(0x3c02, f32_to_half_bits(1.0 + 3.0 * 2_f32.powi(-11)));
+    }
+
+    #[test]
+    fn half_decoding() {
+        assert_eq!(f32::INFINITY, half_from_be_bytes([0x7c, 0x00]));
+        assert_eq!(5.9604645e-8, half_from_le_bytes([0x01, 0x00]));
+        assert_eq!(6.097555e-5, half_from_le_bytes([0xff, 0x03]));
+        for val in [0.0, 0.5, -1.25, 0.1, 3.3, 1e-5, 60000.0] {
+            let half = half_from_le_bytes(half_to_le_bytes(val));
+            assert_eq!(half, half_from_be_bytes(half_to_be_bytes(half)));
+            assert!((half - val).abs() <= val.abs() / 1024.0 + 6e-8, "{} became {}", val, half);
+        }
+    }
 }
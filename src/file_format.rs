@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::bgeo_constants::JID_MAGIC;
+use crate::compression::GZIP_MAGIC;
+
+/// geometry file formats we know about, either for reading or writing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Obj,
+    Stl,
+    Geo,
+    Bgeo,
+    BgeoSc,
+    GeoGz,
+    BgeoGz,
+}
+
+/// compound extensions go first, so that `.bgeo.sc` is not taken for something else
+const EXTENSIONS: [(&str, FileFormat); 10] = [
+    (".bgeo.sc", FileFormat::BgeoSc),
+    (".bgeo.gz", FileFormat::BgeoGz),
+    (".geo.gz", FileFormat::GeoGz),
+    (".json.gz", FileFormat::GeoGz),
+    (".bgeo", FileFormat::Bgeo),
+    (".geo", FileFormat::Geo),
+    (".json", FileFormat::Geo),
+    (".sc", FileFormat::BgeoSc),
+    (".obj", FileFormat::Obj),
+    (".stl", FileFormat::Stl),
+];
+
+impl FileFormat {
+    /// format from a type name as given on the command line, like `bgeo.sc` or `geo`
+    pub fn from_type_name(name: &str) -> Option<FileFormat> {
+        match name {
+            "obj" => Some(FileFormat::Obj),
+            "stl" => Some(FileFormat::Stl),
+            "geo" | "json" => Some(FileFormat::Geo),
+            "bgeo" => Some(FileFormat::Bgeo),
+            "bgeo.sc" | "sc" => Some(FileFormat::BgeoSc),
+            "geo.gz" | "json.gz" => Some(FileFormat::GeoGz),
+            "bgeo.gz" => Some(FileFormat::BgeoGz),
+            _ => None,
+        }
+    }
+
+    /// format from file extension, case insensitive
+    pub fn from_path(path: &str) -> Option<FileFormat> {
+        let path = path.to_ascii_lowercase();
+        EXTENSIONS
+            .iter()
+            .find(|(ext, _)| path.ends_with(ext))
+            .map(|&(_, format)| format)
+    }
+
+    /// guess format of input data from its first bytes, file path is used when content is not conclusive
+    ///
+    /// compressed streams are only distinguished by extension, as we don't look inside them here
+    pub fn sniff(head: &[u8], path: Option<&str>) -> Option<FileFormat> {
+        let by_extension = path.and_then(FileFormat::from_path);
+        let text_start = head.iter().position(|c| !c.is_ascii_whitespace()).map(|i| &head[i..]);
+
+        match (head.first(), text_start.and_then(|t| t.first())) {
+            (None, _) => by_extension,
+            (_, Some(b'[' | b'{')) => Some(FileFormat::Geo),
+            (Some(&JID_MAGIC), _) => Some(FileFormat::Bgeo),
+            _ if head.starts_with(&GZIP_MAGIC) => match by_extension {
+                Some(FileFormat::GeoGz) => Some(FileFormat::GeoGz),
+                _ => Some(FileFormat::BgeoGz),
+            },
+            // binary stl starts with an arbitrary 80 byte header, so trust the extension there
+            _ if by_extension == Some(FileFormat::Stl) => Some(FileFormat::Stl),
+            (Some(1..=4), _) => Some(FileFormat::BgeoSc),
+            _ if text_start.is_some_and(|t| t.starts_with(b"solid")) => Some(FileFormat::Stl),
+            _ if text_start.is_some_and(looks_like_obj) => Some(FileFormat::Obj),
+            _ => by_extension,
+        }
+    }
+
+    /// if we can parse this format as input
+    pub fn is_readable(&self) -> bool {
+        !matches!(self, FileFormat::Obj | FileFormat::Stl)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::Obj => "obj",
+            FileFormat::Stl => "stl",
+            FileFormat::Geo => "geo",
+            FileFormat::Bgeo => "bgeo",
+            FileFormat::BgeoSc => "bgeo.sc",
+            FileFormat::GeoGz => "geo.gz",
+            FileFormat::BgeoGz => "bgeo.gz",
+        }
+    }
+}
+
+fn looks_like_obj(text: &[u8]) -> bool {
+    ["# ", "v ", "vn ", "vt ", "o ", "g ", "f ", "mtllib ", "usemtl "]
+        .iter()
+        .any(|start| text.starts_with(start.as_bytes()))
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_path() {
+        assert_eq!(Some(FileFormat::BgeoSc), FileFormat::from_path("/tmp/cache.0001.bgeo.sc"));
+        assert_eq!(Some(FileFormat::GeoGz), FileFormat::from_path("foo.GEO.GZ"));
+        assert_eq!(Some(FileFormat::Bgeo), FileFormat::from_path("foo.bgeo"));
+        assert_eq!(Some(FileFormat::Stl), FileFormat::from_path("foo.stl"));
+        assert_eq!(None, FileFormat::from_path("foo.xxx"));
+        assert_eq!(None, FileFormat::from_path("bgeo"));
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Some(FileFormat::Geo), FileFormat::sniff(b"\n  [\"fileversion\"", Some("foo.bgeo")));
+        assert_eq!(Some(FileFormat::Bgeo), FileFormat::sniff(&[0x7f, b'N', b'S', b'J', b'b'], None));
+        assert_eq!(Some(FileFormat::BgeoSc), FileFormat::sniff(&[2, 1, 0x11, 4], None));
+        assert_eq!(Some(FileFormat::BgeoGz), FileFormat::sniff(&[0x1f, 0x8b, 8], Some("foo")));
+        assert_eq!(Some(FileFormat::GeoGz), FileFormat::sniff(&[0x1f, 0x8b, 8], Some("foo.geo.gz")));
+        assert_eq!(Some(FileFormat::Obj), FileFormat::sniff(b"# some obj\nv 1 2 3\n", None));
+        assert_eq!(Some(FileFormat::Stl), FileFormat::sniff(b"solid foo\n", None));
+        assert_eq!(Some(FileFormat::Stl), FileFormat::sniff(&[2; 80], Some("binary.stl")));
+        assert_eq!(Some(FileFormat::Bgeo), FileFormat::sniff(&[], Some("empty.bgeo")));
+    }
+}
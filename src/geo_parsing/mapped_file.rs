@@ -0,0 +1,114 @@
+// read-only memory mapped file, so that huge caches are not read into memory as a whole
+//
+// there are no dependencies to get mmap from, so it's declared here directly,
+// platforms where that is not done (or not safe to do) just read the whole file instead
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+pub struct MappedFile {
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    ptr: *mut sys::c_void,
+    #[cfg(not(all(unix, target_pointer_width = "64")))]
+    data: Vec<u8>,
+    len: usize,
+}
+
+// mapping is read only and private, so it's fine to share it between threads
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        MappedFile::map(&File::open(path)?)
+    }
+
+    /// note that file must not be changed while it's mapped, or parsed data will change with it
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    pub fn map(file: &File) -> io::Result<MappedFile> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file is too big to be mapped"))?;
+        if len == 0 {
+            // empty mappings are not allowed
+            return Ok(MappedFile {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+        let ptr = unsafe {
+            sys::mmap(std::ptr::null_mut(), len, sys::PROT_READ, sys::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == sys::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(MappedFile { ptr, len })
+    }
+
+    #[cfg(not(all(unix, target_pointer_width = "64")))]
+    pub fn map(file: &File) -> io::Result<MappedFile> {
+        use std::io::Read;
+
+        let mut data = Vec::new();
+        let mut file = file;
+        file.read_to_end(&mut data)?;
+        Ok(MappedFile { len: data.len(), data })
+    }
+
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    pub fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    #[cfg(not(all(unix, target_pointer_width = "64")))]
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                sys::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MappedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<MappedFile of {} bytes>", self.len)
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod sys {
+    pub use std::os::raw::{c_int, c_void};
+
+    // same values on linux and macos
+    pub const PROT_READ: c_int = 1;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        // off_t is 64 bit on 64 bit unixes
+        pub fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64) -> *mut c_void;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
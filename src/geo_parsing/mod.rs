@@ -1,22 +1,71 @@
+mod parse_error;
 mod parsing_ascii;
 mod parsing_bin;
+mod mapped_file;
+mod parse_options;
 
 use crate::geo_struct::ReaderElement;
 use crate::bgeo_constants::JID_MAGIC;
-use parsing_ascii::parse_ascii_first_byte_separately;
-use parsing_bin::parse_binary_first_byte_separately;
+use crate::compression::{BloscReader, GzipReader, GZIP_MAGIC};
+use std::io::Read;
+use std::sync::Arc;
+use parsing_ascii::try_parse_ascii_first_byte_separately;
+use parsing_bin::{try_parse_binary_first_byte_separately, try_parse_binary_mapped};
 
-pub use parsing_ascii::parse_ascii;
-pub use parsing_bin::parse_binary;
+pub use parse_error::GeoParseError;
+pub use parsing_ascii::{parse_ascii, try_parse_ascii};
+pub use parsing_bin::{parse_binary, try_parse_binary};
+pub use mapped_file::MappedFile;
+pub use parse_options::ParseOptions;
+pub(crate) use parsing_bin::decode_uniform_array;
 
 
 pub fn parse(input: &mut dyn std::io::Read) -> ReaderElement {
+    try_parse(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// same as parse, but returns an error instead of panicking on malformed input
+pub fn try_parse(input: &mut dyn std::io::Read) -> Result<ReaderElement, GeoParseError> {
+    try_parse_with_options(input, &ParseOptions::new())
+}
+
+/// same as try_parse, but attributes that options do not keep are skipped
+pub fn try_parse_with_options(input: &mut dyn std::io::Read, options: &ParseOptions) -> Result<ReaderElement, GeoParseError> {
     let mut buf = [0_u8; 1];
-    input.read_exact(&mut buf).expect("failed to read magic header");
+    if let Err(e) = input.read_exact(&mut buf) {
+        return Err(GeoParseError::new(format!("failed to read magic header: {}", e), "geo or bgeo data", 0));
+    }
 
     if buf[0] == JID_MAGIC {
-        parse_binary_first_byte_separately(buf[0], input)
+        try_parse_binary_first_byte_separately(buf[0], input, options)
+    } else if (1..=4).contains(&buf[0]) {
+        // blosc chunk version byte, neither binary nor ascii geo may start with it
+        let mut chained = (&buf[..]).chain(input);
+        let mut decompressed = BloscReader::new(&mut chained);
+        try_parse_with_options(&mut decompressed, options)
+    } else if buf[0] == GZIP_MAGIC[0] {
+        let mut chained = (&buf[..]).chain(input);
+        let mut decompressed = GzipReader::new(&mut chained);
+        try_parse_with_options(&mut decompressed, options)
+    } else {
+        try_parse_ascii_first_byte_separately(buf[0], input, options)
+    }
+}
+
+/// same as try_parse, but for a memory mapped file
+///
+/// big uniform arrays of binary geo are not decoded while parsing, they stay in the file
+/// and are decoded only when accessed, so schema parser only pays for attributes it actually reads.
+/// compressed and ascii geo can't be lazy, they are just parsed from the mapped memory
+pub fn try_parse_mapped(file: MappedFile) -> Result<ReaderElement, GeoParseError> {
+    try_parse_mapped_with_options(file, &ParseOptions::new())
+}
+
+/// same as try_parse_mapped, but attributes that options do not keep are skipped
+pub fn try_parse_mapped_with_options(file: MappedFile, options: &ParseOptions) -> Result<ReaderElement, GeoParseError> {
+    if file.bytes().first() == Some(&JID_MAGIC) {
+        try_parse_binary_mapped(Arc::new(file), options)
     } else {
-        parse_ascii_first_byte_separately(buf[0], input)
+        try_parse_with_options(&mut file.bytes(), options)
     }
 }
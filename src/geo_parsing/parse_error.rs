@@ -0,0 +1,72 @@
+use std::fmt;
+
+use crate::geo_struct::ReaderElement;
+
+/// error produced when input cannot be parsed as a geo/bgeo file
+///
+/// offset is counted in bytes from the start of the (decompressed) geo stream,
+/// path is the location inside the structure, where key-value arrays are walked by key name
+pub struct GeoParseError {
+    pub message: String,
+    pub expected: String,
+    pub offset: usize,
+    /// 1-based line and column, only known for ascii geo
+    pub line_column: Option<(usize, usize)>,
+    pub path: Vec<String>,
+}
+
+impl GeoParseError {
+    pub(super) fn new(message: String, expected: &str, offset: usize) -> GeoParseError {
+        GeoParseError {
+            message,
+            expected: expected.to_owned(),
+            offset,
+            line_column: None,
+            path: Vec::new(),
+        }
+    }
+
+    /// prepend path of the parent element, we get those while unwinding
+    pub(super) fn within(mut self, segment: String) -> GeoParseError {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// prepend path of the parent array, idx being the index of the element being parsed
+    ///
+    /// houdini geo is mostly built of arrays of alternating keys and values,
+    /// so if previous element is a string - we use it as the name of the segment
+    pub(super) fn within_array(self, arr: &[ReaderElement], idx: usize) -> GeoParseError {
+        let segment = match (idx % 2, idx.checked_sub(1).and_then(|i| arr.get(i))) {
+            (1, Some(ReaderElement::Text(key))) => key.to_string(),
+            _ => idx.to_string(),
+        };
+        self.within(segment)
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+impl fmt::Display for GeoParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GeoParseError: {} at byte {}", self.message, self.offset)?;
+        if let Some((line, column)) = self.line_column {
+            write!(f, " (line {}, column {})", line, column)?;
+        }
+        write!(f, ", expected {}", self.expected)?;
+        if !self.path.is_empty() {
+            write!(f, ", inside '{}'", self.path_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GeoParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}>", self)
+    }
+}
+
+impl std::error::Error for GeoParseError {}
@@ -0,0 +1,175 @@
+// options to skip attributes that are not needed while parsing
+//
+// data of skipped attributes is never decoded (big uniform arrays are just skipped over),
+// in the parsed structure it is replaced with null, so schema parser sees them as present, but not loaded
+//
+// header only parsing goes further: only counts, info and attribute headers are kept,
+// and parsing stops as soon as all of those are read
+
+use crate::geo_struct::ReaderElement;
+use crate::houdini_geo_schema::AttributeClass;
+
+/// what to keep while parsing, by default everything is kept
+///
+/// if any attributes or classes are given - only those are kept, everything else is skipped
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    attributes: Vec<String>,
+    classes: Vec<AttributeClass>,
+    header_only: bool,
+}
+
+/// top level keys that header only parsing keeps
+const HEADER_KEYS: [&str; 6] = ["fileversion", "pointcount", "vertexcount", "primitivecount", "info", "attributes"];
+/// keys of attribute data that hold actual values, everything else there is small
+///
+/// string attribute indices are a block like values, so only the arrays inside it are skipped
+const ATTRIBUTE_VALUE_KEYS: [&str; 3] = ["tuples", "rawpagedata", "arrays"];
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// read just enough to know element counts, info block and attribute headers, see GeoHeader
+    ///
+    /// topology, primitives and attribute values are replaced with nulls,
+    /// and the rest of the input is not read at all once all header keys are found
+    pub fn header_only() -> ParseOptions {
+        ParseOptions {
+            header_only: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    pub fn is_header_only(&self) -> bool {
+        self.header_only
+    }
+
+    /// keep attribute with given name, whatever class it is
+    pub fn keep_attribute(mut self, name: &str) -> ParseOptions {
+        self.attributes.push(name.to_owned());
+        self
+    }
+
+    /// keep all attributes of given class
+    pub fn keep_class(mut self, class: AttributeClass) -> ParseOptions {
+        self.classes.push(class);
+        self
+    }
+
+    pub fn keeps_everything(&self) -> bool {
+        !self.header_only && self.attributes.is_empty() && self.classes.is_empty()
+    }
+
+    pub fn keeps(&self, class: AttributeClass, name: &str) -> bool {
+        self.keeps_everything() || self.classes.contains(&class) || self.attributes.iter().any(|x| x == name)
+    }
+}
+
+/// where parser is in the geo structure, as far as skipping attributes and packing their values is concerned
+///
+/// it goes root -> attributes -> pointattributes -> single attribute -> its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AttributeScope {
+    /// somewhere we don't care about
+    Other,
+    Root,
+    Attributes,
+    ClassAttributes(AttributeClass),
+    /// [header, data] pair of a single attribute
+    Attribute(AttributeClass),
+    /// data of an attribute when parsing header only, everything but actual values is kept
+    Stripped,
+    /// data of an attribute that is not kept, or anything inside it
+    Skipped,
+    /// attribute values given as "tuples", parser may pack them into a TupleArray
+    Tuples,
+}
+
+impl AttributeScope {
+    /// scope to parse the top element in
+    pub(super) fn root(options: &ParseOptions) -> AttributeScope {
+        if options.keeps_everything() {
+            AttributeScope::Other
+        } else {
+            AttributeScope::Root
+        }
+    }
+
+    /// scope of the next element of an array, given array elements parsed so far
+    pub(super) fn array_child(self, arr: &[ReaderElement], options: &ParseOptions) -> AttributeScope {
+        let key = match (arr.len() % 2, arr.last()) {
+            (1, Some(ReaderElement::Text(key))) => Some(key.as_str()),
+            _ => None,
+        };
+        match (self, key) {
+            (AttributeScope::Root, Some("attributes")) => AttributeScope::Attributes,
+            (AttributeScope::Root, Some(key)) if options.header_only && !HEADER_KEYS.contains(&key) => {
+                AttributeScope::Skipped
+            }
+            (AttributeScope::Attributes, Some("pointattributes")) => AttributeScope::ClassAttributes(AttributeClass::Point),
+            (AttributeScope::Attributes, Some("vertexattributes")) => AttributeScope::ClassAttributes(AttributeClass::Vertex),
+            (AttributeScope::Attributes, Some("primitiveattributes")) => {
+                AttributeScope::ClassAttributes(AttributeClass::Primitive)
+            }
+            (AttributeScope::Attributes, Some("globalattributes")) => AttributeScope::ClassAttributes(AttributeClass::Detail),
+            (AttributeScope::ClassAttributes(class), _) => AttributeScope::Attribute(class),
+            (AttributeScope::Attribute(_), _) if arr.len() == 1 && options.header_only => AttributeScope::Stripped,
+            (AttributeScope::Attribute(class), _) if arr.len() == 1 => match attribute_name(&arr[0]) {
+                Some(name) if !options.keeps(class, name) => AttributeScope::Skipped,
+                _ => AttributeScope::Other,
+            },
+            (AttributeScope::Stripped, Some(key)) if ATTRIBUTE_VALUE_KEYS.contains(&key) => AttributeScope::Skipped,
+            (AttributeScope::Stripped, _) => AttributeScope::Stripped,
+            (AttributeScope::Skipped, _) => AttributeScope::Skipped,
+            (AttributeScope::Other, Some("tuples")) => AttributeScope::Tuples,
+            _ => AttributeScope::Other,
+        }
+    }
+
+    /// scope of map values, maps only appear deeper than attributes
+    pub(super) fn map_child(self) -> AttributeScope {
+        match self {
+            AttributeScope::Skipped => AttributeScope::Skipped,
+            _ => AttributeScope::Other,
+        }
+    }
+
+    /// value to put into the array, skipped values are replaced with null here
+    pub(super) fn filter(self, child: AttributeScope, value: ReaderElement) -> ReaderElement {
+        match child {
+            AttributeScope::Skipped => ReaderElement::None,
+            _ => value,
+        }
+    }
+
+    /// true if there's no need to parse the rest of the array,
+    /// that's the case for the top array when parsing header only and all of the header is there
+    pub(super) fn is_complete(self, arr: &[ReaderElement], options: &ParseOptions) -> bool {
+        if self != AttributeScope::Root || !options.header_only {
+            return false;
+        }
+        // only keys that already have values count
+        let mut found = 0;
+        for pair in arr.chunks_exact(2) {
+            if let ReaderElement::Text(key) = &pair[0] {
+                if HEADER_KEYS.contains(&key.as_str()) {
+                    found += 1;
+                }
+            }
+        }
+        found == HEADER_KEYS.len()
+    }
+}
+
+/// name from attribute header kv array
+fn attribute_name(header: &ReaderElement) -> Option<&str> {
+    match header {
+        ReaderElement::Array(arr) => arr.chunks(2).find_map(|pair| match pair {
+            [ReaderElement::Text(key), ReaderElement::Text(name)] if key == "name" => Some(name.as_str()),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
@@ -1,5 +1,8 @@
-use crate::geo_struct::ReaderElement;
-use std::collections::HashMap;
+use crate::geo_struct::{ReaderElement, TupleArray, UniformArrayType};
+use crate::ordered_map::OrderedMap;
+use crate::shared_str::{SharedStr, StringInterner};
+use super::parse_error::GeoParseError;
+use super::parse_options::{AttributeScope, ParseOptions};
 
 #[derive(Debug)]
 enum ReaderState {
@@ -17,6 +20,12 @@ struct BuffChannel<'a> {
     start: usize,
     i: usize,
     src: &'a mut dyn std::io::Read,
+    // position of self.i in the whole stream
+    offset: usize,
+    line: usize,
+    column: usize,
+    // read error is treated as end of stream, but we keep it to report
+    read_error: Option<std::io::Error>,
 }
 
 impl<'a> BuffChannel<'a> {
@@ -37,6 +46,10 @@ impl<'a> BuffChannel<'a> {
             start: 0,
             i: 0,
             src: file,
+            offset: 0,
+            line: 1,
+            column: 1,
+            read_error: None,
         }
     }
 
@@ -55,10 +68,16 @@ impl<'a> BuffChannel<'a> {
         let old_size = self.buff.len();
         self.buff.resize(old_size + chunk, 0);
 
-        let read_bytes = self
-            .src
-            .read(&mut self.buff[old_size..])
-            .expect("read error");
+        let read_bytes = loop {
+            match self.src.read(&mut self.buff[old_size..]) {
+                Ok(x) => break x,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.read_error = Some(e);
+                    break 0;
+                }
+            }
+        };
         self.buff.resize(old_size + read_bytes, 0);
 
         if read_bytes == 0 {
@@ -77,20 +96,22 @@ impl<'a> BuffChannel<'a> {
         return Some(self.buff[self.i]);
     }
 
-    pub fn consume(&mut self) -> Option<()> {
-        if self.i == self.buff.len() {
-            if self.buffer_moar() == None {
-                return None;
-            };
-        };
+    /// move past current byte, keeping track of position
+    fn advance(&mut self) {
+        if self.buff[self.i] == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        self.offset += 1;
         self.i += 1;
-        Some(())
     }
 
-    pub fn get(&mut self) -> Option<u8> {
-        let x = self.peek();
-        self.i += 1;
-        x
+    pub fn consume(&mut self) -> Option<()> {
+        self.peek()?;
+        self.advance();
+        Some(())
     }
 
     pub fn buffer(&self) -> &[u8] {
@@ -120,14 +141,198 @@ impl<'a> BuffChannel<'a> {
         }
     }
 
-    pub fn get_skip_whitespaces(&mut self) -> Option<u8> {
-        let x = self.peek_skip_whitespaces();
-        self.i += 1;
-        x
+    /// error at current position
+    pub fn error(&self, message: String, expected: &str) -> GeoParseError {
+        let mut error = GeoParseError::new(message, expected, self.offset);
+        error.line_column = Some((self.line, self.column));
+        error
+    }
+
+    pub fn eof_error(&self, expected: &str) -> GeoParseError {
+        match &self.read_error {
+            Some(e) => self.error(format!("read error: {}", e), expected),
+            None => self.error("unexpected end of file".to_owned(), expected),
+        }
+    }
+
+    /// consume next non-whitespace byte if it is one of the given, error otherwise
+    pub fn expect_one_of(&mut self, chars: &[u8], expected: &str) -> Result<u8, GeoParseError> {
+        match self.peek_skip_whitespaces() {
+            Some(x) if chars.contains(&x) => {
+                self.advance();
+                Ok(x)
+            }
+            Some(x) => Err(self.error(format!("unexpected character {:?}", x as char), expected)),
+            None => Err(self.eof_error(expected)),
+        }
     }
 }
 
-fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
+/// elements of an array being parsed
+///
+/// arrays of nothing but numbers or bools are kept packed from the start, big arrays in geo are almost always those,
+/// and a full ReaderElement per number is what makes parsed ascii geo many times bigger than the file
+///
+/// attribute tuples are packed too, all of them into one flat array, see TupleArray
+enum ArrayBuilder {
+    Empty,
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    Bools(Vec<bool>),
+    Elements(Vec<ReaderElement>),
+    /// empty array that may turn out to be attribute tuples
+    EmptyTuples,
+    /// tuple size and values of all tuples so far
+    FloatTuples(usize, Vec<f64>),
+    IntTuples(usize, Vec<i64>),
+}
+
+/// bigger arrays in tuples are not tuples anymore, and are cheap enough to keep as they are
+const MAX_PACKED_TUPLE_SIZE: usize = 16;
+
+/// ints mixed with floats (like 0 in "[0.5,0,1.5]") are kept as floats, as long as they convert exactly
+fn is_exact_in_f64(x: i64) -> bool {
+    x.unsigned_abs() <= 1 << f64::MANTISSA_DIGITS
+}
+
+/// values of a packed int array
+fn int_values(array: &UniformArrayType) -> Option<Vec<i64>> {
+    array
+        .to_elements()
+        .iter()
+        .map(|x| if let ReaderElement::Int(i) = x { Some(*i) } else { None })
+        .collect()
+}
+
+impl ArrayBuilder {
+    fn new(scope: AttributeScope) -> ArrayBuilder {
+        match scope {
+            AttributeScope::Tuples => ArrayBuilder::EmptyTuples,
+            _ => ArrayBuilder::Empty,
+        }
+    }
+
+    fn push(&mut self, value: ReaderElement) {
+        match (&mut *self, value) {
+            (ArrayBuilder::FloatTuples(size, vec), ReaderElement::UniformArray(tuple)) if tuple.len() == *size => match tuple {
+                UniformArrayType::UniformArrayTf64(tuple) => vec.extend(tuple),
+                tuple => match int_values(&tuple) {
+                    Some(ints) if ints.iter().all(|i| is_exact_in_f64(*i)) => vec.extend(ints.iter().map(|i| *i as f64)),
+                    _ => self.push_unpacked(ReaderElement::UniformArray(tuple)),
+                },
+            },
+            (ArrayBuilder::IntTuples(size, vec), ReaderElement::UniformArray(tuple)) if tuple.len() == *size => {
+                match (int_values(&tuple), tuple) {
+                    (Some(ints), _) => vec.extend(ints),
+                    (None, UniformArrayType::UniformArrayTf64(tuple)) if vec.iter().all(|i| is_exact_in_f64(*i)) => {
+                        let mut floats: Vec<f64> = vec.iter().map(|i| *i as f64).collect();
+                        floats.extend(tuple);
+                        *self = ArrayBuilder::FloatTuples(*size, floats);
+                    }
+                    (None, tuple) => self.push_unpacked(ReaderElement::UniformArray(tuple)),
+                }
+            }
+            (ArrayBuilder::EmptyTuples, ReaderElement::UniformArray(tuple))
+                if (1..=MAX_PACKED_TUPLE_SIZE).contains(&tuple.len()) =>
+            {
+                *self = match (int_values(&tuple), tuple) {
+                    (Some(ints), _) => ArrayBuilder::IntTuples(ints.len(), ints),
+                    (None, UniformArrayType::UniformArrayTf64(tuple)) => ArrayBuilder::FloatTuples(tuple.len(), tuple),
+                    (None, tuple) => ArrayBuilder::Elements(vec![ReaderElement::UniformArray(tuple)]),
+                };
+            }
+            (ArrayBuilder::EmptyTuples, value) => {
+                *self = ArrayBuilder::Empty;
+                self.push(value);
+            }
+            (ArrayBuilder::Ints(vec), ReaderElement::Int(x)) => vec.push(x),
+            (ArrayBuilder::Floats(vec), ReaderElement::Float(x)) => vec.push(x),
+            (ArrayBuilder::Floats(vec), ReaderElement::Int(x)) if is_exact_in_f64(x) => vec.push(x as f64),
+            (ArrayBuilder::Bools(vec), ReaderElement::Bool(x)) => vec.push(x),
+            (ArrayBuilder::Ints(vec), ReaderElement::Float(x)) if vec.iter().all(|i| is_exact_in_f64(*i)) => {
+                let mut floats: Vec<f64> = vec.iter().map(|i| *i as f64).collect();
+                floats.push(x);
+                *self = ArrayBuilder::Floats(floats);
+            }
+            (ArrayBuilder::Empty, ReaderElement::Int(x)) => *self = ArrayBuilder::Ints(vec![x]),
+            (ArrayBuilder::Empty, ReaderElement::Float(x)) => *self = ArrayBuilder::Floats(vec![x]),
+            (ArrayBuilder::Empty, ReaderElement::Bool(x)) => *self = ArrayBuilder::Bools(vec![x]),
+            (ArrayBuilder::Elements(vec), value) => vec.push(value),
+            (_, value) => self.push_unpacked(value),
+        }
+    }
+
+    /// value does not fit the packed array, so the array is unpacked
+    fn push_unpacked(&mut self, value: ReaderElement) {
+        let mut elements = std::mem::replace(self, ArrayBuilder::Empty).into_elements();
+        elements.push(value);
+        *self = ArrayBuilder::Elements(elements);
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            ArrayBuilder::Empty => 0,
+            ArrayBuilder::Ints(vec) => vec.len(),
+            ArrayBuilder::Floats(vec) => vec.len(),
+            ArrayBuilder::Bools(vec) => vec.len(),
+            ArrayBuilder::Elements(vec) => vec.len(),
+            ArrayBuilder::EmptyTuples => 0,
+            ArrayBuilder::FloatTuples(size, vec) => vec.len() / size,
+            ArrayBuilder::IntTuples(size, vec) => vec.len() / size,
+        }
+    }
+
+    /// elements that are not packed, packed numbers are of no interest to scopes and error paths, so they are not there
+    fn elements(&self) -> &[ReaderElement] {
+        match self {
+            ArrayBuilder::Elements(vec) => vec,
+            _ => &[],
+        }
+    }
+
+    fn into_elements(self) -> Vec<ReaderElement> {
+        match self {
+            ArrayBuilder::Empty => Vec::new(),
+            ArrayBuilder::Ints(vec) => vec.into_iter().map(ReaderElement::Int).collect(),
+            ArrayBuilder::Floats(vec) => vec.into_iter().map(ReaderElement::Float).collect(),
+            ArrayBuilder::Bools(vec) => vec.into_iter().map(ReaderElement::Bool).collect(),
+            ArrayBuilder::Elements(vec) => vec,
+            ArrayBuilder::EmptyTuples => Vec::new(),
+            ArrayBuilder::FloatTuples(size, vec) => vec
+                .chunks(size)
+                .map(|x| ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(x.to_vec())))
+                .collect(),
+            ArrayBuilder::IntTuples(size, vec) => vec
+                .chunks(size)
+                .map(|x| ReaderElement::UniformArray(UniformArrayType::from_ints(x.to_vec())))
+                .collect(),
+        }
+    }
+
+    fn finish(self) -> ReaderElement {
+        match self {
+            ArrayBuilder::Empty => ReaderElement::Array(Vec::new()),
+            ArrayBuilder::Ints(vec) => ReaderElement::UniformArray(UniformArrayType::from_ints(vec)),
+            ArrayBuilder::Floats(vec) => ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(vec)),
+            ArrayBuilder::Bools(vec) => ReaderElement::UniformArray(UniformArrayType::UniformArrayTbool(vec)),
+            ArrayBuilder::Elements(vec) => ReaderElement::Array(vec),
+            ArrayBuilder::EmptyTuples => ReaderElement::Array(Vec::new()),
+            ArrayBuilder::FloatTuples(size, vec) => {
+                ReaderElement::TupleArray(Box::new(TupleArray::new(size, UniformArrayType::UniformArrayTf64(vec))))
+            }
+            ArrayBuilder::IntTuples(size, vec) => {
+                ReaderElement::TupleArray(Box::new(TupleArray::new(size, UniformArrayType::from_ints(vec))))
+            }
+        }
+    }
+}
+
+fn parse_one_element(
+    chan: &mut BuffChannel,
+    strings: &mut StringInterner,
+    options: &ParseOptions,
+    scope: AttributeScope,
+) -> Result<ReaderElement, GeoParseError> {
     let mut state = ReaderState::Off;
 
     let mut value = ReaderElement::None;
@@ -149,8 +354,8 @@ fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
             state = ReaderState::Text;
         }
         Some(smth) => {
-            panic!("unexpected file structure! got {:?}", smth);
-        } // TODO: add position information
+            return Err(chan.error(format!("unexpected file structure! got {:?}", smth as char), "value"));
+        }
         None => {} // normal channel closure, we exit
     }
     // println!("reading {:?}", state);
@@ -159,8 +364,8 @@ fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
         ReaderState::Off => {} // nothing, though it should happen only on empty input
         ReaderState::Number => {
             chan.reset_buffer();
-            loop {
-                let c = chan.peek().expect("unexpected end of file");
+            let (number_offset, number_line, number_column) = (chan.offset, chan.line, chan.column);
+            while let Some(c) = chan.peek() {
                 if !c.is_ascii_digit() {
                     if c == b'.' {
                         // we should ensure there's one dot, but meh
@@ -173,77 +378,63 @@ fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
                         break;
                     }
                 }
-                chan.consume().expect("unexpected end of file");
+                chan.consume();
             }
-            match std::str::from_utf8(chan.buffer())
-                .expect("bad number")
-                .parse::<i64>()
-            {
-                Ok(number) => {
-                    value = ReaderElement::Int(number);
-                }
-                Err(_) => {
-                    match std::str::from_utf8(chan.buffer())
-                        .expect("bad number")
-                        .parse::<f64>()
-                    {
-                        Ok(number) => {
-                            value = ReaderElement::Float(number);
-                        }
-                        Err(_) => {
-                            panic!("bad number !! '{:?}'", chan.buffer())
-                        }
-                    }
-                }
+            // only ascii characters got here, so it's valid utf8
+            let text = String::from_utf8_lossy(chan.buffer());
+            if let Ok(number) = text.parse::<i64>() {
+                value = ReaderElement::Int(number);
+            } else if let Ok(number) = text.parse::<f64>() {
+                value = ReaderElement::Float(number);
+            } else {
+                let mut error = chan.error(format!("bad number '{}'", text), "number");
+                error.offset = number_offset;
+                error.line_column = Some((number_line, number_column));
+                return Err(error);
             }
         }
         ReaderState::Array => {
-            let mut arr: Vec<ReaderElement> = Vec::new();
-            if chan.get().expect("internal error") != b'[' {
-                panic!("internal error");
-            }
-            if chan.peek_skip_whitespaces().expect("unable to parse file") != b']' {
+            let mut arr = ArrayBuilder::new(scope);
+            chan.expect_one_of(b"[", "'['")?;
+            if chan.peek_skip_whitespaces() != Some(b']') {
                 // empty array
                 loop {
-                    let arr_value = parse_one_element(chan);
-                    arr.push(arr_value);
-                    match chan.get_skip_whitespaces() {
-                        Some(b',') => continue,
-                        Some(b']') => break,
-                        x => panic!(
-                            "bad array!, saw {:?}, arr {:?}",
-                            char::from_u32(x.expect("") as u32),
-                            arr
-                        ),
+                    let child_scope = scope.array_child(arr.elements(), options);
+                    let arr_value = parse_one_element(chan, strings, options, child_scope)
+                        .map_err(|e| e.within_array(arr.elements(), arr.len()))?;
+                    arr.push(scope.filter(child_scope, arr_value));
+                    if scope.is_complete(arr.elements(), options) {
+                        // the rest is not needed
+                        break;
+                    }
+                    match chan.expect_one_of(b",]", "',' or ']'")? {
+                        b',' => continue,
+                        _ => break,
                     }
                 }
             } else {
                 chan.consume();
             }; // eat that ]
-            value = ReaderElement::Array(arr);
+            value = arr.finish();
         }
         ReaderState::KeyValueObject => {
-            let mut hmap: HashMap<String, ReaderElement> = HashMap::new();
-            if chan.get().expect("internal error") != b'{' {
-                panic!("internal error");
-            }
-            if chan.peek_skip_whitespaces().expect("unable to parse file") != b'}' {
+            let mut hmap: OrderedMap<SharedStr, ReaderElement> = OrderedMap::new();
+            chan.expect_one_of(b"{", "'{'")?;
+            if chan.peek_skip_whitespaces() != Some(b'}') {
                 // empty map
                 loop {
-                    let mkey = parse_one_element(chan);
-                    if chan.get_skip_whitespaces().expect("internal error") != b':' {
-                        panic!("internal error");
-                    }
-                    let mval = parse_one_element(chan);
-                    if let ReaderElement::Text(text) = mkey {
-                        hmap.insert(text, mval);
-                    } else {
-                        panic!("non-string keys are not yet supported")
-                    }
-                    match chan.get_skip_whitespaces() {
-                        Some(b',') => continue,
-                        Some(b'}') => break,
-                        _ => panic!("bad dict!"),
+                    let key = match chan.peek_skip_whitespaces() {
+                        Some(b'"') => parse_text(chan)?,
+                        Some(_) => return Err(chan.error("non-string keys are not yet supported".to_owned(), "string key")),
+                        None => return Err(chan.eof_error("string key")),
+                    };
+                    chan.expect_one_of(b":", "':'").map_err(|e| e.within(key.clone()))?;
+                    let mval =
+                        parse_one_element(chan, strings, options, scope.map_child()).map_err(|e| e.within(key.clone()))?;
+                    hmap.insert(strings.intern(&key), mval);
+                    match chan.expect_one_of(b",}", "',' or '}'")? {
+                        b',' => continue,
+                        _ => break,
                     }
                 }
             } else {
@@ -252,36 +443,13 @@ fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
             value = ReaderElement::KeyValueObject(hmap);
         }
         ReaderState::Text => {
-            if chan.get().expect("internal error") != b'"' {
-                panic!("internal error");
-            }
-            chan.reset_buffer();
-
-            let mut next_escaped = false;
-            loop {
-                let char = chan.peek().expect("unexpected end of file");
-                if next_escaped {
-                    next_escaped = false;
-                } else if char == b'\\' {
-                    next_escaped = true;
-                    chan.consume();
-                    continue;
-                } else if char == b'"' {
-                    break;
-                };
-                chan.consume();
-            }
-            value = ReaderElement::Text(String::from_utf8_lossy(chan.buffer()).to_string());
-            chan.consume(); // eat closing "
+            value = ReaderElement::Text(strings.intern(&parse_text(chan)?));
         }
         ReaderState::Keyword => {
-            if !chan.peek().expect("internal error").is_ascii_alphabetic() {
-                panic!("internal error");
-            }
             chan.reset_buffer();
+            let (keyword_offset, keyword_line, keyword_column) = (chan.offset, chan.line, chan.column);
 
-            loop {
-                let char = chan.peek().expect("unexpected end of file");
+            while let Some(char) = chan.peek() {
                 if !char.is_ascii_alphanumeric() {
                     break;
                 };
@@ -294,32 +462,145 @@ fn parse_one_element(chan: &mut BuffChannel) -> ReaderElement {
                 b"false" => {
                     value = ReaderElement::Bool(false);
                 }
+                b"null" => {
+                    value = ReaderElement::None;
+                }
                 _ => {
-                    panic!(
-                        "unknown keyword '{}'",
-                        String::from_utf8_lossy(chan.buffer())
+                    let mut error = chan.error(
+                        format!("unknown keyword '{}'", String::from_utf8_lossy(chan.buffer())),
+                        "true, false or null",
                     );
+                    error.offset = keyword_offset;
+                    error.line_column = Some((keyword_line, keyword_column));
+                    return Err(error);
                 }
             }
         }
     };
-    return value;
+    Ok(value)
 }
 
-pub fn parse_ascii_first_byte_separately(
+/// read a json string, decoding escape sequences
+///
+/// unpaired utf16 surrogates from \u escapes can't be represented, they become U+FFFD
+fn parse_text(chan: &mut BuffChannel) -> Result<String, GeoParseError> {
+    chan.expect_one_of(b"\"", "'\"'")?;
+    chan.reset_buffer();
+
+    let mut text: Vec<u8> = Vec::new();
+    let mut high_surrogate: Option<u16> = None;
+    loop {
+        let byte = match chan.peek() {
+            Some(x) => x,
+            None => return Err(chan.eof_error("closing '\"'")),
+        };
+        chan.consume();
+        let unit = match byte {
+            b'\\' => {
+                let escaped = match chan.peek() {
+                    Some(x) => x,
+                    None => return Err(chan.eof_error("escape sequence")),
+                };
+                let decoded = match escaped {
+                    b'"' | b'\\' | b'/' => escaped,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'u' => 0,
+                    _ => {
+                        return Err(chan.error(
+                            format!("unknown escape sequence '\\{}'", escaped as char),
+                            "one of \\\" \\\\ \\/ \\b \\f \\n \\r \\t \\uXXXX",
+                        ))
+                    }
+                };
+                chan.consume();
+                if escaped == b'u' {
+                    Some(parse_hex4(chan)?)
+                } else {
+                    push_unpaired_surrogate(&mut text, &mut high_surrogate);
+                    text.push(decoded);
+                    None
+                }
+            }
+            b'"' => break,
+            _ => {
+                push_unpaired_surrogate(&mut text, &mut high_surrogate);
+                text.push(byte);
+                None
+            }
+        };
+
+        if let Some(unit) = unit {
+            match (high_surrogate.take(), unit) {
+                (Some(high), 0xdc00..=0xdfff) => {
+                    let code = 0x10000 + (((high as u32) - 0xd800) << 10) + ((unit as u32) - 0xdc00);
+                    push_char(&mut text, char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                (high, _) => {
+                    if high.is_some() {
+                        push_char(&mut text, char::REPLACEMENT_CHARACTER);
+                    }
+                    if (0xd800..=0xdbff).contains(&unit) {
+                        high_surrogate = Some(unit);
+                    } else {
+                        // lone low surrogate is the only thing that is not a valid char here
+                        push_char(&mut text, char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                }
+            }
+        }
+    }
+    push_unpaired_surrogate(&mut text, &mut high_surrogate);
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+fn push_char(text: &mut Vec<u8>, c: char) {
+    let mut utf8 = [0_u8; 4];
+    text.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+}
+
+fn push_unpaired_surrogate(text: &mut Vec<u8>, high_surrogate: &mut Option<u16>) {
+    if high_surrogate.take().is_some() {
+        push_char(text, char::REPLACEMENT_CHARACTER);
+    }
+}
+
+fn parse_hex4(chan: &mut BuffChannel) -> Result<u16, GeoParseError> {
+    let mut unit = 0_u16;
+    for _ in 0..4 {
+        let digit = match chan.peek() {
+            Some(x) => (x as char)
+                .to_digit(16)
+                .ok_or_else(|| chan.error(format!("unexpected character {:?}", x as char), "hex digit"))?,
+            None => return Err(chan.eof_error("hex digit")),
+        };
+        chan.consume();
+        unit = unit << 4 | digit as u16;
+    }
+    Ok(unit)
+}
+
+pub fn try_parse_ascii_first_byte_separately(
     first_byte: u8,
     input: &mut dyn std::io::Read,
-) -> ReaderElement {
+    options: &ParseOptions,
+) -> Result<ReaderElement, GeoParseError> {
     // TODO: we already buffer from outside, rework this
     let mut chan = BuffChannel::new(input, 1024 * 128, 0);
     let buff = [first_byte; 1];
     chan.populate_with(&buff);
-    return parse_one_element(&mut chan);
+    parse_one_element(&mut chan, &mut StringInterner::new(), options, AttributeScope::root(options))
 }
 
-pub fn parse_ascii(input: &mut dyn std::io::Read) -> ReaderElement {
-    //let mut buf = String::new();
-
+pub fn try_parse_ascii(input: &mut dyn std::io::Read) -> Result<ReaderElement, GeoParseError> {
     let mut chan = BuffChannel::new(input, 1024 * 128, 0);
-    return parse_one_element(&mut chan);
+    let options = ParseOptions::new();
+    parse_one_element(&mut chan, &mut StringInterner::new(), &options, AttributeScope::root(&options))
+}
+
+pub fn parse_ascii(input: &mut dyn std::io::Read) -> ReaderElement {
+    try_parse_ascii(input).unwrap_or_else(|e| panic!("{}", e))
 }
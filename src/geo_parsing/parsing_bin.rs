@@ -1,8 +1,14 @@
 use std::io::Read;
+use std::sync::Arc;
 
-use crate::geo_struct::{ReaderElement, UniformArrayType};
+use crate::geo_struct::{LazyUniformArray, ReaderElement, UniformArrayType};
+use crate::ordered_map::OrderedMap;
+use crate::shared_str::{SharedStr, StringInterner};
 use crate::f16_half::{half_from_be_bytes, half_from_le_bytes};
 use crate::bgeo_constants::*;
+use super::parse_error::GeoParseError;
+use super::mapped_file::MappedFile;
+use super::parse_options::{AttributeScope, ParseOptions};
 use std::collections::HashMap;
 
 
@@ -14,9 +20,105 @@ enum ReaderElementOption {
     ValueSeparatorToken
 }
 
+// we never preallocate more than that for arrays, as length may come from a broken file
+const MAX_PREALLOC: usize = 1 << 20;
+// uniform arrays are read in blocks of that many bytes
+const BULK_BLOCK_SIZE: usize = 1 << 16;
+// uniform arrays in mapped files smaller than that are decoded right away, it's not worth being lazy about them
+const LAZY_MIN_SIZE: usize = 1 << 12;
+
+/// where bgeo bytes come from, either any stream, or a memory mapped file
+trait BgeoInput: Read {
+    /// mapped input can skip over data without reading it, that's what lazy uniform arrays are made of
+    fn mapped(&mut self) -> Option<&mut MappedInput> {
+        None
+    }
+}
+
+struct StreamInput<'r>(&'r mut dyn Read);
+
+impl Read for StreamInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl BgeoInput for StreamInput<'_> {}
+
+struct MappedInput {
+    file: Arc<MappedFile>,
+    pos: usize,
+}
+
+impl Read for MappedInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut rest = &self.file.bytes()[self.pos..];
+        let read = rest.read(buf)?;
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+impl BgeoInput for MappedInput {
+    fn mapped(&mut self) -> Option<&mut MappedInput> {
+        Some(self)
+    }
+}
+
+/// numbers that uniform arrays are made of, converted from raw bytes in bulk
+trait UniformElement {
+    type Value;
+    const SIZE: usize;
+    fn from_le_slice(bytes: &[u8]) -> Self::Value;
+    fn from_be_slice(bytes: &[u8]) -> Self::Value;
+}
+
+macro_rules! _uniform_element_filler {
+    ($T:ty) => {
+        impl UniformElement for $T {
+            type Value = $T;
+            const SIZE: usize = std::mem::size_of::<$T>();
+            fn from_le_slice(bytes: &[u8]) -> $T {
+                <$T>::from_le_bytes(bytes.try_into().expect("slice must be of element size"))
+            }
+            fn from_be_slice(bytes: &[u8]) -> $T {
+                <$T>::from_be_bytes(bytes.try_into().expect("slice must be of element size"))
+            }
+        }
+    };
+}
+
+_uniform_element_filler!(u8);
+_uniform_element_filler!(u16);
+_uniform_element_filler!(i8);
+_uniform_element_filler!(i16);
+_uniform_element_filler!(i32);
+_uniform_element_filler!(i64);
+_uniform_element_filler!(f32);
+_uniform_element_filler!(f64);
+
+/// half float, that we keep as f32
+struct Half;
+
+impl UniformElement for Half {
+    type Value = f32;
+    const SIZE: usize = 2;
+    fn from_le_slice(bytes: &[u8]) -> f32 {
+        half_from_le_bytes(bytes.try_into().expect("slice must be of element size"))
+    }
+    fn from_be_slice(bytes: &[u8]) -> f32 {
+        half_from_be_bytes(bytes.try_into().expect("slice must be of element size"))
+    }
+}
+
 struct BgeoParser<'a> {
-    chan: &'a mut dyn Read,
-    tokens: HashMap<usize, String>,
+    chan: &'a mut dyn BgeoInput,
+    options: &'a ParseOptions,
+    offset: usize,
+    big_endian: bool,
+    tokens: HashMap<usize, SharedStr>,
+    /// strings and tokens are interned, so the same text is allocated once, however many times it's defined
+    strings: StringInterner,
     u16_from_bytes: &'static dyn Fn([u8; 2]) -> u16,
     u32_from_bytes: &'static dyn Fn([u8; 4]) -> u32,
     u64_from_bytes: &'static dyn Fn([u8; 8]) -> u64,
@@ -29,10 +131,14 @@ struct BgeoParser<'a> {
 }
 
 impl<'a> BgeoParser<'a> {
-    fn new_be(channel: &'a mut dyn Read) -> BgeoParser {
+    fn new_be(channel: &'a mut dyn BgeoInput, options: &'a ParseOptions) -> BgeoParser<'a> {
         BgeoParser {
             chan: channel,
+            options,
+            offset: 0,
+            big_endian: true,
             tokens: HashMap::new(),
+            strings: StringInterner::new(),
             u16_from_bytes: &u16::from_be_bytes,
             u32_from_bytes: &u32::from_be_bytes,
             u64_from_bytes: &u64::from_be_bytes,
@@ -45,10 +151,14 @@ impl<'a> BgeoParser<'a> {
         }
     }
 
-    fn new_le(channel: &'a mut dyn Read) -> BgeoParser {
+    fn new_le(channel: &'a mut dyn BgeoInput, options: &'a ParseOptions) -> BgeoParser<'a> {
         BgeoParser {
             chan: channel,
+            options,
+            offset: 0,
+            big_endian: false,
             tokens: HashMap::new(),
+            strings: StringInterner::new(),
             u32_from_bytes: &u32::from_le_bytes,
             u64_from_bytes: &u64::from_le_bytes,
             u16_from_bytes: &u16::from_le_bytes,
@@ -61,141 +171,168 @@ impl<'a> BgeoParser<'a> {
         }
     }
 
-    fn parse_read_length(&mut self) -> usize {
-        const ERRMSG: &str = "unexpected end of stream while reading length";
-        let mut buff: [u8; 8] = [0; 8];
-        self.chan.read_exact(&mut buff[..1]).expect(ERRMSG);
+    fn error(&self, offset: usize, message: String, expected: &str) -> GeoParseError {
+        GeoParseError::new(message, expected, offset)
+    }
 
-        match buff[0] {
-            x if x<0xf1 => x as usize, 
-            x if x == 0xf0 + 2 => {
-                self.chan.read_exact(&mut buff[..2]).expect(ERRMSG);
-                (self.u16_from_bytes)(buff[..2].try_into().expect("")) as usize
+    fn read_exact(&mut self, buff: &mut [u8], expected: &str) -> Result<(), GeoParseError> {
+        match self.chan.read_exact(buff) {
+            Ok(()) => {
+                self.offset += buff.len();
+                Ok(())
             }
-            x if x == 0xf0 + 4 => {
-                self.chan.read_exact(&mut buff[..4]).expect(ERRMSG);
-                (self.u32_from_bytes)(buff[..4].try_into().expect("")) as usize
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(self.error(self.offset, "unexpected end of stream".to_owned(), expected))
             }
-            x if x == 0xf0 + 8 => {
-                self.chan.read_exact(&mut buff[..8]).expect(ERRMSG);
-                (self.u64_from_bytes)(buff[..8].try_into().expect("")) as usize
-            }
-            _ => panic!("unexpected length format")
+            Err(e) => Err(self.error(self.offset, format!("read error: {}", e), expected)),
         }
     }
 
-    fn parse_string(&mut self) -> String {
-        let len = self.parse_read_length();
-        let mut buff: Vec<u8> = vec![0; len];
-        self.chan.read_exact(&mut buff[..]).expect("unexpected end of buffer while reading string");
+    fn read_bytes<const N: usize>(&mut self, expected: &str) -> Result<[u8; N], GeoParseError> {
+        let mut buff = [0_u8; N];
+        self.read_exact(&mut buff, expected)?;
+        Ok(buff)
+    }
+
+    fn parse_read_length(&mut self) -> Result<usize, GeoParseError> {
+        const EXPECTED: &str = "length";
+        let len_offset = self.offset;
+        let [len_type] = self.read_bytes(EXPECTED)?;
+
+        Ok(match len_type {
+            x if x<0xf1 => x as usize, 
+            x if x == 0xf0 + 2 => (self.u16_from_bytes)(self.read_bytes(EXPECTED)?) as usize,
+            x if x == 0xf0 + 4 => (self.u32_from_bytes)(self.read_bytes(EXPECTED)?) as usize,
+            x if x == 0xf0 + 8 => (self.u64_from_bytes)(self.read_bytes(EXPECTED)?) as usize,
+            x => return Err(self.error(len_offset, format!("unexpected length format {:#x}", x), EXPECTED)),
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, GeoParseError> {
+        let len = self.parse_read_length()?;
+        let str_offset = self.offset;
+        // read through take, so that broken length does not make us allocate crazy amounts
+        let mut buff: Vec<u8> = Vec::new();
+        let read = (&mut *self.chan).take(len as u64).read_to_end(&mut buff);
+        self.offset += buff.len();
+        let expected = format!("string of {} bytes", len);
+        if let Err(e) = read {
+            return Err(self.error(self.offset, format!("read error: {}", e), &expected));
+        }
+        if buff.len() < len {
+            return Err(self.error(self.offset, "unexpected end of stream".to_owned(), &expected));
+        }
 
-        String::from_utf8(buff).expect("malformed utf8 string!")
+        String::from_utf8(buff).map_err(|_| self.error(str_offset, "malformed utf8 string".to_owned(), &expected))
     }
 
-    fn parse_u8(&mut self) -> u8 {
-        let mut buff = [0_u8; 1];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading u8");
-        buff[0]
+    fn parse_shared_string(&mut self) -> Result<SharedStr, GeoParseError> {
+        let s = self.parse_string()?;
+        Ok(self.strings.intern(&s))
     }
 
-    fn parse_u16(&mut self) -> u16 {
-        let mut buff = [0_u8; 2];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading u16");
-        (self.u16_from_bytes)(buff)
+    fn parse_u8(&mut self) -> Result<u8, GeoParseError> {
+        let [x] = self.read_bytes("u8")?;
+        Ok(x)
+    }
+
+    fn parse_u16(&mut self) -> Result<u16, GeoParseError> {
+        Ok((self.u16_from_bytes)(self.read_bytes("u16")?))
     }
 
     // currently not needed
     // 
-    // fn parse_u32(&mut self) -> u32 {
-    //     let mut buff = [0_u8; 4];
-    //     self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading u32");
-    //     (self.u32_from_bytes)(buff)
+    // fn parse_u32(&mut self) -> Result<u32, GeoParseError> {
+    //     Ok((self.u32_from_bytes)(self.read_bytes("u32")?))
     // }
 
-    // fn parse_u64(&mut self) -> u64 {
-    //     let mut buff = [0_u8; 8];
-    //     self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading u64");
-    //     (self.u64_from_bytes)(buff)
+    // fn parse_u64(&mut self) -> Result<u64, GeoParseError> {
+    //     Ok((self.u64_from_bytes)(self.read_bytes("u64")?))
     // }
 
-    fn parse_i8(&mut self) -> i8 {
-        let mut buff = [0_u8; 1];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading i8");
-        buff[0] as i8
+    fn parse_i8(&mut self) -> Result<i8, GeoParseError> {
+        let [x] = self.read_bytes("i8")?;
+        Ok(x as i8)
     }
 
-    fn parse_i16(&mut self) -> i16 {
-        let mut buff = [0_u8; 2];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading i16");
-        (self.i16_from_bytes)(buff)
+    fn parse_i16(&mut self) -> Result<i16, GeoParseError> {
+        Ok((self.i16_from_bytes)(self.read_bytes("i16")?))
     }
 
-    fn parse_i32(&mut self) -> i32 {
-        let mut buff = [0_u8; 4];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading i32");
-        (self.i32_from_bytes)(buff)
+    fn parse_i32(&mut self) -> Result<i32, GeoParseError> {
+        Ok((self.i32_from_bytes)(self.read_bytes("i32")?))
     }
 
-    fn parse_i64(&mut self) -> i64 {
-        let mut buff = [0_u8; 8];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading i64");
-        (self.i64_from_bytes)(buff)
+    fn parse_i64(&mut self) -> Result<i64, GeoParseError> {
+        Ok((self.i64_from_bytes)(self.read_bytes("i64")?))
     }
 
-    fn parse_f16(&mut self) -> f32 {
-        let mut buff = [0_u8; 2];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading f32");
-        (self.f16_32_from_bytes)(buff)
+    fn parse_f16(&mut self) -> Result<f32, GeoParseError> {
+        Ok((self.f16_32_from_bytes)(self.read_bytes("f16")?))
     }
 
-    fn parse_f32(&mut self) -> f32 {
-        let mut buff = [0_u8; 4];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading f32");
-        (self.f32_from_bytes)(buff)
+    fn parse_f32(&mut self) -> Result<f32, GeoParseError> {
+        Ok((self.f32_from_bytes)(self.read_bytes("f32")?))
     }
 
-    fn parse_f64(&mut self) -> f64 {
-        let mut buff = [0_u8; 8];
-        self.chan.read_exact(&mut buff).expect("unexpected end of buffer while reading f64");
-        (self.f64_from_bytes)(buff)
+    fn parse_f64(&mut self) -> Result<f64, GeoParseError> {
+        Ok((self.f64_from_bytes)(self.read_bytes("f64")?))
     }
 
-    fn parse_token_def_binary(&mut self) {
-        let token_id = self.parse_read_length();
-        let s = self.parse_string();
+    fn parse_token_def_binary(&mut self) -> Result<(), GeoParseError> {
+        let token_id = self.parse_read_length()?;
+        let s = self.parse_shared_string()?;
         self.tokens.insert(token_id, s);
+        Ok(())
     }
 
-    fn parse_token_undef_binary(&mut self) {
-        let token_id = self.parse_read_length();
-        self.tokens.remove(&token_id).expect(format!("token {:?} was not defiend", token_id).as_str());
+    /// token id, resolved into the string it was defined with
+    fn parse_token_ref(&mut self) -> Result<SharedStr, GeoParseError> {
+        let token_offset = self.offset;
+        let token_id = self.parse_read_length()?;
+        match self.tokens.get(&token_id) {
+            Some(token) => Ok(token.clone()),
+            None => Err(self.error(
+                token_offset,
+                format!("referenced token {} was not defined", token_id),
+                "defined token id",
+            )),
+        }
     }
 
-    fn parse_one_element_binary(&mut self) -> ReaderElementOption {
-        let mut buff = [0_u8; 8];
+    fn parse_token_undef_binary(&mut self) -> Result<(), GeoParseError> {
+        let token_offset = self.offset;
+        let token_id = self.parse_read_length()?;
+        match self.tokens.remove(&token_id) {
+            Some(_) => Ok(()),
+            None => Err(self.error(token_offset, format!("token {:?} was not defiend", token_id), "defined token id")),
+        }
+    }
 
-        self.chan.read_exact(&mut buff[..1]).expect("unexpected end of buffer while reading token type");
-        let mut next_type_byte = buff[0];
+    fn parse_one_element_binary(&mut self, scope: AttributeScope) -> Result<ReaderElementOption, GeoParseError> {
+        const EXPECTED: &str = "token type";
+        let [mut next_type_byte] = self.read_bytes(EXPECTED)?;
 
         loop {
             match next_type_byte {
                 JID_TOKENDEF => {
-                    self.parse_token_def_binary();     
+                    self.parse_token_def_binary()?;     
                 },
                 JID_TOKENUNDEF => {
-                    self.parse_token_undef_binary();
+                    self.parse_token_undef_binary()?;
                 },
                 _ =>  break
             }
-            self.chan.read_exact(&mut buff[..1]).expect("unexpected end of buffer while reading token type");
-            next_type_byte = buff[0];
+            [next_type_byte] = self.read_bytes(EXPECTED)?;
         }
+        let type_offset = self.offset - 1;
 
         match next_type_byte {
             JID_MAP_BEGIN => {
-                let mut map = HashMap::new();
+                let mut map = OrderedMap::new();
                 loop {
-                    let key = match self.parse_one_element_binary() {
+                    let key_offset = self.offset;
+                    let key = match self.parse_one_element_binary(scope.map_child())? {
                         ReaderElementOption::Some(ReaderElement::Text(key))  => {
                             key
                         }
@@ -204,120 +341,120 @@ impl<'a> BgeoParser<'a> {
                         }
                         // ReaderElementOption::ValueSeparatorToken => continue,
                         t => {
-                            panic!("unexpected token in map: {:?}", t);
+                            let message = format!("unexpected token in map: {:?}", t);
+                            return Err(self.error(key_offset, message, "map key or map end"));
                         }
                     };
-                    // self.chan.read_exact(&mut buff[..1]).expect("unexpected end of buffer while reading key separator");
-                    // if buff[0] != JID_KEY_SEPARATOR {
-                    //     panic!("expected key separator after key, got {:?}, key={:?}", buff[0], key);
-                    // }
-                    let val = match self.parse_one_element_binary() {
+                    let val_offset = self.offset;
+                    let val = match self.parse_one_element_binary(scope.map_child()).map_err(|e| e.within(key.to_string()))? {
                         ReaderElementOption::Some(x) => { x }
-                        _ => { panic!("unexpected end of map"); }
+                        t => {
+                            return Err(self.error(val_offset, format!("unexpected token in map: {:?}", t), "map value")
+                                .within(key.to_string()));
+                        }
                     };
                     map.insert(key, val);
                 }
-                return ReaderElementOption::Some(ReaderElement::KeyValueObject(map));
+                Ok(ReaderElementOption::Some(ReaderElement::KeyValueObject(map)))
             }
             JID_ARRAY_BEGIN => {
                 let mut arr = Vec::new();
                 loop {
-                    let val = match self.parse_one_element_binary() {
+                    let val_offset = self.offset;
+                    let child_scope = scope.array_child(&arr, self.options);
+                    let val = match self.parse_one_element_binary(child_scope).map_err(|e| e.within_array(&arr, arr.len()))? {
                         ReaderElementOption::Some(x) => x,
                         ReaderElementOption::ArrayEndToken => {
                             break;
                         }
                         // ReaderElementOption::ValueSeparatorToken => continue,
-                        _ => { panic!("unexpected end of array"); }
+                        t => {
+                            let message = format!("unexpected token in array: {:?}", t);
+                            return Err(self.error(val_offset, message, "array value or array end"));
+                        }
                     };
-                    arr.push(val);
+                    arr.push(scope.filter(child_scope, val));
+                    if scope.is_complete(&arr, self.options) {
+                        // the rest is not needed
+                        break;
+                    }
                 }
-                return ReaderElementOption::Some(ReaderElement::Array(arr));
+                Ok(ReaderElementOption::Some(ReaderElement::Array(arr)))
             }
             JID_BOOL => {
-                self.chan.read_exact(&mut buff[..1]).expect("unexpected end of buffer while reading bool");
-                return ReaderElementOption::Some(ReaderElement::Bool(buff[0] != 0));
+                Ok(ReaderElementOption::Some(ReaderElement::Bool(self.parse_u8()? != 0)))
             }
             JID_FALSE => {
-                return ReaderElementOption::Some(ReaderElement::Bool(false));
+                Ok(ReaderElementOption::Some(ReaderElement::Bool(false)))
             }
             JID_TRUE => {
-                return ReaderElementOption::Some(ReaderElement::Bool(true));
-            }
-            token @ (JID_INT8 | JID_INT16 | JID_INT32 | JID_INT64) => {
-                return ReaderElementOption::Some(ReaderElement::Int(match token {
-                    JID_INT8 => self.parse_i8() as i64,
-                    JID_INT16 => self.parse_i16() as i64,
-                    JID_INT32 => self.parse_i32() as i64,
-                    JID_INT64 => self.parse_i64(),
-                    _ => { panic!("very unexpected type of int {:?}", token); }
-                }));
-            }
-            token @ (JID_UINT8 | JID_UINT16) => {
-                return ReaderElementOption::Some(ReaderElement::Int(match token {
-                    JID_UINT8 => self.parse_u8() as i64,
-                    JID_UINT16 => self.parse_u16() as i64,
-                    _ => { panic!("very unexpected type of int {:?}", token); }
-                }));
-            }
-            token @ (JID_REAL16 | JID_REAL32 | JID_REAL64 ) => {
-                return ReaderElementOption::Some(ReaderElement::Float(match token {
-                    JID_REAL16 => self.parse_f16() as f64,
-                    JID_REAL32 => self.parse_f32() as f64,
-                    JID_REAL64 => self.parse_f64(),
-                    _ => { panic!("very unexpected type of int {:?}", token); }
-                }));
+                Ok(ReaderElementOption::Some(ReaderElement::Bool(true)))
             }
+            JID_INT8 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_i8()? as i64))),
+            JID_INT16 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_i16()? as i64))),
+            JID_INT32 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_i32()? as i64))),
+            JID_INT64 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_i64()?))),
+            JID_UINT8 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_u8()? as i64))),
+            JID_UINT16 => Ok(ReaderElementOption::Some(ReaderElement::Int(self.parse_u16()? as i64))),
+            JID_REAL16 => Ok(ReaderElementOption::Some(ReaderElement::Float(self.parse_f16()? as f64))),
+            JID_REAL32 => Ok(ReaderElementOption::Some(ReaderElement::Float(self.parse_f32()? as f64))),
+            JID_REAL64 => Ok(ReaderElementOption::Some(ReaderElement::Float(self.parse_f64()?))),
             JID_STRING => {
-                return ReaderElementOption::Some(ReaderElement::Text(self.parse_string()));
+                Ok(ReaderElementOption::Some(ReaderElement::Text(self.parse_shared_string()?)))
             }
             JID_TOKENREF => {
-                let token_id = self.parse_read_length();
-                // we duplicate all tokens at this stage, do we care?
-                return ReaderElementOption::Some(ReaderElement::Text(self.tokens.get(&token_id).expect(format!("referenced token {} was not defined", token_id).as_str()).to_owned()));
+                Ok(ReaderElementOption::Some(ReaderElement::Text(self.parse_token_ref()?)))
             }
             JID_UNIFORM_ARRAY => {
-                self.chan.read_exact(&mut buff[..1]).expect("unexpected end of buffer while reading key uniform array type");
-                let array_type = buff[0];
-                let array_len = self.parse_read_length();
+                let array_type_offset = self.offset;
+                let array_type = self.parse_u8()?;
+                let array_len = self.parse_read_length()?;
+                if scope == AttributeScope::Skipped {
+                    if let Some(byte_len) = uniform_skip_size(array_type, array_len) {
+                        // nobody is going to look at it, so it's not even read
+                        self.skip_bytes(byte_len)?;
+                        return Ok(ReaderElementOption::Some(ReaderElement::None));
+                    }
+                }
+                if let Some(lazy) = self.skip_lazy_uniform_array(array_type, array_len)? {
+                    return Ok(ReaderElementOption::Some(ReaderElement::LazyUniformArray(Box::new(lazy))));
+                }
                 let vec_el: ReaderElement = ReaderElement::UniformArray(match array_type {
                     JID_INT8 => {
-                        UniformArrayType::UniformArrayTi8(self.parse_uniform_array(array_len, &Self::parse_i8))
+                        UniformArrayType::UniformArrayTi8(self.parse_uniform_array_bulk::<i8>(array_len)?)
                     }
-                    JID_INT16 => {  // TODO: make more effective, less repetative
-                        UniformArrayType::UniformArrayTi16(self.parse_uniform_array(array_len, &Self::parse_i16))
+                    JID_INT16 => {
+                        UniformArrayType::UniformArrayTi16(self.parse_uniform_array_bulk::<i16>(array_len)?)
                     }
                     JID_INT32 => {
-                        UniformArrayType::UniformArrayTi32(self.parse_uniform_array(array_len, &Self::parse_i32))
+                        UniformArrayType::UniformArrayTi32(self.parse_uniform_array_bulk::<i32>(array_len)?)
                     }
                     JID_INT64 => {
-                        UniformArrayType::UniformArrayTi64(self.parse_uniform_array(array_len, &Self::parse_i64))
+                        UniformArrayType::UniformArrayTi64(self.parse_uniform_array_bulk::<i64>(array_len)?)
                     }
                     JID_UINT8 => {
-                        UniformArrayType::UniformArrayTu8(self.parse_uniform_array(array_len, &Self::parse_u8))
+                        UniformArrayType::UniformArrayTu8(self.parse_uniform_array_bulk::<u8>(array_len)?)
                     }
                     JID_UINT16 => {
-                        UniformArrayType::UniformArrayTu16(self.parse_uniform_array(array_len, &Self::parse_u16))
+                        UniformArrayType::UniformArrayTu16(self.parse_uniform_array_bulk::<u16>(array_len)?)
                     }
                     JID_REAL16 => {
-                        UniformArrayType::UniformArrayTf16(self.parse_uniform_array(array_len, &Self::parse_f16))
+                        UniformArrayType::UniformArrayTf16(self.parse_uniform_array_bulk::<Half>(array_len)?)
                     }
                     JID_REAL32 => {
-                        UniformArrayType::UniformArrayTf32(self.parse_uniform_array(array_len, &Self::parse_f32))
+                        UniformArrayType::UniformArrayTf32(self.parse_uniform_array_bulk::<f32>(array_len)?)
                     }
                     JID_REAL64 => {
-                        UniformArrayType::UniformArrayTf64(self.parse_uniform_array(array_len, &Self::parse_f64))
+                        UniformArrayType::UniformArrayTf64(self.parse_uniform_array_bulk::<f64>(array_len)?)
                     }
                     JID_BOOL => {
                         // packed blocks of 32 bits
                         let mut remaining_len = array_len;
-                        let mut buff = [0_u8; 4];
-                        let mut vec = Vec::with_capacity(array_len);
+                        let mut vec = Vec::with_capacity(array_len.min(MAX_PREALLOC));
                         while remaining_len > 0 {
                             let nbits = 32.min(remaining_len);
 
-                            self.chan.read_exact(&mut buff).expect("failed to read buffer");
-                            let sample = (self.u32_from_bytes)(buff.try_into().expect("failed to convert bits"));
+                            let sample = (self.u32_from_bytes)(self.read_bytes("packed bool block")?);
                             for i in 0..nbits {
                                 vec.push(sample & (1 << i) != 0);
                             }
@@ -325,70 +462,234 @@ impl<'a> BgeoParser<'a> {
                         }
                         UniformArrayType::UniformArrayTbool(vec)
                     }
+                    JID_STRING => {
+                        UniformArrayType::UniformArrayTstring(self.parse_uniform_array(array_len, &Self::parse_shared_string)?)
+                    }
                     JID_TOKENREF => {
-                        panic!("not yet implemented!");
+                        // tokens can't be defined inside, they are only referenced by ids
+                        UniformArrayType::UniformArrayTstring(self.parse_uniform_array(array_len, &Self::parse_token_ref)?)
                     }
-                    JID_STRING => {
-                        panic!("not yet implemented!");
+                    _ => {
+                        return Err(self.error(
+                            array_type_offset,
+                            format!("unknown unified array type {}", array_type),
+                            "uniform array type",
+                        ));
                     }
-                    _ => panic!("unknown unified array type {}", array_type)
                 });
                 // we convert uniform array into simple array... is it good enough? 
                 // imagine voxel data array - even one byte overhead from enum is a pain,
                 // but converint it later in schema parsers is even more of a unnecessary overhead, so
                 // TODO: add uniform array to ReaderElement
-                return ReaderElementOption::Some(vec_el);
+                Ok(ReaderElementOption::Some(vec_el))
             }
             JID_NULL => {
-                return ReaderElementOption::Some(ReaderElement::None);
+                Ok(ReaderElementOption::Some(ReaderElement::None))
             }
             JID_MAP_END => {
-                return ReaderElementOption::MapEndToken;
+                Ok(ReaderElementOption::MapEndToken)
             }
             JID_ARRAY_END => {
-                return ReaderElementOption::ArrayEndToken;
+                Ok(ReaderElementOption::ArrayEndToken)
             }
             JID_VALUE_SEPARATOR => {
-                return ReaderElementOption::ValueSeparatorToken;
+                Ok(ReaderElementOption::ValueSeparatorToken)
             }
             x => {
-                panic!("unexpected token: {:?}", x);
+                Err(self.error(type_offset, format!("unexpected token: {:#x}", x), EXPECTED))
+            }
+        }
+    }
+
+    /// numeric uniform arrays are read in big blocks and converted all at once,
+    /// which is way faster than reading them value by value
+    fn parse_uniform_array_bulk<T: UniformElement>(&mut self, array_len: usize) -> Result<Vec<T::Value>, GeoParseError> {
+        const EXPECTED: &str = "uniform array data";
+        if array_len.checked_mul(T::SIZE).is_none() {
+            return Err(self.error(self.offset, format!("uniform array length {} is too big", array_len), EXPECTED));
+        }
+        let mut vec = Vec::with_capacity(array_len.min(MAX_PREALLOC));
+        let mut block = vec![0_u8; (array_len * T::SIZE).min(BULK_BLOCK_SIZE)];
+        let mut remaining_len = array_len;
+        while remaining_len > 0 {
+            let block_len = remaining_len.min(BULK_BLOCK_SIZE / T::SIZE);
+            let bytes = &mut block[..block_len * T::SIZE];
+            self.read_exact(bytes, EXPECTED)?;
+            decode_into::<T>(bytes, self.big_endian, &mut vec);
+            remaining_len -= block_len;
+        }
+        Ok(vec)
+    }
+
+    /// with mapped input big numeric uniform arrays are not read, but just referenced, to be decoded when needed
+    fn skip_lazy_uniform_array(
+        &mut self,
+        array_type: u8,
+        array_len: usize,
+    ) -> Result<Option<LazyUniformArray>, GeoParseError> {
+        let byte_len = match uniform_element_size(array_type).and_then(|size| size.checked_mul(array_len)) {
+            Some(x) if x >= LAZY_MIN_SIZE => x,
+            _ => return Ok(None),
+        };
+        let big_endian = self.big_endian;
+        let mapped = match self.chan.mapped() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let data_offset = mapped.pos;
+        if mapped.file.len() - data_offset < byte_len {
+            let expected = format!("uniform array data of {} bytes", byte_len);
+            return Err(self.error(self.offset, "unexpected end of stream".to_owned(), &expected));
+        }
+        mapped.pos += byte_len;
+        let file = mapped.file.clone();
+        self.offset += byte_len;
+        Ok(Some(LazyUniformArray::new(file, data_offset..data_offset + byte_len, array_len, array_type, big_endian)))
+    }
+
+    fn skip_bytes(&mut self, byte_len: usize) -> Result<(), GeoParseError> {
+        let end_error = |offset| {
+            let expected = format!("uniform array data of {} bytes", byte_len);
+            GeoParseError::new("unexpected end of stream".to_owned(), &expected, offset)
+        };
+        match self.chan.mapped() {
+            Some(mapped) => {
+                if mapped.file.len() - mapped.pos < byte_len {
+                    return Err(end_error(self.offset));
+                }
+                mapped.pos += byte_len;
+            }
+            None => {
+                let skipped = std::io::copy(&mut (&mut *self.chan).take(byte_len as u64), &mut std::io::sink())
+                    .map_err(|e| self.error(self.offset, format!("failed to read: {}", e), "uniform array data"))?;
+                if skipped != byte_len as u64 {
+                    return Err(end_error(self.offset + skipped as usize));
+                }
             }
         }
+        self.offset += byte_len;
+        Ok(())
     }
 
-    fn parse_uniform_array<T>(&mut self, array_len: usize, parse_func: & dyn Fn(&mut Self) -> T) -> Vec<T> {
-        let mut vec = Vec::with_capacity(array_len);
+    fn parse_uniform_array<T>(
+        &mut self,
+        array_len: usize,
+        parse_func: &dyn Fn(&mut Self) -> Result<T, GeoParseError>,
+    ) -> Result<Vec<T>, GeoParseError> {
+        let mut vec = Vec::with_capacity(array_len.min(MAX_PREALLOC));
         for _ in 0..array_len {
-            vec.push(parse_func(self));
+            vec.push(parse_func(self)?);
         }
-        vec
+        Ok(vec)
     }
 }
 
 
-pub fn parse_binary_first_byte_separately(first_byte: u8, input: &mut dyn std::io::Read) -> ReaderElement {
+/// element size of numeric uniform arrays, None for others
+fn uniform_element_size(array_type: u8) -> Option<usize> {
+    match array_type {
+        JID_INT8 | JID_UINT8 => Some(1),
+        JID_INT16 | JID_UINT16 | JID_REAL16 => Some(2),
+        JID_INT32 | JID_REAL32 => Some(4),
+        JID_INT64 | JID_REAL64 => Some(8),
+        _ => None,
+    }
+}
+
+/// size of uniform array data that can be skipped without parsing it, None for arrays of strings
+fn uniform_skip_size(array_type: u8, array_len: usize) -> Option<usize> {
+    match array_type {
+        // packed in blocks of 32 bits
+        JID_BOOL => array_len.div_ceil(32).checked_mul(4),
+        _ => uniform_element_size(array_type)?.checked_mul(array_len),
+    }
+}
+
+fn decode_into<T: UniformElement>(bytes: &[u8], big_endian: bool, vec: &mut Vec<T::Value>) {
+    if big_endian {
+        vec.extend(bytes.chunks_exact(T::SIZE).map(T::from_be_slice));
+    } else {
+        vec.extend(bytes.chunks_exact(T::SIZE).map(T::from_le_slice));
+    }
+}
+
+fn decode<T: UniformElement>(bytes: &[u8], big_endian: bool) -> Vec<T::Value> {
+    let mut vec = Vec::with_capacity(bytes.len() / T::SIZE);
+    decode_into::<T>(bytes, big_endian, &mut vec);
+    vec
+}
+
+/// decode raw data of a numeric uniform array, bytes must be of the size that array type and length require
+pub(crate) fn decode_uniform_array(bytes: &[u8], array_type: u8, big_endian: bool) -> UniformArrayType {
+    match array_type {
+        JID_INT8 => UniformArrayType::UniformArrayTi8(decode::<i8>(bytes, big_endian)),
+        JID_INT16 => UniformArrayType::UniformArrayTi16(decode::<i16>(bytes, big_endian)),
+        JID_INT32 => UniformArrayType::UniformArrayTi32(decode::<i32>(bytes, big_endian)),
+        JID_INT64 => UniformArrayType::UniformArrayTi64(decode::<i64>(bytes, big_endian)),
+        JID_UINT8 => UniformArrayType::UniformArrayTu8(decode::<u8>(bytes, big_endian)),
+        JID_UINT16 => UniformArrayType::UniformArrayTu16(decode::<u16>(bytes, big_endian)),
+        JID_REAL16 => UniformArrayType::UniformArrayTf16(decode::<Half>(bytes, big_endian)),
+        JID_REAL32 => UniformArrayType::UniformArrayTf32(decode::<f32>(bytes, big_endian)),
+        JID_REAL64 => UniformArrayType::UniformArrayTf64(decode::<f64>(bytes, big_endian)),
+        x => panic!("{:#x} is not a numeric uniform array type", x),
+    }
+}
+
+
+pub fn try_parse_binary_first_byte_separately(
+    first_byte: u8,
+    input: &mut dyn std::io::Read,
+    options: &ParseOptions,
+) -> Result<ReaderElement, GeoParseError> {
+    try_parse_binary_input(first_byte, &mut StreamInput(input), options)
+}
+
+/// parse binary geo from a mapped file, big uniform arrays are left in the file until they are needed
+pub fn try_parse_binary_mapped(file: Arc<MappedFile>, options: &ParseOptions) -> Result<ReaderElement, GeoParseError> {
+    let mut input = MappedInput { file, pos: 0 };
+    let mut buf = [0_u8; 1];
+    if let Err(e) = input.read_exact(&mut buf[..1]) {
+        return Err(GeoParseError::new(format!("failed to read magic: {}", e), "binary json magic", 0));
+    }
+    try_parse_binary_input(buf[0], &mut input, options)
+}
+
+fn try_parse_binary_input(
+    first_byte: u8,
+    input: &mut dyn BgeoInput,
+    options: &ParseOptions,
+) -> Result<ReaderElement, GeoParseError> {
     if first_byte != JID_MAGIC {
-        panic!("bad magic header!");
+        return Err(GeoParseError::new(format!("bad magic header {:#x}", first_byte), "binary json magic", 0));
     }
     let mut buf = [0_u8; 4];
-    input.read_exact(&mut buf[..4]).expect("failed to endian magic");
+    if let Err(e) = input.read_exact(&mut buf[..4]) {
+        return Err(GeoParseError::new(format!("failed to read endian magic: {}", e), "endian magic", 1));
+    }
 
     let mut parser = match buf {
-        BINARY_MAGIC => BgeoParser::new_be(input),
-        BINARY_MAGIC_SWAP => BgeoParser::new_le(input),
-        _ => panic!("unrecognized binary magic!"),
-    };
-    return match parser.parse_one_element_binary() {
-        ReaderElementOption::Some(x) => x,
-        _ => panic!("failed to parse file")
+        BINARY_MAGIC => BgeoParser::new_be(input, options),
+        BINARY_MAGIC_SWAP => BgeoParser::new_le(input, options),
+        _ => return Err(GeoParseError::new("unrecognized binary magic".to_owned(), "endian magic", 1)),
     };
+    parser.offset = 5;
+    match parser.parse_one_element_binary(AttributeScope::root(options))? {
+        ReaderElementOption::Some(x) => Ok(x),
+        t => Err(parser.error(5, format!("unexpected token at top level: {:?}", t), "top level element")),
+    }
 }
 
 
-pub fn parse_binary(input: &mut dyn std::io::Read) -> ReaderElement {
+pub fn try_parse_binary(input: &mut dyn std::io::Read) -> Result<ReaderElement, GeoParseError> {
     let mut buf = [0_u8; 1];
-    input.read_exact(&mut buf[..1]).expect("failed to read magic");
+    if let Err(e) = input.read_exact(&mut buf[..1]) {
+        return Err(GeoParseError::new(format!("failed to read magic: {}", e), "binary json magic", 0));
+    }
+
+    try_parse_binary_first_byte_separately(buf[0], input, &ParseOptions::new())
+}
+
 
-    return parse_binary_first_byte_separately(buf[0], input);
+pub fn parse_binary(input: &mut dyn std::io::Read) -> ReaderElement {
+    try_parse_binary(input).unwrap_or_else(|e| panic!("{}", e))
 }
@@ -1,15 +1,39 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use crate::geo_parsing::{decode_uniform_array, MappedFile};
+use crate::ordered_map::OrderedMap;
+use crate::shared_str::SharedStr;
 
 #[derive(Debug, Clone)]
 pub enum ReaderElement {
     None,
     Bool(bool),
-    Text(String),
+    /// parsers intern strings, so repeated keys share memory
+    Text(SharedStr),
     Int(i64),
     Float(f64),
     Array(Vec<ReaderElement>),
-    KeyValueObject(HashMap<String, ReaderElement>),
+    KeyValueObject(OrderedMap<SharedStr, ReaderElement>),
     UniformArray(UniformArrayType),
+    /// uniform array that is still in the mapped file, see LazyUniformArray,
+    /// boxed to keep every other element small
+    LazyUniformArray(Box<LazyUniformArray>),
+    /// array of equally sized number arrays packed together, see TupleArray
+    TupleArray(Box<TupleArray>),
+}
+
+impl ReaderElement {
+    /// lazy uniform arrays are decoded (once) and returned as ordinary uniform arrays,
+    /// all other elements are returned as is
+    pub fn resolved(&self) -> &ReaderElement {
+        match self {
+            ReaderElement::LazyUniformArray(x) => x.element(),
+            x => x,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +48,268 @@ pub enum UniformArrayType {
     UniformArrayTf32(Vec<f32>),
     UniformArrayTf64(Vec<f64>),
     UniformArrayTbool(Vec<bool>),
+    /// both uniform arrays of strings and of token references end up here
+    UniformArrayTstring(Vec<SharedStr>),
+}
+
+impl UniformArrayType {
+    pub fn len(&self) -> usize {
+        match self {
+            UniformArrayType::UniformArrayTu8(vec) => vec.len(),
+            UniformArrayType::UniformArrayTu16(vec) => vec.len(),
+            UniformArrayType::UniformArrayTi8(vec) => vec.len(),
+            UniformArrayType::UniformArrayTi16(vec) => vec.len(),
+            UniformArrayType::UniformArrayTi32(vec) => vec.len(),
+            UniformArrayType::UniformArrayTi64(vec) => vec.len(),
+            UniformArrayType::UniformArrayTf16(vec) => vec.len(),
+            UniformArrayType::UniformArrayTf32(vec) => vec.len(),
+            UniformArrayType::UniformArrayTf64(vec) => vec.len(),
+            UniformArrayType::UniformArrayTbool(vec) => vec.len(),
+            UniformArrayType::UniformArrayTstring(vec) => vec.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// single value as a plain element, the way it would be if the array was not uniform
+    pub fn element(&self, i: usize) -> ReaderElement {
+        match self {
+            UniformArrayType::UniformArrayTu8(vec) => ReaderElement::Int(vec[i] as i64),
+            UniformArrayType::UniformArrayTu16(vec) => ReaderElement::Int(vec[i] as i64),
+            UniformArrayType::UniformArrayTi8(vec) => ReaderElement::Int(vec[i] as i64),
+            UniformArrayType::UniformArrayTi16(vec) => ReaderElement::Int(vec[i] as i64),
+            UniformArrayType::UniformArrayTi32(vec) => ReaderElement::Int(vec[i] as i64),
+            UniformArrayType::UniformArrayTi64(vec) => ReaderElement::Int(vec[i]),
+            UniformArrayType::UniformArrayTf16(vec) => ReaderElement::Float(vec[i] as f64),
+            UniformArrayType::UniformArrayTf32(vec) => ReaderElement::Float(vec[i] as f64),
+            UniformArrayType::UniformArrayTf64(vec) => ReaderElement::Float(vec[i]),
+            UniformArrayType::UniformArrayTbool(vec) => ReaderElement::Bool(vec[i]),
+            UniformArrayType::UniformArrayTstring(vec) => ReaderElement::Text(vec[i].clone()),
+        }
+    }
+
+    /// part of the array, as a new array of the same type
+    pub fn slice(&self, range: Range<usize>) -> UniformArrayType {
+        match self {
+            UniformArrayType::UniformArrayTu8(vec) => UniformArrayType::UniformArrayTu8(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTu16(vec) => UniformArrayType::UniformArrayTu16(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTi8(vec) => UniformArrayType::UniformArrayTi8(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTi16(vec) => UniformArrayType::UniformArrayTi16(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTi32(vec) => UniformArrayType::UniformArrayTi32(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTi64(vec) => UniformArrayType::UniformArrayTi64(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTf16(vec) => UniformArrayType::UniformArrayTf16(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTf32(vec) => UniformArrayType::UniformArrayTf32(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTf64(vec) => UniformArrayType::UniformArrayTf64(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTbool(vec) => UniformArrayType::UniformArrayTbool(vec[range].to_vec()),
+            UniformArrayType::UniformArrayTstring(vec) => UniformArrayType::UniformArrayTstring(vec[range].to_vec()),
+        }
+    }
+
+    /// expand into plain elements, the way the same array would look if it was not uniform
+    pub fn to_elements(&self) -> Vec<ReaderElement> {
+        match self {
+            UniformArrayType::UniformArrayTu8(vec) => vec.iter().map(|x| ReaderElement::Int(*x as i64)).collect(),
+            UniformArrayType::UniformArrayTu16(vec) => vec.iter().map(|x| ReaderElement::Int(*x as i64)).collect(),
+            UniformArrayType::UniformArrayTi8(vec) => vec.iter().map(|x| ReaderElement::Int(*x as i64)).collect(),
+            UniformArrayType::UniformArrayTi16(vec) => vec.iter().map(|x| ReaderElement::Int(*x as i64)).collect(),
+            UniformArrayType::UniformArrayTi32(vec) => vec.iter().map(|x| ReaderElement::Int(*x as i64)).collect(),
+            UniformArrayType::UniformArrayTi64(vec) => vec.iter().map(|x| ReaderElement::Int(*x)).collect(),
+            UniformArrayType::UniformArrayTf16(vec) => vec.iter().map(|x| ReaderElement::Float(*x as f64)).collect(),
+            UniformArrayType::UniformArrayTf32(vec) => vec.iter().map(|x| ReaderElement::Float(*x as f64)).collect(),
+            UniformArrayType::UniformArrayTf64(vec) => vec.iter().map(|x| ReaderElement::Float(*x)).collect(),
+            UniformArrayType::UniformArrayTbool(vec) => vec.iter().map(|x| ReaderElement::Bool(*x)).collect(),
+            UniformArrayType::UniformArrayTstring(vec) => vec.iter().map(|x| ReaderElement::Text(x.clone())).collect(),
+        }
+    }
+
+    /// pack plain elements into a uniform array, only arrays of all ints or all floats can be packed
+    ///
+    /// ints get the narrowest type that fits all of them, floats stay 64 bit to not lose precision
+    pub fn from_elements(arr: &[ReaderElement]) -> Option<UniformArrayType> {
+        match arr.first()? {
+            ReaderElement::Int(_) => arr
+                .iter()
+                .map(|x| if let ReaderElement::Int(i) = x { Some(*i) } else { None })
+                .collect::<Option<Vec<i64>>>()
+                .map(UniformArrayType::from_ints),
+            ReaderElement::Float(_) => arr
+                .iter()
+                .map(|x| if let ReaderElement::Float(f) = x { Some(*f) } else { None })
+                .collect::<Option<Vec<f64>>>()
+                .map(UniformArrayType::UniformArrayTf64),
+            _ => None,
+        }
+    }
+
+    /// narrowest integer uniform array that holds all the values
+    pub fn from_ints(ints: Vec<i64>) -> UniformArrayType {
+        let min = ints.iter().copied().min().unwrap_or(0);
+        let max = ints.iter().copied().max().unwrap_or(0);
+        let fits = |lo: i64, hi: i64| lo <= min && max <= hi;
+        if fits(i8::MIN as i64, i8::MAX as i64) {
+            UniformArrayType::UniformArrayTi8(ints.into_iter().map(|x| x as i8).collect())
+        } else if fits(0, u8::MAX as i64) {
+            UniformArrayType::UniformArrayTu8(ints.into_iter().map(|x| x as u8).collect())
+        } else if fits(i16::MIN as i64, i16::MAX as i64) {
+            UniformArrayType::UniformArrayTi16(ints.into_iter().map(|x| x as i16).collect())
+        } else if fits(0, u16::MAX as i64) {
+            UniformArrayType::UniformArrayTu16(ints.into_iter().map(|x| x as u16).collect())
+        } else if fits(i32::MIN as i64, i32::MAX as i64) {
+            UniformArrayType::UniformArrayTi32(ints.into_iter().map(|x| x as i32).collect())
+        } else {
+            UniformArrayType::UniformArrayTi64(ints)
+        }
+    }
+}
+
+/// array of tuples, like attribute values in ascii geo: "tuples",[[0.5,-0.5,0.5],[-0.5,-0.5,0.5],...]
+///
+/// a small array per tuple costs more than the numbers in it, so parsers keep all tuples in one flat uniform array
+#[derive(Debug, Clone)]
+pub struct TupleArray {
+    tuple_size: usize,
+    values: UniformArrayType,
+}
+
+impl TupleArray {
+    /// tuple size must not be 0, and values must hold a whole number of tuples
+    pub fn new(tuple_size: usize, values: UniformArrayType) -> TupleArray {
+        assert!(
+            tuple_size > 0 && values.len().is_multiple_of(tuple_size),
+            "values do not split into tuples of {}",
+            tuple_size
+        );
+        TupleArray { tuple_size, values }
+    }
+
+    pub fn tuple_size(&self) -> usize {
+        self.tuple_size
+    }
+
+    /// number of tuples
+    pub fn len(&self) -> usize {
+        self.values.len() / self.tuple_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// values of all tuples, one tuple after another
+    pub fn values(&self) -> &UniformArrayType {
+        &self.values
+    }
+
+    /// single tuple, as it would be in the array if tuples were not packed
+    pub fn tuple(&self, i: usize) -> UniformArrayType {
+        self.values.slice(i * self.tuple_size..(i + 1) * self.tuple_size)
+    }
+
+    /// expand into an array of uniform arrays, one per tuple
+    pub fn to_elements(&self) -> Vec<ReaderElement> {
+        (0..self.len()).map(|i| ReaderElement::UniformArray(self.tuple(i))).collect()
+    }
+}
+
+/// numeric uniform array, that was not decoded while parsing a mapped file,
+/// it's decoded on first access and kept decoded after that
+#[derive(Clone)]
+pub struct LazyUniformArray {
+    file: Arc<MappedFile>,
+    /// location of array data in the file
+    data: Range<usize>,
+    len: usize,
+    array_type: u8,
+    big_endian: bool,
+    decoded: OnceLock<Box<ReaderElement>>,
+}
+
+impl LazyUniformArray {
+    /// array_type must be a numeric one, and file must have all the data, parser checks that
+    pub(crate) fn new(
+        file: Arc<MappedFile>,
+        data: Range<usize>,
+        len: usize,
+        array_type: u8,
+        big_endian: bool,
+    ) -> LazyUniformArray {
+        LazyUniformArray {
+            file,
+            data,
+            len,
+            array_type,
+            big_endian,
+            decoded: OnceLock::new(),
+        }
+    }
+
+    /// number of elements in the array
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        self.decoded.get().is_some()
+    }
+
+    /// bgeo type of array elements, like JID_REAL32
+    pub fn array_type(&self) -> u8 {
+        self.array_type
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// raw array data as it is in the file
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.file.bytes()[self.data.clone()]
+    }
+
+    /// decoded array, always a ReaderElement::UniformArray
+    pub fn element(&self) -> &ReaderElement {
+        self.decoded.get_or_init(|| {
+            let array = decode_uniform_array(self.raw_bytes(), self.array_type, self.big_endian);
+            Box::new(ReaderElement::UniformArray(array))
+        })
+    }
+
+    /// decoded array, but without keeping it decoded if it was not already,
+    /// for when it's needed just once, like when writing it out
+    pub fn to_element(&self) -> Cow<'_, ReaderElement> {
+        match self.decoded.get() {
+            Some(x) => Cow::Borrowed(x),
+            None => Cow::Owned(ReaderElement::UniformArray(decode_uniform_array(
+                self.raw_bytes(),
+                self.array_type,
+                self.big_endian,
+            ))),
+        }
+    }
+
+    pub fn get(&self) -> &UniformArrayType {
+        match self.element() {
+            ReaderElement::UniformArray(x) => x,
+            _ => unreachable!("lazy array is always decoded into a uniform array"),
+        }
+    }
+}
+
+impl fmt::Debug for LazyUniformArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.decoded.get() {
+            Some(x) => write!(f, "LazyUniformArray({:?})", x),
+            None => {
+                write!(f, "LazyUniformArray(<{} elements of type {:#x} at {}>)", self.len, self.array_type, self.data.start)
+            }
+        }
+    }
 }
 
 /// this guy is supposed to provide information how to locate a certain key
@@ -37,6 +323,8 @@ pub struct ReaderElementPointer {
 enum ReaderElementPointerEntry {
     ArrayIndex(usize),
     MapKey(String),
+    /// value in a houdini style kv array, it's located by index, but displayed by key
+    KvArrayKey(String, usize),
 }
 
 impl ReaderElementPointer {
@@ -54,15 +342,37 @@ impl ReaderElementPointer {
         self.path.push(ReaderElementPointerEntry::MapKey(key));
     }
 
+    /// add index of a value in kv array, key is the key preceding that value
+    pub fn add_kv_array_key(&mut self, key: &str, idx: usize) {
+        self.path.push(ReaderElementPointerEntry::KvArrayKey(key.to_owned(), idx));
+    }
+
+    /// copy of this pointer with array index added
+    pub fn with_array_index(&self, idx: usize) -> ReaderElementPointer {
+        let mut pointer = self.clone();
+        pointer.add_array_index(idx);
+        pointer
+    }
+
+    /// copy of this pointer with kv array key added
+    pub fn with_kv_array_key(&self, key: &str, idx: usize) -> ReaderElementPointer {
+        let mut pointer = self.clone();
+        pointer.add_kv_array_key(key, idx);
+        pointer
+    }
+
     pub fn locate_key_in<'a>(&self, elem: &'a ReaderElement) -> Option<&'a ReaderElement> {
         let mut curr = elem;
         for entry in self.path.iter() {
             match (entry, curr) {
-                (ReaderElementPointerEntry::ArrayIndex(idx), ReaderElement::Array(next_array)) => {
+                (
+                    ReaderElementPointerEntry::ArrayIndex(idx) | ReaderElementPointerEntry::KvArrayKey(_, idx),
+                    ReaderElement::Array(next_array),
+                ) => {
                     curr = next_array.get(*idx)?;
                 }
                 (ReaderElementPointerEntry::MapKey(key), ReaderElement::KeyValueObject(next_map)) => {
-                    curr = next_map.get(key)?;
+                    curr = next_map.get(key.as_str())?;
                 }
                 _ => {
                     return None;
@@ -76,11 +386,14 @@ impl ReaderElementPointer {
         let mut curr = elem;
         for entry in self.path.iter() {
             match (entry, curr) {
-                (ReaderElementPointerEntry::ArrayIndex(idx), ReaderElement::Array(next_array)) => {
+                (
+                    ReaderElementPointerEntry::ArrayIndex(idx) | ReaderElementPointerEntry::KvArrayKey(_, idx),
+                    ReaderElement::Array(next_array),
+                ) => {
                     curr = next_array.get_mut(*idx)?;
                 }
                 (ReaderElementPointerEntry::MapKey(key), ReaderElement::KeyValueObject(next_map)) => {
-                    curr = next_map.get_mut(key)?;
+                    curr = next_map.get_mut(key.as_str())?;
                 }
                 _ => {
                     return None;
@@ -90,3 +403,26 @@ impl ReaderElementPointer {
         Some(curr)
     }
 }
+
+impl fmt::Display for ReaderElementPointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, entry) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, "/")?;
+            }
+            match entry {
+                ReaderElementPointerEntry::ArrayIndex(idx) => write!(f, "{}", idx)?,
+                ReaderElementPointerEntry::MapKey(key) | ReaderElementPointerEntry::KvArrayKey(key, _) => {
+                    write!(f, "{}", key)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ReaderElementPointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ReaderElementPointer {}>", self)
+    }
+}
@@ -1,6 +1,7 @@
 use std::io::stdout;
 use std::io::Write;
 
+use crate::compression::GzipWriter;
 use crate::geo_struct::{ReaderElement, UniformArrayType};
 
 pub fn preview(elem: &ReaderElement) {
@@ -11,6 +12,13 @@ pub fn to_json(elem: &ReaderElement, output: &mut dyn Write) {
     write_element(output, elem, 0);
 }
 
+/// same as to_json, but gzipped, like houdini's .geo.gz
+pub fn to_json_gz(elem: &ReaderElement, output: &mut dyn Write) {
+    let mut compressed = GzipWriter::new(output);
+    to_json(elem, &mut compressed);
+    compressed.finish().expect(ERRMSG);
+}
+
 const ERRMSG: &str = "write error!";
 
 fn write_tabs(output: &mut dyn Write, tabs: usize) {
@@ -19,6 +27,35 @@ fn write_tabs(output: &mut dyn Write, tabs: usize) {
     }
 }
 
+/// write a json string literal, escaping quotes, backslashes and control characters
+fn write_string(output: &mut dyn Write, text: &str) {
+    let bytes = text.as_bytes();
+    output.write_all(b"\"").expect(ERRMSG);
+    // multibyte utf8 sequences never contain bytes below 0x80, so it's safe to go byte by byte
+    let mut unwritten = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escaped = match byte {
+            b'"' => Some("\\\""),
+            b'\\' => Some("\\\\"),
+            b'\n' => Some("\\n"),
+            b'\r' => Some("\\r"),
+            b'\t' => Some("\\t"),
+            0x08 => Some("\\b"),
+            0x0c => Some("\\f"),
+            0..=0x1f => None,
+            _ => continue,
+        };
+        output.write_all(&bytes[unwritten..i]).expect(ERRMSG);
+        match escaped {
+            Some(escaped) => output.write_all(escaped.as_bytes()).expect(ERRMSG),
+            None => write!(output, "\\u{:04x}", byte).expect(ERRMSG),
+        }
+        unwritten = i + 1;
+    }
+    output.write_all(&bytes[unwritten..]).expect(ERRMSG);
+    output.write_all(b"\"").expect(ERRMSG);
+}
+
 enum WroteWhat {
     Init,
     WroteInline,
@@ -47,7 +84,7 @@ fn write_element(output: &mut dyn Write, elem: &ReaderElement, tabs: usize) {
             let mut wrote_in_line = 0;
             for (i, elem) in x.iter().enumerate() {
                 match elem {
-                    ReaderElement::Array(_) | ReaderElement::KeyValueObject(_) => {
+                    ReaderElement::Array(_) | ReaderElement::TupleArray(_) | ReaderElement::KeyValueObject(_) => {
                         if let WroteWhat::WroteInline | WroteWhat::WroteBlock = last_wrote_what {
                             writeln!(output, "").expect(ERRMSG);
                             write_tabs(output, tabs + 1);
@@ -124,10 +161,43 @@ fn write_element(output: &mut dyn Write, elem: &ReaderElement, tabs: usize) {
                 UniformArrayType::UniformArrayTf64(vec) if vec.len() > 0 => {
                     uniform_array_loop_print!(output, vec);
                 }
-                &_ => { panic!("wtf is this?"); }
+                UniformArrayType::UniformArrayTstring(vec) => {
+                    for (i, el) in vec.iter().enumerate() {
+                        if i > 0 {
+                            write!(output, ", ").expect(ERRMSG);
+                        }
+                        write_string(output, el);
+                    }
+                }
+                // only empty arrays are left here
+                &_ => (),
             };
             write!(output, "]").expect(ERRMSG);
         }
+        ReaderElement::LazyUniformArray(x) => {
+            write_element(output, &x.to_element(), tabs);
+        }
+        ReaderElement::TupleArray(x) if x.is_empty() => {
+            write!(output, "[]").expect(ERRMSG);
+        }
+        ReaderElement::TupleArray(x) => {
+            // same as an array of uniform arrays, but tuples are unpacked one at a time
+            writeln!(output, "[").expect(ERRMSG);
+            write_tabs(output, tabs + 1);
+            for i in 0..x.len() {
+                if i > 0 && i % 20 == 0 {
+                    writeln!(output).expect(ERRMSG);
+                    write_tabs(output, tabs + 1);
+                }
+                write_element(output, &ReaderElement::UniformArray(x.tuple(i)), 0);
+                if i != x.len() - 1 {
+                    write!(output, ", ").expect(ERRMSG);
+                }
+            }
+            writeln!(output).expect(ERRMSG);
+            write_tabs(output, tabs);
+            write!(output, "]").expect(ERRMSG);
+        }
         ReaderElement::KeyValueObject(x) if x.len() == 0 => {
             write!(output, "{{}}").expect(ERRMSG);
         }
@@ -138,8 +208,9 @@ fn write_element(output: &mut dyn Write, elem: &ReaderElement, tabs: usize) {
                 writeln!(output, "").expect(ERRMSG);
                 write_tabs(output, tabs + 1);
                 match elem {
-                    ReaderElement::Array(_) | ReaderElement::KeyValueObject(_) => {
-                        writeln!(output, "\"{}\":", key).expect(ERRMSG);
+                    ReaderElement::Array(_) | ReaderElement::TupleArray(_) | ReaderElement::KeyValueObject(_) => {
+                        write_string(output, key);
+                        writeln!(output, ":").expect(ERRMSG);
                         write_tabs(output, tabs + 1);
                         write_element(output, elem, tabs + 1);
                         if i != arr_last_i {
@@ -148,7 +219,8 @@ fn write_element(output: &mut dyn Write, elem: &ReaderElement, tabs: usize) {
                         write_tabs(output, tabs + 1);
                     }
                     _ => {
-                        write!(output, "\"{}\": ", key).expect(ERRMSG);
+                        write_string(output, key);
+                        write!(output, ": ").expect(ERRMSG);
                         write_element(output, elem, 0);
                         if i != arr_last_i {
                             write!(output, ", ").expect(ERRMSG);
@@ -170,10 +242,10 @@ fn write_element(output: &mut dyn Write, elem: &ReaderElement, tabs: usize) {
             write!(output, "{}", x).expect(ERRMSG);
         }
         ReaderElement::None => {
-            write!(output, "None").expect(ERRMSG);
+            write!(output, "null").expect(ERRMSG);
         }
         ReaderElement::Text(x) => {
-            write!(output, "\"{}\"", x).expect(ERRMSG);
+            write_string(output, x);
         }
     };
 }
@@ -0,0 +1,525 @@
+// owned geometry model, independent of the structure it was read from
+//
+// schema parser works over a borrowed structure and edits go back into it by paths,
+// geometry instead owns everything: topology, primitives and attribute tables,
+// so it can be edited freely, made from scratch, and turned into a new structure to be written
+
+use std::ops::Range;
+
+use crate::geo_struct::{ReaderElement, ReaderElementPointer, UniformArrayType};
+use crate::houdini_geo_schema::{
+    attribute_block, push_kv, typeinfo_option, AttributeClass, EdgeGroup, ElementGroup, GeoAttributeKind, GeoHeader,
+    HoudiniGeoSchemaParser, SchemaError,
+};
+use crate::ordered_map::OrderedMap;
+use crate::shared_str::SharedStr;
+
+#[derive(Clone)]
+pub struct Geometry {
+    point_count: usize,
+    /// point number of every vertex
+    vertex_points: Vec<usize>,
+    primitives: Vec<GeoPrimitive>,
+    point_attributes: OrderedMap<String, GeometryAttribute>,
+    vertex_attributes: OrderedMap<String, GeometryAttribute>,
+    primitive_attributes: OrderedMap<String, GeometryAttribute>,
+    detail_attributes: OrderedMap<String, GeometryAttribute>,
+    /// point, vertex and primitive groups, detail has none
+    groups: Vec<(AttributeClass, String, ElementGroup)>,
+    edge_groups: OrderedMap<String, EdgeGroup>,
+    pub fileversion: Option<String>,
+    /// info block as is, it has things like software, date and bounds
+    pub info: Option<OrderedMap<SharedStr, ReaderElement>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GeoPrimitive {
+    /// polygon made of a range of vertices
+    Polygon(Range<usize>),
+    /// primitive of a type that is not supported yet, it's kept as the raw primitive block it came in,
+    /// the block is held by the first primitive of it, the rest of primitives of that block have None
+    Unsupported(Option<Box<ReaderElement>>),
+}
+
+#[derive(Clone)]
+pub struct GeometryAttribute {
+    pub data: GeoAttributeKind,
+    /// attribute options as they are in the file, like {"type":{"type":"string","value":"vector"}}
+    pub options: OrderedMap<SharedStr, ReaderElement>,
+}
+
+impl GeometryAttribute {
+    pub fn new(data: GeoAttributeKind) -> GeometryAttribute {
+        GeometryAttribute {
+            data,
+            options: OrderedMap::new(),
+        }
+    }
+
+    /// how houdini interprets values, like "point", "vector", "normal" or "color", if options tell it
+    pub fn typeinfo(&self) -> Option<&str> {
+        match self.options.get("type") {
+            Some(ReaderElement::KeyValueObject(x)) => match x.get("value") {
+                Some(ReaderElement::Text(value)) => Some(value.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn set_typeinfo(&mut self, typeinfo: &str) {
+        self.options.insert("type".into(), typeinfo_option(typeinfo));
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::new()
+    }
+}
+
+impl Geometry {
+    /// empty geometry, with no points, primitives or attributes
+    pub fn new() -> Geometry {
+        Geometry {
+            point_count: 0,
+            vertex_points: Vec::new(),
+            primitives: Vec::new(),
+            point_attributes: OrderedMap::new(),
+            vertex_attributes: OrderedMap::new(),
+            primitive_attributes: OrderedMap::new(),
+            detail_attributes: OrderedMap::new(),
+            groups: Vec::new(),
+            edge_groups: OrderedMap::new(),
+            fileversion: None,
+            info: None,
+        }
+    }
+
+    /// read geometry from a parsed geo structure
+    ///
+    /// attributes of types that schema parser does not support, and attributes skipped by parse options are not there
+    pub fn from_structure(structure: &ReaderElement) -> Result<Geometry, SchemaError> {
+        let header = GeoHeader::new(structure)?;
+        let mut schema = HoudiniGeoSchemaParser::new(structure)?;
+        schema.parse_primitives()?;
+
+        let mut geometry = Geometry::new();
+        geometry.point_count = schema.point_count();
+        geometry.vertex_points = schema.vertex_points().to_vec();
+        geometry.info = header.info.clone();
+        if let Some(ReaderElement::Text(x)) = find_in_kv_array(structure, "fileversion") {
+            geometry.fileversion = Some(x.to_string());
+        }
+
+        let mut primitives = vec![None; schema.primitive_count()];
+        // polygons without vertices start where the previous one ended, so runs of polygons stay contiguous
+        let mut next_vertex = 0;
+        for polygon in schema.polygons() {
+            let start = polygon.vertices.first().map_or(next_vertex, |x| x.vtxnum);
+            next_vertex = start + polygon.vertices.len();
+            primitives[polygon.number] = Some(GeoPrimitive::Polygon(start..next_vertex));
+        }
+        for (first, count, block) in schema.unsupported_primitive_blocks() {
+            for number in *first..*first + *count {
+                let block = (number == *first).then(|| Box::new((*block).clone()));
+                // counts are not checked for unsupported blocks, so they may go past primitivecount
+                if let Some(x) = primitives.get_mut(number) {
+                    *x = Some(GeoPrimitive::Unsupported(block));
+                }
+            }
+        }
+        geometry.primitives = primitives
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| {
+                x.ok_or_else(|| {
+                    let mut path = ReaderElementPointer::new();
+                    path.add_kv_array_key("primitives", 0);
+                    SchemaError::new(format!("primitive {} is not in any primitive block", i), &path)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        for class in AttributeClass::ALL {
+            schema.parse_class_attributes(class)?;
+            let mut parsed = schema.take_class_attributes(class);
+            for attr_header in header.class_attributes(class) {
+                if let Some(data) = parsed.remove(attr_header.name.as_str()) {
+                    let attr = GeometryAttribute {
+                        data,
+                        options: attr_header.options.clone(),
+                    };
+                    geometry.attributes_mut(class).insert(attr_header.name.clone(), attr);
+                }
+            }
+        }
+
+        schema.parse_groups()?;
+        for class in AttributeClass::ALL {
+            for name in schema.group_names(class) {
+                let group = schema.group(class, name).expect("group names must match groups").clone();
+                geometry.groups.push((class, name.to_owned(), group));
+            }
+        }
+        for name in schema.edge_group_names() {
+            let group = schema.edge_group(name).expect("group names must match groups").clone();
+            geometry.edge_groups.insert(name.to_owned(), group);
+        }
+        Ok(geometry)
+    }
+
+    /// build a geo structure, that can be written as geo or bgeo
+    pub fn to_structure(&self) -> ReaderElement {
+        let mut root = Vec::new();
+        if let Some(fileversion) = &self.fileversion {
+            push_kv(&mut root, "fileversion", ReaderElement::Text(fileversion.as_str().into()));
+        }
+        push_kv(&mut root, "hasindex", ReaderElement::Bool(false));
+        push_kv(&mut root, "pointcount", ReaderElement::Int(self.point_count as i64));
+        push_kv(&mut root, "vertexcount", ReaderElement::Int(self.vertex_count() as i64));
+        push_kv(&mut root, "primitivecount", ReaderElement::Int(self.primitive_count() as i64));
+        if let Some(info) = &self.info {
+            push_kv(&mut root, "info", ReaderElement::KeyValueObject(info.clone()));
+        }
+
+        let indices = UniformArrayType::from_ints(self.vertex_points.iter().map(|x| *x as i64).collect());
+        let pointref = ReaderElement::Array(vec![
+            ReaderElement::Text("indices".into()),
+            ReaderElement::UniformArray(indices),
+        ]);
+        push_kv(&mut root, "topology", ReaderElement::Array(vec![ReaderElement::Text("pointref".into()), pointref]));
+
+        let mut attributes = Vec::new();
+        for (class, key) in [
+            (AttributeClass::Vertex, "vertexattributes"),
+            (AttributeClass::Point, "pointattributes"),
+            (AttributeClass::Primitive, "primitiveattributes"),
+            (AttributeClass::Detail, "globalattributes"),
+        ] {
+            if !self.attributes(class).is_empty() {
+                let class_attributes =
+                    self.attributes(class).iter().map(|(name, attr)| attribute_block(name, &attr.data, &attr.options));
+                push_kv(&mut attributes, key, ReaderElement::Array(class_attributes.collect()));
+            }
+        }
+        push_kv(&mut root, "attributes", ReaderElement::Array(attributes));
+        push_kv(&mut root, "primitives", ReaderElement::Array(self.primitive_blocks()));
+
+        for (class, key) in [
+            (AttributeClass::Point, "pointgroups"),
+            (AttributeClass::Vertex, "vertexgroups"),
+            (AttributeClass::Primitive, "primitivegroups"),
+        ] {
+            let class_groups = self.groups.iter().filter(|(c, _, _)| *c == class);
+            let blocks: Vec<_> = class_groups.map(|(_, name, group)| group_structure(name, group)).collect();
+            if !blocks.is_empty() {
+                push_kv(&mut root, key, ReaderElement::Array(blocks));
+            }
+        }
+        if !self.edge_groups.is_empty() {
+            let blocks = self.edge_groups.iter().map(|(name, group)| edge_group_structure(name, group)).collect();
+            push_kv(&mut root, "edgegroups", ReaderElement::Array(blocks));
+        }
+        ReaderElement::Array(root)
+    }
+
+    /// primitives as blocks of the structure, consecutive polygons go into polygon runs
+    fn primitive_blocks(&self) -> Vec<ReaderElement> {
+        let mut blocks = Vec::new();
+        // vertex range and vertex counts of polygons of the current run
+        let mut run: Option<(Range<usize>, Vec<usize>)> = None;
+        for primitive in self.primitives.iter() {
+            match primitive {
+                GeoPrimitive::Polygon(vertices) => match &mut run {
+                    Some((run_vertices, counts)) if run_vertices.end == vertices.start => {
+                        run_vertices.end = vertices.end;
+                        counts.push(vertices.len());
+                    }
+                    _ => {
+                        blocks.extend(run.take().map(|(run_vertices, counts)| polygon_run(run_vertices.start, &counts)));
+                        run = Some((vertices.clone(), vec![vertices.len()]));
+                    }
+                },
+                GeoPrimitive::Unsupported(block) => {
+                    blocks.extend(run.take().map(|(run_vertices, counts)| polygon_run(run_vertices.start, &counts)));
+                    blocks.extend(block.as_deref().cloned());
+                }
+            }
+        }
+        blocks.extend(run.take().map(|(run_vertices, counts)| polygon_run(run_vertices.start, &counts)));
+        blocks
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.point_count
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_points.len()
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitives.len()
+    }
+
+    /// get number of elements of given class
+    pub fn element_count(&self, class: AttributeClass) -> usize {
+        match class {
+            AttributeClass::Point => self.point_count(),
+            AttributeClass::Vertex => self.vertex_count(),
+            AttributeClass::Primitive => self.primitive_count(),
+            AttributeClass::Detail => 1,
+        }
+    }
+
+    /// get point number of the point given vertex belongs to
+    pub fn vtx_to_ptnum(&self, vtx_num: usize) -> usize {
+        self.vertex_points[vtx_num]
+    }
+
+    pub fn primitives(&self) -> &[GeoPrimitive] {
+        &self.primitives
+    }
+
+    /// polygons with their primitive numbers
+    pub fn polygons(&self) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+        self.primitives.iter().enumerate().filter_map(|(number, primitive)| match primitive {
+            GeoPrimitive::Polygon(vertices) => Some((number, vertices.clone())),
+            GeoPrimitive::Unsupported(_) => None,
+        })
+    }
+
+    /// attribute table of given class, in the order attributes were added
+    pub fn attributes(&self, class: AttributeClass) -> &OrderedMap<String, GeometryAttribute> {
+        match class {
+            AttributeClass::Point => &self.point_attributes,
+            AttributeClass::Vertex => &self.vertex_attributes,
+            AttributeClass::Primitive => &self.primitive_attributes,
+            AttributeClass::Detail => &self.detail_attributes,
+        }
+    }
+
+    fn attributes_mut(&mut self, class: AttributeClass) -> &mut OrderedMap<String, GeometryAttribute> {
+        match class {
+            AttributeClass::Point => &mut self.point_attributes,
+            AttributeClass::Vertex => &mut self.vertex_attributes,
+            AttributeClass::Primitive => &mut self.primitive_attributes,
+            AttributeClass::Detail => &mut self.detail_attributes,
+        }
+    }
+
+    pub fn attribute(&self, class: AttributeClass, name: &str) -> Option<&GeoAttributeKind> {
+        self.attributes(class).get(name).map(|x| &x.data)
+    }
+
+    /// attribute values can be changed, but number of elements must stay the same
+    pub fn attribute_mut(&mut self, class: AttributeClass, name: &str) -> Option<&mut GeoAttributeKind> {
+        self.attributes_mut(class).get_mut(name).map(|x| &mut x.data)
+    }
+
+    pub fn attribute_names(&self, class: AttributeClass) -> impl Iterator<Item = &str> {
+        self.attributes(class).keys().map(|x| x.as_str())
+    }
+
+    /// add an attribute, or replace the one with the same name, the old one is returned then
+    ///
+    /// attribute must have a value for every element of its class
+    pub fn insert_attribute(
+        &mut self,
+        class: AttributeClass,
+        name: &str,
+        attr: GeometryAttribute,
+    ) -> Option<GeometryAttribute> {
+        assert_eq!(self.element_count(class), attr.data.len(), "{} attribute {} has wrong number of elements", class, name);
+        self.attributes_mut(class).insert(name.to_owned(), attr)
+    }
+
+    pub fn remove_attribute(&mut self, class: AttributeClass, name: &str) -> Option<GeometryAttribute> {
+        self.attributes_mut(class).remove(name)
+    }
+
+    /// add points, attribute values of new points are zeroes, returns number of the first new point
+    pub fn add_points(&mut self, count: usize) -> usize {
+        let first = self.point_count;
+        self.point_count += count;
+        self.resize_attributes(AttributeClass::Point);
+        first
+    }
+
+    /// add polygon made of given points, new vertices are added for it, returns its primitive number
+    pub fn add_polygon(&mut self, points: &[usize]) -> usize {
+        assert!(points.iter().all(|x| *x < self.point_count), "polygon points must exist");
+        let start = self.vertex_points.len();
+        self.vertex_points.extend_from_slice(points);
+        self.primitives.push(GeoPrimitive::Polygon(start..self.vertex_points.len()));
+        self.resize_attributes(AttributeClass::Vertex);
+        self.resize_attributes(AttributeClass::Primitive);
+        self.primitives.len() - 1
+    }
+
+    /// get names of groups of given class, in the order they were added
+    pub fn group_names(&self, class: AttributeClass) -> impl Iterator<Item = &str> {
+        self.groups.iter().filter(move |(c, _, _)| *c == class).map(|(_, name, _)| name.as_str())
+    }
+
+    pub fn group(&self, class: AttributeClass, name: &str) -> Option<&ElementGroup> {
+        self.groups.iter().find(|(c, n, _)| *c == class && n == name).map(|(_, _, group)| group)
+    }
+
+    /// add a group, or replace the one with the same name, the old one is returned then
+    ///
+    /// group must have a flag for every element of its class, detail groups are not a thing
+    pub fn insert_group(&mut self, class: AttributeClass, name: &str, group: ElementGroup) -> Option<ElementGroup> {
+        assert!(class != AttributeClass::Detail, "there are no detail groups");
+        assert_eq!(self.element_count(class), group.members().len(), "{} group {} has wrong number of elements", class, name);
+        match self.groups.iter_mut().find(|(c, n, _)| *c == class && n == name) {
+            Some((_, _, existing)) => Some(std::mem::replace(existing, group)),
+            None => {
+                self.groups.push((class, name.to_owned(), group));
+                None
+            }
+        }
+    }
+
+    pub fn remove_group(&mut self, class: AttributeClass, name: &str) -> Option<ElementGroup> {
+        let index = self.groups.iter().position(|(c, n, _)| *c == class && n == name)?;
+        Some(self.groups.remove(index).2)
+    }
+
+    pub fn edge_group_names(&self) -> impl Iterator<Item = &str> {
+        self.edge_groups.keys().map(|x| x.as_str())
+    }
+
+    pub fn edge_group(&self, name: &str) -> Option<&EdgeGroup> {
+        self.edge_groups.get(name)
+    }
+
+    /// add an edge group, or replace the one with the same name, the old one is returned then
+    pub fn insert_edge_group(&mut self, name: &str, group: EdgeGroup) -> Option<EdgeGroup> {
+        assert!(
+            group.edges().iter().all(|(a, b)| *a < self.point_count && *b < self.point_count),
+            "edge group {} points must exist",
+            name
+        );
+        self.edge_groups.insert(name.to_owned(), group)
+    }
+
+    pub fn remove_edge_group(&mut self, name: &str) -> Option<EdgeGroup> {
+        self.edge_groups.remove(name)
+    }
+
+    fn resize_attributes(&mut self, class: AttributeClass) {
+        let count = self.element_count(class);
+        for (_, attr) in self.attributes_mut(class).iter_mut() {
+            attr.data.resize(count);
+        }
+        for (_, _, group) in self.groups.iter_mut().filter(|(c, _, _)| *c == class) {
+            group.resize(count);
+        }
+    }
+}
+
+fn find_in_kv_array<'a>(arr: &'a ReaderElement, key: &str) -> Option<&'a ReaderElement> {
+    match arr {
+        ReaderElement::Array(arr) => arr.chunks_exact(2).find_map(|pair| match &pair[0] {
+            ReaderElement::Text(x) if x == key => Some(&pair[1]),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn polygon_run(start_vertex: usize, vertex_counts: &[usize]) -> ReaderElement {
+    // run length encoded vertex counts, as pairs of count and number of polygons with it
+    let mut rle: Vec<i64> = Vec::new();
+    for count in vertex_counts {
+        match rle.as_mut_slice() {
+            [.., last_count, repeats] if *last_count == *count as i64 => *repeats += 1,
+            _ => rle.extend([*count as i64, 1]),
+        }
+    }
+    let mut data = Vec::new();
+    push_kv(&mut data, "startvertex", ReaderElement::Int(start_vertex as i64));
+    push_kv(&mut data, "nprimitives", ReaderElement::Int(vertex_counts.len() as i64));
+    push_kv(&mut data, "nvertices_rle", ReaderElement::UniformArray(UniformArrayType::from_ints(rle)));
+    ReaderElement::Array(vec![
+        ReaderElement::Array(vec![ReaderElement::Text("type".into()), ReaderElement::Text("Polygon_run".into())]),
+        ReaderElement::Array(data),
+    ])
+}
+
+/// group in the same layout houdini writes it in, unordered groups as a flag per element
+fn group_structure(name: &str, group: &ElementGroup) -> ReaderElement {
+    let selection = if group.is_ordered() {
+        let order = UniformArrayType::UniformArrayTi32(group.iter().map(|x| x as i32).collect());
+        vec![ReaderElement::Text("ordered".into()), typed_group_values("i32", order)]
+    } else {
+        let flags = UniformArrayType::UniformArrayTi8(group.members().iter().map(|x| *x as i8).collect());
+        vec![ReaderElement::Text("unordered".into()), typed_group_values("i8", flags)]
+    };
+    ReaderElement::Array(vec![
+        ReaderElement::Array(vec![ReaderElement::Text("name".into()), ReaderElement::Text(name.into())]),
+        ReaderElement::Array(vec![ReaderElement::Text("selection".into()), ReaderElement::Array(selection)]),
+    ])
+}
+
+fn typed_group_values(value_type: &str, values: UniformArrayType) -> ReaderElement {
+    ReaderElement::Array(vec![ReaderElement::Text(value_type.into()), ReaderElement::UniformArray(values)])
+}
+
+/// edge group as a flat list of point number pairs
+fn edge_group_structure(name: &str, group: &EdgeGroup) -> ReaderElement {
+    let points = group.edges().iter().flat_map(|(a, b)| [*a as i64, *b as i64]).collect();
+    ReaderElement::Array(vec![
+        ReaderElement::Array(vec![ReaderElement::Text("name".into()), ReaderElement::Text(name.into())]),
+        ReaderElement::Array(vec![
+            ReaderElement::Text("points".into()),
+            ReaderElement::UniformArray(UniformArrayType::from_ints(points)),
+        ]),
+    ])
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::houdini_geo_schema::{GeoAttribute, TupleGeoAttribute};
+
+    #[test]
+    fn polygons_are_grouped_into_runs() {
+        let mut geometry = Geometry::new();
+        geometry.add_points(4);
+        geometry.add_polygon(&[0, 1, 2]);
+        geometry.add_polygon(&[0, 2, 3]);
+        geometry.add_polygon(&[0, 1, 2, 3]);
+        let blocks = geometry.primitive_blocks();
+        assert_eq!(1, blocks.len());
+        let data = match &blocks[0] {
+            ReaderElement::Array(x) => &x[1],
+            x => panic!("unexpected block {:?}", x),
+        };
+        assert!(matches!(
+            find_in_kv_array(data, "nvertices_rle"),
+            Some(ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(x))) if *x == [3, 2, 4, 1]
+        ));
+    }
+
+    #[test]
+    fn new_elements_extend_attributes() {
+        let mut geometry = Geometry::new();
+        geometry.add_points(2);
+        let p = GeoAttributeKind::Float64(TupleGeoAttribute::new(3, vec![1.0; 6]));
+        geometry.insert_attribute(AttributeClass::Point, "P", GeometryAttribute::new(p));
+        geometry.add_points(1);
+        match geometry.attribute(AttributeClass::Point, "P") {
+            Some(GeoAttributeKind::Float64(x)) => assert_eq!([0.0; 3], x.value(2)),
+            _ => panic!("P must be there"),
+        }
+    }
+}
@@ -1,22 +1,36 @@
 // So far it's a very limited parser, just enough for stl, but with slight thought of the future
 
 use core::num;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 use std::ops::{Deref, Range};
 use std::slice::ChunksExactMut;
 use std::thread::Scope;
 
 use crate::convert_from_trait::ConvertFromAll;
+use crate::diagnostics;
+use crate::f16_half;
 use crate::geo_struct::{ReaderElement, ReaderElementPointer, UniformArrayType};
+use crate::shared_str::SharedStr;
+use crate::ordered_map::OrderedMap;
 
 pub struct HoudiniGeoSchemaParser<'a> {
     structure: &'a ReaderElement,
     _point_attributes: Option<HashMap<&'a str, GeoAttributeKind>>,
     _vertex_attributes: Option<HashMap<&'a str, GeoAttributeKind>>,
     _prim_attributes: Option<HashMap<&'a str, GeoAttributeKind>>,
+    _detail_attributes: Option<HashMap<&'a str, GeoAttributeKind>>,
     _point_attribute_names_cached: Option<Vec<&'a str>>,
+    /// attributes that are in the structure, but their data was skipped while parsing
+    _skipped_attributes: Vec<(AttributeClass, &'a str)>,
     _vertex_nums_to_point_nums: Vec<usize>,
     _polygons: Option<Vec<GeoPolygon>>,
+    /// primitive blocks of types that are not supported, with number of the first primitive and count
+    _unsupported_primitive_blocks: Vec<(usize, usize, &'a ReaderElement)>,
+    /// point, vertex and primitive groups, in the order they are in the structure
+    _groups: Option<Vec<(AttributeClass, &'a str, ElementGroup)>>,
+    _edge_groups: Option<Vec<(&'a str, EdgeGroup)>>,
     _prim_count: usize,
     _point_count: usize,
     _vertex_count: usize,
@@ -26,9 +40,126 @@ pub struct HoudiniGeoSchemaParser<'a> {
 pub struct TupleGeoAttribute<T: Copy> {
     tuple_size: usize,
     data: Vec<T>,
+    /// storage values had in the structure, they are written back in it too
+    storage: AttributeStorage,
+    /// default value, either one value for all tuple components or a whole tuple, empty means zeroes
+    defaults: Vec<T>,
     path_to_element: ReaderElementPointer,
 }
 
+/// storage of numeric attribute values, as houdini names it
+///
+/// values are always kept as f64 or i64 in memory, storage is what they are read from and written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeStorage {
+    Uint8,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Fpreal16,
+    Fpreal32,
+    Fpreal64,
+}
+
+impl AttributeStorage {
+    pub fn from_name(name: &str) -> Option<AttributeStorage> {
+        match name {
+            "uint8" => Some(AttributeStorage::Uint8),
+            "int8" => Some(AttributeStorage::Int8),
+            "int16" => Some(AttributeStorage::Int16),
+            "int32" => Some(AttributeStorage::Int32),
+            "int64" => Some(AttributeStorage::Int64),
+            "fpreal16" => Some(AttributeStorage::Fpreal16),
+            "fpreal32" => Some(AttributeStorage::Fpreal32),
+            "fpreal64" => Some(AttributeStorage::Fpreal64),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttributeStorage::Uint8 => "uint8",
+            AttributeStorage::Int8 => "int8",
+            AttributeStorage::Int16 => "int16",
+            AttributeStorage::Int32 => "int32",
+            AttributeStorage::Int64 => "int64",
+            AttributeStorage::Fpreal16 => "fpreal16",
+            AttributeStorage::Fpreal32 => "fpreal32",
+            AttributeStorage::Fpreal64 => "fpreal64",
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, AttributeStorage::Fpreal16 | AttributeStorage::Fpreal32 | AttributeStorage::Fpreal64)
+    }
+
+    /// value as it is after being written in this storage and read back, ints are left as they are
+    pub fn round_float(&self, value: f64) -> f64 {
+        match self {
+            AttributeStorage::Fpreal16 => f16_half::half_from_le_bytes(f16_half::half_to_le_bytes(value as f32)) as f64,
+            AttributeStorage::Fpreal32 => value as f32 as f64,
+            _ => value,
+        }
+    }
+
+    /// float values encoded in this storage, it must be a float storage
+    pub(crate) fn encode_floats(&self, data: Vec<f64>) -> UniformArrayType {
+        match self {
+            AttributeStorage::Fpreal16 => UniformArrayType::UniformArrayTf16(data.iter().map(|x| *x as f32).collect()),
+            AttributeStorage::Fpreal32 => UniformArrayType::UniformArrayTf32(data.iter().map(|x| *x as f32).collect()),
+            _ => UniformArrayType::UniformArrayTf64(data),
+        }
+    }
+
+    /// int values encoded in this storage, it must be an int storage, values that do not fit are clamped with a warning
+    pub(crate) fn encode_ints(&self, data: Vec<i64>) -> UniformArrayType {
+        let (min, max) = match self {
+            AttributeStorage::Uint8 => (u8::MIN.into(), u8::MAX.into()),
+            AttributeStorage::Int8 => (i8::MIN.into(), i8::MAX.into()),
+            AttributeStorage::Int16 => (i16::MIN.into(), i16::MAX.into()),
+            AttributeStorage::Int32 => (i32::MIN.into(), i32::MAX.into()),
+            _ => (i64::MIN, i64::MAX),
+        };
+        let out_of_range = data.iter().filter(|x| !(min..=max).contains(*x)).count();
+        if out_of_range > 0 {
+            diagnostics::warning(&format!(
+                "{} values do not fit {} storage, clamping them to [{}, {}]",
+                out_of_range, self, min, max
+            ));
+        }
+        let data = data.into_iter().map(|x| x.clamp(min, max));
+        match self {
+            AttributeStorage::Uint8 => UniformArrayType::UniformArrayTu8(data.map(|x| x as u8).collect()),
+            AttributeStorage::Int8 => UniformArrayType::UniformArrayTi8(data.map(|x| x as i8).collect()),
+            AttributeStorage::Int16 => UniformArrayType::UniformArrayTi16(data.map(|x| x as i16).collect()),
+            AttributeStorage::Int32 => UniformArrayType::UniformArrayTi32(data.map(|x| x as i32).collect()),
+            _ => UniformArrayType::UniformArrayTi64(data.collect()),
+        }
+    }
+}
+
+impl fmt::Display for AttributeStorage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// types numeric attribute values are kept in
+pub trait NumericAttributeValue: Copy {
+    /// storage new attributes of this type are written in
+    const DEFAULT_STORAGE: AttributeStorage;
+}
+
+impl NumericAttributeValue for f64 {
+    const DEFAULT_STORAGE: AttributeStorage = AttributeStorage::Fpreal64;
+}
+
+impl NumericAttributeValue for i64 {
+    const DEFAULT_STORAGE: AttributeStorage = AttributeStorage::Int64;
+}
+
+#[derive(Clone)]
 pub struct TokenGeoAttribute {
     tokens: Vec<String>,
     data: Vec<usize>,
@@ -104,6 +235,91 @@ impl<'a, T: Copy> GeoAttribute<'a, [T]> for TupleGeoAttributeChunk<'a, T> {
     }
 }
 
+impl<T: NumericAttributeValue> TupleGeoAttribute<T> {
+    /// attribute that is not in any structure yet, data is tuples one after another
+    ///
+    /// it's stored as 64 bit, use set_storage to write it as something smaller
+    pub fn new(tuple_size: usize, data: Vec<T>) -> TupleGeoAttribute<T> {
+        assert!(tuple_size > 0 && data.len().is_multiple_of(tuple_size), "data must be whole tuples");
+        TupleGeoAttribute {
+            tuple_size,
+            data,
+            storage: T::DEFAULT_STORAGE,
+            defaults: Vec::new(),
+            path_to_element: ReaderElementPointer::new(),
+        }
+    }
+
+    /// all values, tuples one after another
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    /// storage values were read from, and will be written in
+    pub fn storage(&self) -> AttributeStorage {
+        self.storage
+    }
+
+    /// change storage attribute is written in, float attributes can only have float storages, ints - int ones
+    pub fn set_storage(&mut self, storage: AttributeStorage) -> Result<(), SchemaError> {
+        if T::DEFAULT_STORAGE.is_float() != storage.is_float() {
+            let kind = if T::DEFAULT_STORAGE.is_float() { "float" } else { "int" };
+            return Err(SchemaError::new(
+                format!("storage {} does not fit {} attribute values", storage, kind),
+                &self.path_to_element,
+            ));
+        }
+        self.storage = storage;
+        Ok(())
+    }
+
+    /// default value, one value for all tuple components or a whole tuple, empty if defaults are zeroes
+    pub fn defaults(&self) -> &[T] {
+        &self.defaults
+    }
+
+    /// set default value, new elements get it when attribute is resized
+    pub fn set_defaults(&mut self, defaults: Vec<T>) {
+        assert!(defaults.len() <= 1 || defaults.len() == self.tuple_size, "defaults must be one value or a whole tuple");
+        self.defaults = defaults;
+    }
+
+    /// default value of every tuple component
+    fn default_tuple(&self) -> Vec<T>
+    where
+        T: Default,
+    {
+        (0..self.tuple_size)
+            .map(|i| match self.defaults.len() {
+                0 => T::default(),
+                1 => self.defaults[0],
+                _ => self.defaults[i],
+            })
+            .collect()
+    }
+}
+
+impl TokenGeoAttribute {
+    /// attribute that is not in any structure yet, every element is an index into tokens
+    pub fn new(tokens: Vec<String>, indices: Vec<usize>) -> TokenGeoAttribute {
+        assert!(indices.iter().all(|x| *x < tokens.len()), "indices must point to tokens");
+        TokenGeoAttribute {
+            tokens,
+            data: indices,
+            path_to_element: ReaderElementPointer::new(),
+        }
+    }
+
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// index of token of every element
+    pub fn indices(&self) -> &[usize] {
+        &self.data
+    }
+}
+
 impl<'b, 'a: 'b, T: Copy> TupleGeoAttribute<T> {
     pub fn chunks_mut_scoped(&'a mut self, num_elements: usize) -> Vec<TupleGeoAttributeChunk<'b, T>> {// impl Iterator<Item = TupleGeoAttributeChunk<'b, T>> {
         let tuple_size = self.tuple_size;
@@ -126,8 +342,16 @@ impl<'a> GeoAttribute<'a, String> for TokenGeoAttribute {
         &self.tokens[shit]
     }
 
+    /// new strings are added to tokens, tokens that are no longer used are kept
     fn set_value(&mut self, number: usize, val: &String) {
-        panic!("not implemented yet!");
+        let token = match self.tokens.iter().position(|x| x == val) {
+            Some(x) => x,
+            None => {
+                self.tokens.push(val.clone());
+                self.tokens.len() - 1
+            }
+        };
+        self.data[number] = token;
     }
 
     fn tuple_size(&self) -> usize {
@@ -147,12 +371,126 @@ impl<'a> GeoAttribute<'a, String> for TokenGeoAttribute {
     }
 }
 
+#[derive(Clone)]
 pub enum GeoAttributeKind {
     Float64(TupleGeoAttribute<f64>),
     Int64(TupleGeoAttribute<i64>),
     String(TokenGeoAttribute),
 }
 
+impl GeoAttributeKind {
+    pub fn tuple_size(&self) -> usize {
+        match self {
+            GeoAttributeKind::Float64(attr) => attr.tuple_size(),
+            GeoAttributeKind::Int64(attr) => attr.tuple_size(),
+            GeoAttributeKind::String(attr) => attr.tuple_size(),
+        }
+    }
+
+    /// number of elements (not components) in the attribute
+    pub fn len(&self) -> usize {
+        match self {
+            GeoAttributeKind::Float64(attr) => attr.len(),
+            GeoAttributeKind::Int64(attr) => attr.len(),
+            GeoAttributeKind::String(attr) => attr.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// storage of numeric attributes, strings have none
+    pub fn storage(&self) -> Option<AttributeStorage> {
+        match self {
+            GeoAttributeKind::Float64(attr) => Some(attr.storage()),
+            GeoAttributeKind::Int64(attr) => Some(attr.storage()),
+            GeoAttributeKind::String(_) => None,
+        }
+    }
+
+    /// short human readable name of the value type
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            GeoAttributeKind::Float64(_) => "float",
+            GeoAttributeKind::Int64(_) => "int",
+            GeoAttributeKind::String(_) => "string",
+        }
+    }
+
+    /// point attribute to the given location in a structure
+    fn set_path(&mut self, path: ReaderElementPointer) {
+        match self {
+            GeoAttributeKind::Float64(attr) => attr.path_to_element = path,
+            GeoAttributeKind::Int64(attr) => attr.path_to_element = path,
+            GeoAttributeKind::String(attr) => attr.path_to_element = path,
+        }
+    }
+
+    /// change number of elements, new elements get defaults, or the first token for strings
+    pub fn resize(&mut self, len: usize) {
+        match self {
+            GeoAttributeKind::Float64(attr) => {
+                let default = attr.default_tuple();
+                attr.data.truncate(len * attr.tuple_size);
+                while attr.data.len() < len * attr.tuple_size {
+                    attr.data.extend_from_slice(&default);
+                }
+            }
+            GeoAttributeKind::Int64(attr) => {
+                let default = attr.default_tuple();
+                attr.data.truncate(len * attr.tuple_size);
+                while attr.data.len() < len * attr.tuple_size {
+                    attr.data.extend_from_slice(&default);
+                }
+            }
+            GeoAttributeKind::String(attr) => {
+                if attr.tokens.is_empty() && len > attr.data.len() {
+                    attr.tokens.push(String::new());
+                }
+                attr.data.resize(len, 0)
+            }
+        }
+    }
+}
+
+/// class of elements attribute belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeClass {
+    Point,
+    Vertex,
+    Primitive,
+    /// whole geometry, houdini calls those global attributes, they always have exactly one element
+    Detail,
+}
+
+impl AttributeClass {
+    pub const ALL: [AttributeClass; 4] =
+        [AttributeClass::Point, AttributeClass::Vertex, AttributeClass::Primitive, AttributeClass::Detail];
+
+    /// class from its name, houdini's short `prim` and `global` are accepted too
+    pub fn from_name(name: &str) -> Option<AttributeClass> {
+        match name {
+            "point" => Some(AttributeClass::Point),
+            "vertex" => Some(AttributeClass::Vertex),
+            "prim" | "primitive" => Some(AttributeClass::Primitive),
+            "detail" | "global" => Some(AttributeClass::Detail),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AttributeClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeClass::Point => write!(f, "point"),
+            AttributeClass::Vertex => write!(f, "vertex"),
+            AttributeClass::Primitive => write!(f, "primitive"),
+            AttributeClass::Detail => write!(f, "detail"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GeoVertex {
     pub ptnum: usize,
@@ -165,38 +503,497 @@ pub struct GeoPolygon {
     pub vertices: Vec<GeoVertex>,
 }
 
-fn get_from_kv_array<'a>(arr_elem: &'a ReaderElement, key: &str) -> Option<(&'a ReaderElement, usize)> {
-    // TODO: not optimal, better refactor with macros
-    get_from_any_kv_array(arr_elem, &[key])
+/// group of points, vertices or primitives
+///
+/// houdini stores most groups unordered, as a flag per element,
+/// ordered groups also remember the order elements were added in
+#[derive(Debug, Clone)]
+pub struct ElementGroup {
+    members: Vec<bool>,
+    order: Option<Vec<usize>>,
+}
+
+impl ElementGroup {
+    /// unordered group, with membership flag of every element
+    pub fn new(members: Vec<bool>) -> ElementGroup {
+        ElementGroup { members, order: None }
+    }
+
+    /// ordered group of given elements, out of element_count elements in total
+    pub fn from_ordered(element_count: usize, order: Vec<usize>) -> ElementGroup {
+        let mut members = vec![false; element_count];
+        for x in order.iter() {
+            assert!(*x < element_count, "group element {} is out of range 0..{}", x, element_count);
+            members[*x] = true;
+        }
+        ElementGroup {
+            members,
+            order: Some(order),
+        }
+    }
+
+    pub fn contains(&self, number: usize) -> bool {
+        self.members.get(number).copied().unwrap_or(false)
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.order.is_some()
+    }
+
+    /// number of elements in the group
+    pub fn len(&self) -> usize {
+        match &self.order {
+            Some(order) => order.len(),
+            None => self.members.iter().filter(|x| **x).count(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// numbers of elements in the group, in group order for ordered groups, ascending otherwise
+    pub fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match &self.order {
+            Some(order) => Box::new(order.iter().copied()),
+            None => Box::new(self.members.iter().enumerate().filter(|(_, x)| **x).map(|(i, _)| i)),
+        }
+    }
+
+    /// membership flag of every element
+    pub fn members(&self) -> &[bool] {
+        &self.members
+    }
+
+    /// new elements are not in the group
+    pub(crate) fn resize(&mut self, len: usize) {
+        self.members.resize(len, false);
+    }
+}
+
+/// group of edges, every edge is a pair of point numbers
+#[derive(Debug, Clone)]
+pub struct EdgeGroup {
+    edges: Vec<(usize, usize)>,
+}
+
+impl EdgeGroup {
+    pub fn new(edges: Vec<(usize, usize)>) -> EdgeGroup {
+        EdgeGroup { edges }
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// edges have no direction, so a-b is the same edge as b-a
+    pub fn contains(&self, a: usize, b: usize) -> bool {
+        self.edges.iter().any(|x| *x == (a, b) || *x == (b, a))
+    }
+
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+/// error in geo structure, when it does not follow the houdini geo schema we expect
+///
+/// path points to the offending element within the structure
+pub struct SchemaError {
+    pub message: String,
+    pub path: ReaderElementPointer,
+}
+
+impl SchemaError {
+    pub(crate) fn new(message: String, path: &ReaderElementPointer) -> SchemaError {
+        SchemaError {
+            message,
+            path: path.clone(),
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SchemaError: {} at '{}'", self.message, self.path)
+    }
+}
+
+impl fmt::Debug for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<SCHEMA ERROR HAPPENED: {} at '{}'>", self.message, self.path)
+    }
 }
 
-fn get_from_any_kv_array<'a>(arr_elem: &'a ReaderElement, keys: &[&str]) -> Option<(&'a ReaderElement, usize)> {
-    let mut is_key = false;
-    let mut next_one_is_the_shit = false;
+impl std::error::Error for SchemaError {}
+
+/// find value by key in houdini's kv array, returns the value and the pointer to it
+///
+/// * `path` - pointer to arr_elem itself
+fn get_from_kv_array<'a>(
+    arr_elem: &'a ReaderElement,
+    key: &str,
+    path: &ReaderElementPointer,
+) -> Result<Option<(&'a ReaderElement, ReaderElementPointer)>, SchemaError> {
+    get_from_any_kv_array(arr_elem, &[key], path)
+}
+
+fn get_from_any_kv_array<'a>(
+    arr_elem: &'a ReaderElement,
+    keys: &[&str],
+    path: &ReaderElementPointer,
+) -> Result<Option<(&'a ReaderElement, ReaderElementPointer)>, SchemaError> {
     let arr = if let ReaderElement::Array(x) = arr_elem {
         x
     } else {
-        panic!("bad schema! expected kv array, but it's not!");
+        return Err(SchemaError::new("expected kv array, but it's not!".to_owned(), path));
     };
 
-    for (i, elem) in arr.iter().enumerate() {
-        is_key = !is_key;
-        if next_one_is_the_shit {
-            return Some((elem, i));
+    for (i, pair) in arr.chunks(2).enumerate() {
+        match &pair[0] {
+            ReaderElement::Text(arr_key) if keys.contains(&arr_key.as_str()) => {
+                // lazy arrays are decoded here, so only values that are actually looked up get decoded
+                return Ok(pair.get(1).map(|value| (value.resolved(), path.with_kv_array_key(arr_key, 2 * i + 1))));
+            }
+            ReaderElement::Text(_) => (),
+            _ => {
+                return Err(SchemaError::new("kv array key is not a string".to_owned(), &path.with_array_index(2 * i)));
+            }
         }
-        if !is_key {
-            continue;
+    }
+    Ok(None)
+}
+
+/// same as get_from_kv_array, but missing key is an error
+fn require_from_kv_array<'a>(
+    arr_elem: &'a ReaderElement,
+    key: &str,
+    path: &ReaderElementPointer,
+) -> Result<(&'a ReaderElement, ReaderElementPointer), SchemaError> {
+    get_from_kv_array(arr_elem, key, path)?
+        .ok_or_else(|| SchemaError::new(format!("key '{}' not found", key), path))
+}
+
+fn get_int_from_kv_array(arr_elem: &ReaderElement, key: &str, path: &ReaderElementPointer) -> Result<i64, SchemaError> {
+    match require_from_kv_array(arr_elem, key, path)? {
+        (ReaderElement::Int(x), _) => Ok(*x),
+        (_, value_path) => Err(SchemaError::new(format!("'{}' is expected to be an int", key), &value_path)),
+    }
+}
+
+fn get_usize_from_kv_array(arr_elem: &ReaderElement, key: &str, path: &ReaderElementPointer) -> Result<usize, SchemaError> {
+    let x = get_int_from_kv_array(arr_elem, key, path)?;
+    x.try_into()
+        .map_err(|_| SchemaError::new(format!("'{}' is expected to be non-negative, got {}", key, x), path))
+}
+
+fn get_text_from_kv_array<'a>(
+    arr_elem: &'a ReaderElement,
+    key: &str,
+    path: &ReaderElementPointer,
+) -> Result<&'a SharedStr, SchemaError> {
+    match require_from_kv_array(arr_elem, key, path)? {
+        (ReaderElement::Text(x), _) => Ok(x),
+        (_, value_path) => Err(SchemaError::new(format!("'{}' is expected to be a string", key), &value_path)),
+    }
+}
+
+fn non_negative(x: i64, path: &ReaderElementPointer) -> Result<usize, SchemaError> {
+    x.try_into()
+        .map_err(|_| SchemaError::new(format!("expected a non-negative int, got {}", x), path))
+}
+
+/// convert index values into usizes, checking they are within 0..upper
+fn collect_indices<T: Copy + TryInto<usize>>(
+    values: &[T],
+    upper: usize,
+    path: &ReaderElementPointer,
+) -> Result<Vec<usize>, SchemaError> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, x)| match (*x).try_into() {
+            Ok(idx) if idx < upper => Ok(idx),
+            _ => Err(SchemaError::new(format!("index is out of range 0..{}", upper), &path.with_array_index(i))),
+        })
+        .collect()
+}
+
+/// elements of an array, whether it's a plain or a uniform array
+fn array_elements<'a>(elem: &'a ReaderElement, path: &ReaderElementPointer) -> Result<Cow<'a, [ReaderElement]>, SchemaError> {
+    match elem.resolved() {
+        ReaderElement::Array(x) => Ok(Cow::Borrowed(x.as_slice())),
+        ReaderElement::UniformArray(x) => Ok(Cow::Owned(x.to_elements())),
+        _ => Err(SchemaError::new("expected an array".to_owned(), path)),
+    }
+}
+
+/// group values are often given with their type, like ["i8",[1,0,1]], but can also be a bare array
+fn untyped_group_values<'a>(elem: &'a ReaderElement, path: &ReaderElementPointer) -> (&'a ReaderElement, ReaderElementPointer) {
+    match elem {
+        ReaderElement::Array(x) if x.len() == 2 && matches!(x[0], ReaderElement::Text(_)) => {
+            (x[1].resolved(), path.with_array_index(1))
+        }
+        _ => (elem, path.clone()),
+    }
+}
+
+/// parse group selection, it's either "unordered" with a flag per element, or "ordered" with element numbers
+///
+///  "selection",[
+///     "unordered",[
+///         "i8",[1,1,0,0]
+///     ]
+///  ]
+///
+/// flags can also be run length encoded, as ["boolRLE",[count,flag,count,flag...]]
+fn parse_group_selection(
+    selection: &ReaderElement,
+    selection_path: &ReaderElementPointer,
+    element_count: usize,
+) -> Result<ElementGroup, SchemaError> {
+    let flag = |x: &ReaderElement, path: &ReaderElementPointer| match x {
+        ReaderElement::Bool(b) => Ok(*b),
+        ReaderElement::Int(i) => Ok(*i != 0),
+        _ => Err(SchemaError::new("group flag is expected to be a bool or an int".to_owned(), path)),
+    };
+
+    if let Some((unordered, unordered_path)) = get_from_kv_array(selection, "unordered", selection_path)? {
+        let is_rle = match unordered {
+            ReaderElement::Array(x) => matches!(&x[..], [ReaderElement::Text(t), _] if t == "boolRLE"),
+            _ => false,
         };
-        if let ReaderElement::Text(arr_key) = elem {
-            if let Some(_) = keys.iter().find(|&&x| x == arr_key) {
-                // arr_key == key
-                next_one_is_the_shit = true;
+        let (values, values_path) = untyped_group_values(unordered, &unordered_path);
+        let values = array_elements(values, &values_path)?;
+        let mut members = Vec::with_capacity(element_count);
+        if is_rle {
+            if values.len() % 2 != 0 {
+                return Err(SchemaError::new(
+                    "boolRLE array must have an even number of entries".to_owned(),
+                    &values_path,
+                ));
+            }
+            for (pair_i, pair) in values.chunks(2).enumerate() {
+                let count_path = values_path.with_array_index(2 * pair_i);
+                let count = match &pair[0] {
+                    ReaderElement::Int(x) => non_negative(*x, &count_path)?,
+                    _ => return Err(SchemaError::new("rle count is not an int".to_owned(), &count_path)),
+                };
+                let value = flag(&pair[1], &values_path.with_array_index(2 * pair_i + 1))?;
+                if count > element_count - members.len() {
+                    return Err(SchemaError::new(
+                        format!("boolRLE runs cover more than {} elements", element_count),
+                        &count_path,
+                    ));
+                }
+                members.extend(std::iter::repeat_n(value, count));
             }
         } else {
-            panic!("bad schema! root array key is not a string {:?}", elem)
+            for (i, x) in values.iter().enumerate() {
+                members.push(flag(x, &values_path.with_array_index(i))?);
+            }
+        }
+        if members.len() != element_count {
+            return Err(SchemaError::new(
+                format!("expected {} group flags, but got {}", element_count, members.len()),
+                &values_path,
+            ));
+        }
+        Ok(ElementGroup::new(members))
+    } else if let Some((ordered, ordered_path)) = get_from_kv_array(selection, "ordered", selection_path)? {
+        let (values, values_path) = untyped_group_values(ordered, &ordered_path);
+        let numbers = array_elements(values, &values_path)?
+            .iter()
+            .enumerate()
+            .map(|(i, x)| match x {
+                ReaderElement::Int(x) => Ok(*x),
+                _ => Err(SchemaError::new("group element is not an int".to_owned(), &values_path.with_array_index(i))),
+            })
+            .collect::<Result<Vec<i64>, SchemaError>>()?;
+        Ok(ElementGroup::from_ordered(element_count, collect_indices(&numbers, element_count, &values_path)?))
+    } else {
+        Err(SchemaError::new("group selection is neither unordered nor ordered".to_owned(), selection_path))
+    }
+}
+
+/// parse edge group data, edges are stored as a flat list of point number pairs
+///
+///  "points",[0,1,1,3]
+fn parse_edge_group_points(
+    data: &ReaderElement,
+    data_path: &ReaderElementPointer,
+    point_count: usize,
+) -> Result<EdgeGroup, SchemaError> {
+    let (points, points_path) = match get_from_kv_array(data, "points", data_path)? {
+        Some(x) => x,
+        None => match get_from_kv_array(data, "selection", data_path)? {
+            Some((selection, selection_path)) => require_from_kv_array(selection, "points", &selection_path)?,
+            None => return Err(SchemaError::new("edge group has no points".to_owned(), data_path)),
+        },
+    };
+    let (values, values_path) = untyped_group_values(points, &points_path);
+    let numbers = array_elements(values, &values_path)?
+        .iter()
+        .enumerate()
+        .map(|(i, x)| match x {
+            ReaderElement::Int(x) => Ok(*x),
+            _ => Err(SchemaError::new("edge point is not an int".to_owned(), &values_path.with_array_index(i))),
+        })
+        .collect::<Result<Vec<i64>, SchemaError>>()?;
+    if numbers.len() % 2 != 0 {
+        return Err(SchemaError::new("edge points must come in pairs".to_owned(), &values_path));
+    }
+    let points = collect_indices(&numbers, point_count, &values_path)?;
+    Ok(EdgeGroup::new(points.chunks(2).map(|x| (x[0], x[1])).collect()))
+}
+
+/// what geometry has, without the geometry itself: element counts, info block and attribute headers
+///
+/// it's meant to be made from a structure parsed with ParseOptions::header_only, which is way faster
+/// than parsing the whole thing, but fully parsed structures work just as well
+#[derive(Debug)]
+pub struct GeoHeader {
+    pub point_count: usize,
+    pub vertex_count: usize,
+    pub primitive_count: usize,
+    /// info block as is, it has things like software, date and bounds
+    pub info: Option<OrderedMap<SharedStr, ReaderElement>>,
+    pub attributes: Vec<AttributeHeader>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttributeHeader {
+    pub class: AttributeClass,
+    pub name: String,
+    /// attribute type as houdini names it, like numeric or string
+    pub attrib_type: String,
+    /// storage and tuple size are not known if attribute data was skipped while parsing
+    pub storage: Option<String>,
+    pub tuple_size: Option<usize>,
+    /// attribute options as they are in the file, like {"type":{"type":"string","value":"vector"}}
+    pub options: OrderedMap<SharedStr, ReaderElement>,
+}
+
+impl AttributeHeader {
+    /// short human readable name of the value type, same as GeoAttributeKind::type_name gives
+    pub fn type_name(&self) -> &str {
+        match (self.attrib_type.as_str(), self.storage.as_deref()) {
+            ("string", _) => "string",
+            ("numeric", Some(storage)) if storage.starts_with("fpreal") => "float",
+            ("numeric", Some(storage)) if storage.starts_with("int") => "int",
+            (attrib_type, _) => attrib_type,
+        }
+    }
+}
+
+impl GeoHeader {
+    pub fn new(structure: &ReaderElement) -> Result<GeoHeader, SchemaError> {
+        let root = ReaderElementPointer::new();
+        let info = match get_from_kv_array(structure, "info", &root)? {
+            Some((ReaderElement::KeyValueObject(x), _)) => Some(x.clone()),
+            Some((_, path)) => return Err(SchemaError::new("info must be a map".to_owned(), &path)),
+            None => None,
+        };
+
+        let mut attributes = Vec::new();
+        if let Some((attribs, attribs_path)) = get_from_kv_array(structure, "attributes", &root)? {
+            for (class, key) in [
+                (AttributeClass::Point, "pointattributes"),
+                (AttributeClass::Vertex, "vertexattributes"),
+                (AttributeClass::Primitive, "primitiveattributes"),
+                (AttributeClass::Detail, "globalattributes"),
+            ] {
+                let (class_attribs, class_path) = match get_from_kv_array(attribs, key, &attribs_path)? {
+                    Some((ReaderElement::Array(x), path)) => (x, path),
+                    Some((_, path)) => return Err(SchemaError::new(format!("{} must be an array", key), &path)),
+                    None => continue,
+                };
+                for (i, attrib) in class_attribs.iter().enumerate() {
+                    let attrib_path = class_path.with_array_index(i);
+                    let (metadata, data) = match attrib {
+                        ReaderElement::Array(x) if x.len() == 2 => (&x[0], &x[1]),
+                        _ => {
+                            diagnostics::warning(&format!("unrecognized attribute block type at '{}', skipping", attrib_path));
+                            continue;
+                        }
+                    };
+                    let metadata_path = attrib_path.with_array_index(0);
+                    let data_path = attrib_path.with_array_index(1);
+                    let values = match data {
+                        ReaderElement::None => None,
+                        _ => get_from_any_kv_array(data, &["values", "indices"], &data_path)?,
+                    };
+                    let (storage, tuple_size) = match values {
+                        Some((values, values_path)) => (
+                            Some(get_text_from_kv_array(values, "storage", &values_path)?.to_string()),
+                            Some(get_usize_from_kv_array(values, "size", &values_path)?),
+                        ),
+                        None => (None, None),
+                    };
+                    let options = match get_from_kv_array(metadata, "options", &metadata_path)? {
+                        Some((ReaderElement::KeyValueObject(x), _)) => x.clone(),
+                        Some((_, path)) => return Err(SchemaError::new("options must be a map".to_owned(), &path)),
+                        None => OrderedMap::new(),
+                    };
+                    attributes.push(AttributeHeader {
+                        class,
+                        name: get_text_from_kv_array(metadata, "name", &metadata_path)?.to_string(),
+                        attrib_type: get_text_from_kv_array(metadata, "type", &metadata_path)?.to_string(),
+                        storage,
+                        tuple_size,
+                        options,
+                    });
+                }
+            }
         }
+
+        Ok(GeoHeader {
+            point_count: get_usize_from_kv_array(structure, "pointcount", &root)?,
+            vertex_count: get_usize_from_kv_array(structure, "vertexcount", &root)?,
+            primitive_count: get_usize_from_kv_array(structure, "primitivecount", &root)?,
+            info,
+            attributes,
+        })
+    }
+
+    /// attributes of given class, in the order they are in the file
+    pub fn class_attributes(&self, class: AttributeClass) -> impl Iterator<Item = &AttributeHeader> {
+        self.attributes.iter().filter(move |x| x.class == class)
+    }
+
+    /// text value from the info block, like software or date
+    pub fn info_text(&self, key: &str) -> Option<&str> {
+        match self.info.as_ref()?.get(key)? {
+            ReaderElement::Text(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// bounding box from the info block, as min and max corners
+    pub fn bounds(&self) -> Option<([f64; 3], [f64; 3])> {
+        let bounds = match self.info.as_ref()?.get("bounds")?.resolved() {
+            ReaderElement::Array(x) => Cow::Borrowed(x.as_slice()),
+            ReaderElement::UniformArray(x) => Cow::Owned(x.to_elements()),
+            _ => return None,
+        };
+        if bounds.len() != 6 {
+            return None;
+        }
+        let mut values = [0.0; 6];
+        for (value, x) in values.iter_mut().zip(bounds.iter()) {
+            *value = match x {
+                ReaderElement::Float(f) => *f,
+                ReaderElement::Int(i) => *i as f64,
+                _ => return None,
+            };
+        }
+        // houdini stores them as xmin, xmax, ymin, ymax, zmin, zmax
+        Some(([values[0], values[2], values[4]], [values[1], values[3], values[5]]))
     }
-    return None;
 }
 
 impl<'a> HoudiniGeoSchemaParser<'a> {
@@ -206,73 +1003,72 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
     /// you have to parse them beforehand explicitly
     ///
     /// you should only parse what you need for particular geo conversion
-    pub fn new(read_structure: &'a ReaderElement) -> HoudiniGeoSchemaParser<'a> {
-        let indices = if let Some((topo, _)) = get_from_kv_array(read_structure, "topology") {
-            if let Some((pointref, _)) = get_from_kv_array(topo, "pointref") {
-                match get_from_kv_array(pointref, "indices") {
-                    Some((ReaderElement::Array(idxs), _)) => Vec::from_iter(idxs.iter().map(|x| -> usize {
-                        if let ReaderElement::Int(u) = x {
-                            *u as usize
-                        } else {
-                            panic!("bad schema! index no int!");
+    pub fn new(read_structure: &'a ReaderElement) -> Result<HoudiniGeoSchemaParser<'a>, SchemaError> {
+        let root = ReaderElementPointer::new();
+        let prim_count = get_usize_from_kv_array(read_structure, "primitivecount", &root)?;
+        let point_count = get_usize_from_kv_array(read_structure, "pointcount", &root)?;
+        let vertex_count = get_usize_from_kv_array(read_structure, "vertexcount", &root)?;
+
+        let mut indices = Vec::new();
+        if let Some((topo, topo_path)) = get_from_kv_array(read_structure, "topology", &root)? {
+            if let Some((pointref, pointref_path)) = get_from_kv_array(topo, "pointref", &topo_path)? {
+                if let Some((idxs, path)) = get_from_kv_array(pointref, "indices", &pointref_path)? {
+                    indices = match idxs {
+                        ReaderElement::Array(idxs) => {
+                            let ints = idxs
+                                .iter()
+                                .enumerate()
+                                .map(|(i, x)| {
+                                    if let ReaderElement::Int(u) = x {
+                                        Ok(*u)
+                                    } else {
+                                        Err(SchemaError::new("index is not an int".to_owned(), &path.with_array_index(i)))
+                                    }
+                                })
+                                .collect::<Result<Vec<i64>, SchemaError>>()?;
+                            collect_indices(&ints, point_count, &path)?
                         }
-                    })),
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vec)), _)) => {
-                        vec.iter().map(|x| -> usize { *x as usize }).collect()
-                    }
-                    Some(_) => panic!("bad schema! index no int!"), // TODO impl uniform arr
-                    None => Vec::new(),
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vec)) => {
+                            collect_indices(vec, point_count, &path)?
+                        }
+                        _ => return Err(SchemaError::new("indices are expected to be an array of ints".to_owned(), &path)),
+                    };
                 }
-            } else {
-                Vec::new()
             }
-        } else {
-            Vec::new()
-        };
-
-        let prim_count: usize = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(read_structure, "primitivecount") {
-            (*x).try_into().expect("bad primitivecount")
-        } else {
-            panic!("bad schema! primitivecount not found");
-        };
-        let point_count: usize = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(read_structure, "pointcount") {
-            (*x).try_into().expect("bad pointcount")
-        } else {
-            panic!("bad schema! pointcount not found");
-        };
-        let vertex_count: usize = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(read_structure, "vertexcount") {
-            (*x).try_into().expect("bad vertexcount")
-        } else {
-            panic!("bad schema! vertexcount not found");
-        };
+        }
 
-        HoudiniGeoSchemaParser {
+        Ok(HoudiniGeoSchemaParser {
             structure: read_structure,
             _point_attributes: None,
             _vertex_attributes: None,
             _prim_attributes: None,
+            _detail_attributes: None,
             _point_attribute_names_cached: None,
+            _skipped_attributes: Vec::new(),
             _vertex_nums_to_point_nums: indices,
             _polygons: None,
+            _unsupported_primitive_blocks: Vec::new(),
+            _groups: None,
+            _edge_groups: None,
             _prim_count: prim_count,
             _point_count: point_count,
             _vertex_count: vertex_count,
-        }
+        })
     }
 
     /// parse values into a linear array of target type
@@ -295,10 +1091,11 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
     /// even if element is tuple, we still produce liear array here, an later will use tuple_size for proper indexing
     fn parse_values<T>(
         values: &ReaderElement,
+        values_path: &ReaderElementPointer,
         tuple_size: usize,
-        reader_element_mapper: &dyn Fn(&ReaderElement) -> T,
+        reader_element_mapper: &dyn Fn(&ReaderElement) -> Option<T>,
         number_of_elements: usize,
-    ) -> Vec<T>
+    ) -> Result<Vec<T>, SchemaError>
     where
         T: Copy
             + Default
@@ -311,91 +1108,128 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
             + ConvertFromAll<i32>
             + ConvertFromAll<i64>,
     {
-        let mut attrib_values: Vec<T> = if number_of_elements == 0 {
-            Vec::new()
-        } else {
-            Vec::with_capacity(
-                number_of_elements
-                    * if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(values, "size") {
-                        *x as usize
-                    } else {
-                        1
-                    },
-            )
+        let map_elements = |elements: &[ReaderElement], path: &ReaderElementPointer| -> Result<Vec<T>, SchemaError> {
+            elements
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    reader_element_mapper(x)
+                        .ok_or_else(|| SchemaError::new("unexpected type of value".to_owned(), &path.with_array_index(i)))
+                })
+                .collect()
         };
 
-        if let Some((ReaderElement::Array(tuples), _)) = get_from_kv_array(values, "tuples") {
+        let mut attrib_values: Vec<T> = Vec::new();
+
+        if let Some((ReaderElement::TupleArray(tuples), tuples_path)) = get_from_kv_array(values, "tuples", values_path)? {
+            // tuples packed by parser
+            if tuples.tuple_size() != tuple_size {
+                return Err(SchemaError::new(
+                    format!("value tuple is not of declared size {}", tuple_size),
+                    &tuples_path.with_array_index(0),
+                ));
+            }
+            let values = tuples.values();
+            attrib_values = (0..values.len())
+                .map(|i| {
+                    reader_element_mapper(&values.element(i)).ok_or_else(|| {
+                        let tuple_path = tuples_path.with_array_index(i / tuple_size);
+                        SchemaError::new(
+                            "unexpected type of value".to_owned(),
+                            &tuple_path.with_array_index(i % tuple_size),
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        } else if let Some((ReaderElement::Array(tuples), tuples_path)) = get_from_kv_array(values, "tuples", values_path)? {
             // so it's key tuples
-            for tuple in tuples {
-                let tuple = if let ReaderElement::Array(x) = tuple {
-                    x
-                } else {
-                    panic!("bad schema! value tuple is no tuple")
+            attrib_values.reserve(tuples.len() * tuple_size);
+            for (i, tuple) in tuples.iter().enumerate() {
+                let tuple_path = tuples_path.with_array_index(i);
+                let tuple = match tuple.resolved() {
+                    ReaderElement::Array(x) => Cow::Borrowed(x.as_slice()),
+                    ReaderElement::UniformArray(x) => Cow::Owned(x.to_elements()),
+                    _ => return Err(SchemaError::new("value tuple is no tuple".to_owned(), &tuple_path)),
                 };
                 if tuple.len() != tuple_size {
-                    panic!("bad schema! value tuple is not of declared size")
+                    return Err(SchemaError::new(
+                        format!("value tuple is not of declared size {}", tuple_size),
+                        &tuple_path,
+                    ));
                 }
 
-                // TODO: wtf is this mess below? is it really the rust way to go?
-                attrib_values.extend(tuple.iter().map(reader_element_mapper));
+                attrib_values.extend(map_elements(&tuple, &tuple_path)?);
             }
-        } else if let Some((ReaderElement::Array(arrays), _)) = get_from_kv_array(values, "arrays") {
+        } else if let Some((ReaderElement::Array(arrays), arrays_path)) = get_from_kv_array(values, "arrays", values_path)? {
             // so it's key arrays
             // for now i know only of case of size=1, no idea when it can be not 1 and what would that mean
-            let values_size = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(values, "size") {
-                *x
-            } else {
-                panic!("bad schema! no size in values");
-            };
+            let values_size = get_int_from_kv_array(values, "size", values_path)?;
             // can values_size differ from tuple_size? haven't seen such cases
             if values_size != 1 {
-                panic!("no idea how to parse values with arrays and size!=1");
+                return Err(SchemaError::new(
+                    "no idea how to parse values with arrays and size!=1".to_owned(),
+                    values_path,
+                ));
             }
-            let indices = if let ReaderElement::Array(x) = &arrays[0] {
-                x
-            } else {
-                panic!("bad schema! arrays had no arrays!")
+            let indices = match arrays.first().map(ReaderElement::resolved) {
+                Some(ReaderElement::Array(x)) => Cow::Borrowed(x.as_slice()),
+                Some(ReaderElement::UniformArray(x)) => Cow::Owned(x.to_elements()),
+                _ => return Err(SchemaError::new("arrays had no arrays!".to_owned(), &arrays_path)),
             };
-            attrib_values.extend(indices.iter().map(reader_element_mapper));
-        } else if let Some((raw_page_data, _)) = get_from_kv_array(values, "rawpagedata") {
+            attrib_values.extend(map_elements(&indices, &arrays_path.with_array_index(0))?);
+        } else if let Some((raw_page_data, raw_path)) = get_from_kv_array(values, "rawpagedata", values_path)? {
             let segment = match raw_page_data {
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTf16(vec)) => {
-                    Self::parse_rawpagedata::<T, f32>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, f32>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTf32(vec)) => {
-                    Self::parse_rawpagedata::<T, f32>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, f32>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(vec)) => {
-                    Self::parse_rawpagedata::<T, f64>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, f64>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vec)) => {
-                    Self::parse_rawpagedata::<T, i8>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, i8>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vec)) => {
-                    Self::parse_rawpagedata::<T, i16>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, i16>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vec)) => {
-                    Self::parse_rawpagedata::<T, i32>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, i32>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vec)) => {
-                    Self::parse_rawpagedata::<T, i64>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, i64>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vec)) => {
-                    Self::parse_rawpagedata::<T, u8>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, u8>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vec)) => {
-                    Self::parse_rawpagedata::<T, u16>(values, vec, number_of_elements, tuple_size)
+                    Self::parse_rawpagedata::<T, u16>(values, values_path, vec, number_of_elements, tuple_size)
                 }
                 _ => {
-                    panic!("Unexpected array type");
+                    return Err(SchemaError::new("unexpected rawpagedata array type".to_owned(), &raw_path));
                 }
             };
-            attrib_values.extend(segment);
+            attrib_values = segment?;
         } else {
-            panic!("rawpagedata support not implemented yet");
+            return Err(SchemaError::new(
+                "values have neither tuples, arrays nor rawpagedata".to_owned(),
+                values_path,
+            ));
         }
 
-        attrib_values
+        if attrib_values.len() != number_of_elements * tuple_size {
+            return Err(SchemaError::new(
+                format!(
+                    "expected {} values of size {}, but got {} numbers",
+                    number_of_elements,
+                    tuple_size,
+                    attrib_values.len()
+                ),
+                values_path,
+            ));
+        }
+        Ok(attrib_values)
     }
 
     /// this function deals with rawpagedata
@@ -414,83 +1248,93 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
     /// raw_page_data must be from values. it's in args just to not get it twice
     fn parse_rawpagedata<T: Copy + Default + ConvertFromAll<K>, K: Copy>(
         values: &ReaderElement,
+        values_path: &ReaderElementPointer,
         raw_page_array: &Vec<K>,
         number_of_elements: usize,
         tuple_size: usize,
-    ) -> Vec<T> {
+    ) -> Result<Vec<T>, SchemaError> {
         // rawpackagedata case
 
-        let size = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(values, "size") {
-            *x as usize
-        } else {
-            panic!("bad schemd! vector size not found!");
-        };
-        let page_size = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(values, "pagesize") {
-            *x as usize
-        } else {
-            panic!("bad schemd! vector size not found!");
-        };
-        let packing = match get_from_kv_array(values, "packing") {
-            Some((ReaderElement::Array(packing_array), _)) => {
+        let size = get_usize_from_kv_array(values, "size", values_path)?;
+        let page_size = get_usize_from_kv_array(values, "pagesize", values_path)?;
+        if page_size == 0 {
+            return Err(SchemaError::new("pagesize must not be 0".to_owned(), values_path));
+        }
+        let packing: Vec<usize> = match get_from_kv_array(values, "packing", values_path)? {
+            Some((ReaderElement::Array(packing_array), packing_path)) => {
                 packing_array
                     .iter()
-                    .map(|x| {
+                    .enumerate()
+                    .map(|(i, x)| {
                         match x {
-                            ReaderElement::Int(v) => *v as usize,
-                            ReaderElement::Bool(v) => *v as usize, // wtf is this case even possible?
-                            _ => {
-                                panic!("bad schema! unexpected data type in packing element array");
-                            }
+                            ReaderElement::Int(v) if *v >= 0 => Ok(*v as usize),
+                            ReaderElement::Bool(v) => Ok(*v as usize), // wtf is this case even possible?
+                            _ => Err(SchemaError::new(
+                                "unexpected data type in packing element array".to_owned(),
+                                &packing_path.with_array_index(i),
+                            )),
                         }
                     })
-                    .collect()
+                    .collect::<Result<_, _>>()?
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(v)), _)) => {
-                v.into_iter().map(|x| *x as usize).collect()
+                v.iter().map(|x| *x as usize).collect()
             }
             None => vec![size],
-            _ => {
-                panic!("bad schema! unknown packing data representation");
+            Some((_, packing_path)) => {
+                return Err(SchemaError::new("unknown packing data representation".to_owned(), &packing_path));
             }
         };
+        if packing.iter().sum::<usize>() != tuple_size {
+            return Err(SchemaError::new(
+                format!("packing does not add up to tuple size {}", tuple_size),
+                values_path,
+            ));
+        }
         let constant_page_flags: Vec<Vec<bool>> =
-            if let Some((ReaderElement::Array(flag_array), _)) = get_from_kv_array(values, "constantpageflags") {
+            if let Some((ReaderElement::Array(flag_array), flags_path)) =
+                get_from_kv_array(values, "constantpageflags", values_path)?
+            {
                 let mut page_flags = Vec::with_capacity(packing.len());
 
-                for flag_subvector in flag_array {
+                for (flag_i, flag_subvector) in flag_array.iter().enumerate() {
+                    let subvector_path = flags_path.with_array_index(flag_i);
                     page_flags.push(match flag_subvector {
                         ReaderElement::Array(vec) => vec
                             .iter()
-                            .map(|x| -> bool {
-                                match x {
-                                    ReaderElement::Bool(v) => *v,
-                                    ReaderElement::Int(v) => *v != 0,
-                                    _ => {
-                                        panic!("bad schema! packing subvector has unexpected element {:?}", x);
-                                    }
-                                }
+                            .enumerate()
+                            .map(|(i, x)| match x {
+                                ReaderElement::Bool(v) => Ok(*v),
+                                ReaderElement::Int(v) => Ok(*v != 0),
+                                _ => Err(SchemaError::new(
+                                    "packing subvector has unexpected element".to_owned(),
+                                    &subvector_path.with_array_index(i),
+                                )),
                             })
-                            .collect(),
+                            .collect::<Result<_, _>>()?,
                         ReaderElement::UniformArray(UniformArrayType::UniformArrayTbool(vec)) => {
                             vec.clone() // TODO: this may be a reference instead, but then prev arm need to be rethought
                         }
                         _ => {
-                            panic!("bad schema! element of constantpageflags is not an array")
+                            return Err(SchemaError::new(
+                                "element of constantpageflags is not an array".to_owned(),
+                                &subvector_path,
+                            ));
                         }
                     });
                 }
@@ -504,6 +1348,8 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
         let mut elements_left = number_of_elements;
         let mut cur_array_i = 0;
 
+        let not_enough_data = || SchemaError::new("rawpagedata is shorter than expected".to_owned(), values_path);
+
         let mut result: Vec<T> = Vec::with_capacity(number_of_elements * tuple_size);
         result.resize(number_of_elements * tuple_size, T::default());
 
@@ -513,22 +1359,36 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
 
             // for each subvector as defined by packing info
             for (cur_subvector, subvec_size) in packing.iter().enumerate() {
-                if constant_page_flags[cur_subvector].len() == 0 || !constant_page_flags[cur_subvector][cur_page] {
+                let page_is_constant = match constant_page_flags.get(cur_subvector) {
+                    None => false,
+                    Some(flags) if flags.is_empty() => false,
+                    Some(flags) => match flags.get(cur_page) {
+                        Some(flag) => *flag,
+                        None => {
+                            return Err(SchemaError::new(
+                                format!("no constant page flag for page {}", cur_page),
+                                values_path,
+                            ));
+                        }
+                    },
+                };
+                if !page_is_constant {
                     // if page flags empty or flag shows non-constant page
 
                     // copy entire page data
                     for i in 0..page_size.min(elements_left) {
                         let base = base_idx + i * tuple_size + base_subvec_i;
+                        let source = raw_page_array.get(cur_array_i..cur_array_i + subvec_size).ok_or_else(not_enough_data)?;
                         for k in 0..*subvec_size {
-                            result[base..base + subvec_size][k] =
-                                T::convert_from(raw_page_array[cur_array_i..cur_array_i + subvec_size][k]);
+                            result[base..base + subvec_size][k] = T::convert_from(source[k]);
                         }
                         cur_array_i += subvec_size;
                     }
-                    base_subvec_i += subvec_size;
                 } else {
                     // the page is constant for subvector, it contains a single value
-                    let val: Vec<T> = raw_page_array[cur_array_i..cur_array_i + subvec_size]
+                    let val: Vec<T> = raw_page_array
+                        .get(cur_array_i..cur_array_i + subvec_size)
+                        .ok_or_else(not_enough_data)?
                         .iter()
                         .map(|x| T::convert_from(*x))
                         .collect();
@@ -539,204 +1399,250 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
                         result[base..base + subvec_size].copy_from_slice(&val);
                     }
                 }
+                base_subvec_i += subvec_size;
             }
             elements_left -= page_size.min(elements_left);
             cur_page += 1;
         }
 
-        assert_eq!(cur_array_i, raw_page_array.len()); // sanity check
+        if cur_array_i != raw_page_array.len() {
+            return Err(SchemaError::new("rawpagedata is longer than expected".to_owned(), values_path));
+        }
 
-        result
+        Ok(result)
     }
 
     /// parse point attributes
     ///
-    pub fn parse_point_attributes(&mut self) {
-        if let None = self._point_attributes {
-            self._point_attributes = Self::parse_attributes(self.structure, "pointattributes", "pointcount");
+    pub fn parse_point_attributes(&mut self) -> Result<(), SchemaError> {
+        if self._point_attributes.is_none() {
+            self._point_attributes = Some(Self::parse_attributes(
+                self.structure,
+                "pointattributes",
+                AttributeClass::Point,
+                self._point_count,
+                &mut self._skipped_attributes,
+            )?);
         }
+        Ok(())
     }
 
     /// parse vertex attributes
     ///
-    pub fn parse_vertex_attributes(&mut self) {
-        if let None = self._vertex_attributes {
-            self._vertex_attributes = Self::parse_attributes(self.structure, "vertexattributes", "vertexcount");
+    pub fn parse_vertex_attributes(&mut self) -> Result<(), SchemaError> {
+        if self._vertex_attributes.is_none() {
+            self._vertex_attributes = Some(Self::parse_attributes(
+                self.structure,
+                "vertexattributes",
+                AttributeClass::Vertex,
+                self._vertex_count,
+                &mut self._skipped_attributes,
+            )?);
         }
+        Ok(())
     }
 
     /// parse primitive attributes
     ///
-    pub fn parse_primitive_attributes(&mut self) {
-        if let None = self._prim_attributes {
-            self._prim_attributes = Self::parse_attributes(self.structure, "primitiveattributes", "primitivecount");
+    pub fn parse_primitive_attributes(&mut self) -> Result<(), SchemaError> {
+        if self._prim_attributes.is_none() {
+            self._prim_attributes = Some(Self::parse_attributes(
+                self.structure,
+                "primitiveattributes",
+                AttributeClass::Primitive,
+                self._prim_count,
+                &mut self._skipped_attributes,
+            )?);
+        }
+        Ok(())
+    }
+
+    /// parse detail attributes, houdini calls them global attributes
+    ///
+    pub fn parse_detail_attributes(&mut self) -> Result<(), SchemaError> {
+        if self._detail_attributes.is_none() {
+            self._detail_attributes = Some(Self::parse_attributes(
+                self.structure,
+                "globalattributes",
+                AttributeClass::Detail,
+                1,
+                &mut self._skipped_attributes,
+            )?);
         }
+        Ok(())
     }
 
     /// parse general attribute structure
     ///
     /// * `structure` - overall schema
     /// * `attrib_key` - name of the key where to find attributes
-    /// * `elem_count_key` - key name of where to find element count
+    /// * `class` - class of those attributes
+    /// * `elem_count` - number of elements of that class
+    /// * `skipped` - attributes with skipped data are added here
     ///
     fn parse_attributes(
         structure: &'a ReaderElement,
         attrib_key: &str,
-        elem_count_key: &str,
-    ) -> Option<HashMap<&'a str, GeoAttributeKind>> {
+        class: AttributeClass,
+        elem_count: usize,
+        skipped: &mut Vec<(AttributeClass, &'a str)>,
+    ) -> Result<HashMap<&'a str, GeoAttributeKind>, SchemaError> {
         let mut attribute_map = HashMap::new();
-        let mut attrib_base_path = ReaderElementPointer::new();
-
-        let elem_count: usize = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(structure, elem_count_key) {
-            (*x).try_into().expect("incorrect element count")
-        } else {
-            panic!("could not find key {}", elem_count_key);
-        };
 
-        let attributes = if let Some((x, i)) = get_from_kv_array(structure, "attributes") {
-            attrib_base_path.add_array_index(i);
-            x
-        } else {
-            panic!("bad schema! attributes must be an array");
-        };
-        let elem_attributes = if let Some((ReaderElement::Array(x), i)) = get_from_kv_array(attributes, attrib_key) {
-            attrib_base_path.add_array_index(i);
-            x
-        } else {
-            println!("no {} attributes!", attrib_key);
-            return Some(HashMap::new());
+        let (attributes, attributes_path) = require_from_kv_array(structure, "attributes", &ReaderElementPointer::new())?;
+        let (elem_attributes, attrib_base_path) = match get_from_kv_array(attributes, attrib_key, &attributes_path)? {
+            Some((ReaderElement::Array(x), path)) => (x, path),
+            Some((_, path)) => {
+                return Err(SchemaError::new(format!("{} must be an array", attrib_key), &path));
+            }
+            None => {
+                diagnostics::info(&format!("no {} attributes!", attrib_key));
+                return Ok(HashMap::new());
+            }
         };
 
         for (attrib_idx, elem_attribute_block_el) in elem_attributes.iter().enumerate() {
             let elem_attribute_block = if let ReaderElement::Array(x) = elem_attribute_block_el {
                 x
             } else {
-                println!("bad schema! unrecognized point attribute block type, skipping");
+                diagnostics::warning(&format!(
+                    "unrecognized attribute block type at '{}', skipping",
+                    attrib_base_path.with_array_index(attrib_idx)
+                ));
                 continue;
             };
             if elem_attribute_block.len() != 2 {
-                println!("bad schema! unrecognized point attribute block type, skipping");
+                diagnostics::warning(&format!(
+                    "unrecognized attribute block type at '{}', skipping",
+                    attrib_base_path.with_array_index(attrib_idx)
+                ));
+                continue;
+            }
+            let attrib_path = attrib_base_path.with_array_index(attrib_idx);
+            let metadata_path = attrib_path.with_array_index(0);
+            let data_path = attrib_path.with_array_index(1);
+
+            let attrib_name = get_text_from_kv_array(&elem_attribute_block[0], "name", &metadata_path)?;
+            let attrib_type = get_text_from_kv_array(&elem_attribute_block[0], "type", &metadata_path)?;
+
+            if let ReaderElement::None = elem_attribute_block[1] {
+                // parse options told to skip it
+                skipped.push((class, attrib_name.as_str()));
                 continue;
             }
-            let attrib_name = if let Some((ReaderElement::Text(x), _)) = get_from_kv_array(&elem_attribute_block[0], "name") {
-                x
-            } else {
-                panic!("bad schema! no attrib name");
-            };
-            let attrib_type = if let Some((ReaderElement::Text(x), _)) = get_from_kv_array(&elem_attribute_block[0], "type") {
-                x
-            } else {
-                panic!("bad schema! no attrib type");
-            };
 
-            let values = if let Some((x, _)) = get_from_kv_array(&elem_attribute_block[1], "values") {
+            let (values, values_path) = if let Some(x) = get_from_kv_array(&elem_attribute_block[1], "values", &data_path)? {
                 x
-            } else if let Some((x, _)) = get_from_kv_array(&elem_attribute_block[1], "indices") {
+            } else if let Some(x) = get_from_kv_array(&elem_attribute_block[1], "indices", &data_path)? {
                 // for now treat indices same as values
                 x
             } else {
-                panic!("bad schema! attrib values bad");
+                return Err(SchemaError::new("attrib has neither values nor indices".to_owned(), &data_path));
             };
 
             // either it has tuples, or rawpagedata, or arrays
 
-            let mut attrib_path = attrib_base_path.clone();
-            attrib_path.add_array_index(attrib_idx);
             attribute_map.insert(
                 attrib_name.as_str(),
                 match attrib_type.as_str() {
                     "numeric" => {
-                        let size: usize = if let Some((ReaderElement::Int(x), _)) = get_from_kv_array(values, "size") {
-                            *x as usize
-                        } else {
-                            panic!("bad schema! no size for values!");
-                        };
-                        let storage = if let Some((ReaderElement::Text(x), _)) = get_from_kv_array(values, "storage") {
-                            x
-                        } else {
-                            panic!("bad schema! no storage for values!");
+                        let size = get_usize_from_kv_array(values, "size", &values_path)?;
+                        let storage_name = get_text_from_kv_array(values, "storage", &values_path)?;
+                        let storage = match AttributeStorage::from_name(storage_name) {
+                            Some(storage) => storage,
+                            None => {
+                                diagnostics::warning(&format!(
+                                    "not implemented parsing attrib storage {}, skipping {}",
+                                    storage_name, attrib_name
+                                ));
+                                continue;
+                            }
                         };
-                        if storage.starts_with("fpreal") {
+                        let data_block = &elem_attribute_block[1];
+                        if storage.is_float() {
+                            let mapper = |x: &ReaderElement| -> Option<f64> {
+                                match x {
+                                    ReaderElement::Float(f) => Some(*f),
+                                    ReaderElement::Int(f) => Some(*f as f64),
+                                    _ => None,
+                                }
+                            };
                             GeoAttributeKind::Float64(TupleGeoAttribute {
                                 tuple_size: size,
-                                data: Self::parse_values(
-                                    values,
-                                    size,
-                                    &|x| -> f64 {
-                                        if let ReaderElement::Float(f) = x {
-                                            *f
-                                        } else if let ReaderElement::Int(f) = x {
-                                            *f as f64
-                                        } else {
-                                            panic!("bad schema! {:?}", x);
-                                        }
-                                    },
-                                    elem_count,
-                                ),
+                                data: Self::parse_values(values, &values_path, size, &mapper, elem_count)?,
+                                storage,
+                                defaults: parse_defaults(data_block, &data_path, size, &mapper)?,
                                 path_to_element: attrib_path,
                             })
-                        } else if storage.starts_with("int") {
+                        } else {
+                            let mapper = |x: &ReaderElement| -> Option<i64> {
+                                if let ReaderElement::Int(f) = x {
+                                    Some(*f)
+                                } else {
+                                    None
+                                }
+                            };
                             GeoAttributeKind::Int64(TupleGeoAttribute {
                                 tuple_size: size,
-                                data: Self::parse_values(
-                                    values,
-                                    size,
-                                    &|x| -> i64 {
-                                        if let ReaderElement::Int(f) = x {
-                                            *f
-                                        } else {
-                                            panic!("bad schema! {:?}", x);
-                                        }
-                                    },
-                                    elem_count,
-                                ),
+                                data: Self::parse_values(values, &values_path, size, &mapper, elem_count)?,
+                                storage,
+                                defaults: parse_defaults(data_block, &data_path, size, &mapper)?,
                                 path_to_element: attrib_path,
                             })
-                        } else {
-                            println!("not implemented parsing attrib storage {}", storage);
-                            continue;
                         }
                     }
                     "string" => {
-                        let strings = if let Some((ReaderElement::Array(x), _)) =
-                            get_from_kv_array(&elem_attribute_block[1], "strings")
-                        {
-                            x
-                        } else {
-                            panic!("bad schema! no stirngs for string attrib!")
+                        let strings_found = require_from_kv_array(&elem_attribute_block[1], "strings", &data_path)?;
+                        let tokens = match strings_found {
+                            (ReaderElement::Array(strings), strings_path) => strings
+                                .iter()
+                                .enumerate()
+                                .map(|(i, x)| {
+                                    if let ReaderElement::Text(s) = x {
+                                        Ok(s.to_string())
+                                    } else {
+                                        Err(SchemaError::new(
+                                            "strings contain not a string".to_owned(),
+                                            &strings_path.with_array_index(i),
+                                        ))
+                                    }
+                                })
+                                .collect::<Result<Vec<String>, SchemaError>>()?,
+                            (ReaderElement::UniformArray(UniformArrayType::UniformArrayTstring(strings)), _) => {
+                                strings.iter().map(|x| x.to_string()).collect()
+                            }
+                            (_, path) => return Err(SchemaError::new("strings must be an array".to_owned(), &path)),
                         };
+                        let token_count = tokens.len();
                         GeoAttributeKind::String(TokenGeoAttribute {
-                            tokens: Vec::from_iter(strings.iter().map(|x| -> String {
-                                if let ReaderElement::Text(s) = x {
-                                    s.to_owned()
-                                } else {
-                                    panic!("bad schema! strings contain not a string")
-                                }
-                            })),
+                            tokens,
                             data: Self::parse_values(
                                 values,
+                                &values_path,
                                 1,
-                                &|x| -> usize {
-                                    if let ReaderElement::Int(f) = x {
-                                        (*f).try_into().expect(&format!("failed to convert int to usize {}", *f))
-                                    } else {
-                                        panic!("bad schema! {:?}", x);
+                                &|x| -> Option<usize> {
+                                    match x {
+                                        ReaderElement::Int(f) => (*f).try_into().ok().filter(|i| *i < token_count),
+                                        _ => None,
                                     }
                                 },
                                 elem_count,
-                            ),
+                            )?,
                             path_to_element: attrib_path,
                         })
                     }
                     _ => {
-                        println!("not implemented parsing attrib type {}", attrib_type);
+                        diagnostics::warning(&format!(
+                            "not implemented parsing attrib type {}, skipping {}",
+                            attrib_type, attrib_name
+                        ));
                         continue;
                     }
                 },
             );
         }
 
-        Some(attribute_map)
+        Ok(attribute_map)
     }
 
     /// parse primitives from geo structure
@@ -744,201 +1650,227 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
     /// for now only polygons are supported
     ///
     /// TODO: support other types of primitives
-    pub fn parse_primitives(&mut self) {
+    pub fn parse_primitives(&mut self) -> Result<(), SchemaError> {
+        self._unsupported_primitive_blocks.clear();
         let mut polygons = Vec::with_capacity(self._prim_count);
         let mut cur_prim_num: usize = 0;
-        let prim_blocks = if let Some((ReaderElement::Array(x), _)) = get_from_kv_array(self.structure, "primitives") {
-            x
-        } else {
-            panic!("bad schema! no primitives entry")
-        };
+        let (prim_blocks, prim_blocks_path) =
+            match require_from_kv_array(self.structure, "primitives", &ReaderElementPointer::new())? {
+                (ReaderElement::Array(x), path) => (x, path),
+                (_, path) => return Err(SchemaError::new("primitives must be an array".to_owned(), &path)),
+            };
 
-        for prim_block in prim_blocks {
+        for (block_i, prim_block) in prim_blocks.iter().enumerate() {
+            let block_path = prim_blocks_path.with_array_index(block_i);
             let prim_block_arr = if let ReaderElement::Array(x) = prim_block {
                 x
             } else {
-                panic!("bad schema! prim block is no array");
+                return Err(SchemaError::new("prim block is no array".to_owned(), &block_path));
             };
             if prim_block_arr.len() != 2 {
-                println!("skipping unexpected prim block scheme");
+                diagnostics::warning(&format!("skipping unexpected prim block scheme at '{}'", block_path));
                 continue;
             }
-
-            let (block_type, _) = get_from_kv_array(&prim_block_arr[0], "type").expect("bad schema! no prim block type!");
-            if let ReaderElement::Text(type_text) = block_type {
-                // skip unknown for now types of blocks
-                if type_text != "Polygon_run" && type_text != "p_r" {
-                    println!("skipping block {}", type_text);
-                    if type_text.ends_with("_run") {
-                        let nprims_in_block = if let Some((ReaderElement::Int(x), _)) =
-                            get_from_any_kv_array(&prim_block_arr[1], &["nprimitives", "n_p"])
-                        {
-                            *x
-                        } else {
-                            panic!("bad schema! {} block is expected to have nprimitives/n_p key", type_text);
-                        };
-                        cur_prim_num += <i64 as TryInto<usize>>::try_into(nprims_in_block)
-                            .expect(&format!("bad data. nprimitives negative?? {}", nprims_in_block));
-                    } else {
-                        cur_prim_num += 1;
-                    }
-                    continue;
+            let block_header_path = block_path.with_array_index(0);
+            let block_data_path = block_path.with_array_index(1);
+
+            let type_text = get_text_from_kv_array(&prim_block_arr[0], "type", &block_header_path)?;
+            // skip unknown for now types of blocks
+            if type_text != "Polygon_run" && type_text != "p_r" {
+                diagnostics::warning(&format!("skipping primitive block {}", type_text));
+                if type_text.ends_with("_run") {
+                    let nprims_found = get_from_any_kv_array(&prim_block_arr[1], &["nprimitives", "n_p"], &block_data_path)?;
+                    let nprims_in_block = match nprims_found {
+                        Some((ReaderElement::Int(x), _)) if *x >= 0 => *x as usize,
+                        Some((_, path)) => {
+                            return Err(SchemaError::new("nprimitives is expected to be a non-negative int".to_owned(), &path));
+                        }
+                        None => {
+                            return Err(SchemaError::new(
+                                format!("{} block is expected to have nprimitives/n_p key", type_text),
+                                &block_data_path,
+                            ));
+                        }
+                    };
+                    self._unsupported_primitive_blocks.push((cur_prim_num, nprims_in_block, prim_block));
+                    cur_prim_num += nprims_in_block;
+                } else {
+                    self._unsupported_primitive_blocks.push((cur_prim_num, 1, prim_block));
+                    cur_prim_num += 1;
                 }
-            } else {
-                panic!("bad schemd! primitive type is not a string");
+                continue;
             }
 
-            let start_vertex =
-                if let Some((ReaderElement::Int(x), _)) = get_from_any_kv_array(&prim_block_arr[1], &["startvertex", "s_v"]) {
-                    *x
-                } else {
-                    panic!("unexpected type!")
-                } as usize;
+            let start_vertex = match get_from_any_kv_array(&prim_block_arr[1], &["startvertex", "s_v"], &block_data_path)? {
+                Some((ReaderElement::Int(x), _)) if *x >= 0 => *x as usize,
+                Some((_, path)) => {
+                    return Err(SchemaError::new("startvertex is expected to be a non-negative int".to_owned(), &path));
+                }
+                None => {
+                    return Err(SchemaError::new("no startvertex/s_v in polygon block".to_owned(), &block_data_path));
+                }
+            };
 
             // it's either nvertices_rle or nvertices
 
             // nvertices_rle case
             macro_rules! _loop_iter_helper {
-                ($vtx_cnt_pairs:ident, $vtx_elem_func:expr) => {
-                    let mut vtx_cnts = $vtx_cnt_pairs.iter();
+                ($vtx_cnt_pairs:ident, $path:ident, $vtx_elem_func:expr) => {
+                    if $vtx_cnt_pairs.len() % 2 != 0 {
+                        return Err(SchemaError::new(
+                            "nvertices_rle array must have an even number of entries".to_owned(),
+                            &$path,
+                        ));
+                    }
                     let mut cur_vtx = start_vertex;
-                    loop {
-                        let vtx_cnt = if let Some(x) = vtx_cnts.next() {
-                            $vtx_elem_func(x)
-                        } else {
-                            break;
-                        };
-                        let count = if let Some(x) = vtx_cnts.next() {
-                            $vtx_elem_func(x)
-                        } else {
-                            panic!("bad schema! rle no even")
-                        };
+                    for (pair_i, pair) in $vtx_cnt_pairs.chunks(2).enumerate() {
+                        let vtx_cnt = $vtx_elem_func(&pair[0], &$path.with_array_index(2 * pair_i))?;
+                        let count = $vtx_elem_func(&pair[1], &$path.with_array_index(2 * pair_i + 1))?;
 
                         for _ in 0..count {
-                            let mut prim_vtxs = Vec::with_capacity(vtx_cnt);
-                            for vtx_shift in 0..vtx_cnt {
-                                let vtx_num = cur_vtx + vtx_shift;
-                                prim_vtxs.push(GeoVertex {
-                                    ptnum: self.vtx_to_ptnum(vtx_num),
-                                    vtxnum: vtx_num,
-                                });
-                            }
+                            polygons.push(self.make_polygon(cur_prim_num, cur_vtx, vtx_cnt, &$path)?);
                             cur_vtx += vtx_cnt;
-                            polygons.push(GeoPolygon {
-                                number: cur_prim_num,
-                                vertices: prim_vtxs,
-                            });
                             cur_prim_num += 1;
                         }
                     }
                 };
             }
 
-            match get_from_any_kv_array(&prim_block_arr[1], &["nvertices_rle", "r_v"]) {
-                Some((ReaderElement::Array(vtx_cnt_pairs), _)) => {
-                    // TODO: check len is even
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &ReaderElement| {
-                        if let ReaderElement::Int(u) = x {
-                            *u as usize
-                        } else {
-                            panic!("bad schema! rel no int");
+            match get_from_any_kv_array(&prim_block_arr[1], &["nvertices_rle", "r_v"], &block_data_path)? {
+                Some((ReaderElement::Array(vtx_cnt_pairs), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &ReaderElement, path: &ReaderElementPointer| {
+                        match x {
+                            ReaderElement::Int(u) if *u >= 0 => Ok(*u as usize),
+                            _ => Err(SchemaError::new("rle is not a non-negative int".to_owned(), path)),
                         }
                     });
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &u8| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &u8, _| -> Result<usize, SchemaError> { Ok(*x as usize) });
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &u16| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &u16, _| -> Result<usize, SchemaError> { Ok(*x as usize) });
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &i8| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &i8, path| non_negative(*x as i64, path));
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &i16| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &i16, path| non_negative(*x as i64, path));
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &i32| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &i32, path| non_negative(*x as i64, path));
                 }
-                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vtx_cnt_pairs)), _)) => {
-                    _loop_iter_helper!(vtx_cnt_pairs, |x: &i64| { *x as usize });
+                Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vtx_cnt_pairs)), path)) => {
+                    _loop_iter_helper!(vtx_cnt_pairs, path, |x: &i64, path| non_negative(*x, path));
                 }
                 None => {
                     // nvertices case
                     macro_rules! _loop_iter_helper2 {
-                        ($vtx_cnts:ident, $vtx_cnt_func:expr) => {
+                        ($vtx_cnts:ident, $path:ident, $vtx_cnt_func:expr) => {
                             let mut cur_vtx = start_vertex;
-                            for vtx_cnt in $vtx_cnts {
-                                let vtx_cnt = $vtx_cnt_func(vtx_cnt);
-
-                                let mut prim_vtxs = Vec::with_capacity(vtx_cnt);
-                                for vtx_shift in 0..vtx_cnt {
-                                    let vtx_num = cur_vtx + vtx_shift;
-                                    prim_vtxs.push(GeoVertex {
-                                        ptnum: self.vtx_to_ptnum(vtx_num),
-                                        vtxnum: vtx_num,
-                                    });
-                                }
+                            for (i, vtx_cnt) in $vtx_cnts.iter().enumerate() {
+                                let vtx_cnt = $vtx_cnt_func(vtx_cnt, &$path.with_array_index(i))?;
+
+                                polygons.push(self.make_polygon(cur_prim_num, cur_vtx, vtx_cnt, &$path)?);
                                 cur_vtx += vtx_cnt;
-                                polygons.push(GeoPolygon {
-                                    number: cur_prim_num,
-                                    vertices: prim_vtxs,
-                                });
                                 cur_prim_num += 1;
                             }
                         };
                     }
 
-                    match get_from_any_kv_array(&prim_block_arr[1], &["nvertices", "n_v"]) {
-                        Some((ReaderElement::Array(vtx_cnts), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &ReaderElement| {
-                                if let ReaderElement::Int(u) = v {
-                                    *u as usize
-                                } else {
-                                    panic!("schema error! vtx cnt no int");
+                    match get_from_any_kv_array(&prim_block_arr[1], &["nvertices", "n_v"], &block_data_path)? {
+                        Some((ReaderElement::Array(vtx_cnts), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &ReaderElement, path: &ReaderElementPointer| {
+                                match v {
+                                    ReaderElement::Int(u) if *u >= 0 => Ok(*u as usize),
+                                    _ => Err(SchemaError::new("vtx cnt is not a non-negative int".to_owned(), path)),
                                 }
                             });
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &u8| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu8(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &u8, _| -> Result<usize, SchemaError> { Ok(*v as usize) });
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &u16| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTu16(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &u16, _| -> Result<usize, SchemaError> { Ok(*v as usize) });
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &i8| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &i8, path| non_negative(*v as i64, path));
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &i16| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &i16, path| non_negative(*v as i64, path));
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &i32| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &i32, path| non_negative(*v as i64, path));
                         }
-                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vtx_cnts)), _)) => {
-                            _loop_iter_helper2!(vtx_cnts, |v: &i64| { *v as usize });
+                        Some((ReaderElement::UniformArray(UniformArrayType::UniformArrayTi64(vtx_cnts)), path)) => {
+                            _loop_iter_helper2!(vtx_cnts, path, |v: &i64, path| non_negative(*v, path));
                         }
-                        _ => {
-                            panic!("unexpected type of nvertices/n_v block!");
+                        Some((_, path)) => {
+                            return Err(SchemaError::new("unexpected type of nvertices/n_v block!".to_owned(), &path));
+                        }
+                        None => {
+                            return Err(SchemaError::new(
+                                "polygon block has neither nvertices_rle nor nvertices".to_owned(),
+                                &block_data_path,
+                            ));
                         }
                     }
                 }
-                _ => {
-                    panic!("unexpected type of nvertices_rle/r_v block!");
+                Some((_, path)) => {
+                    return Err(SchemaError::new("unexpected type of nvertices_rle/r_v block!".to_owned(), &path));
                 }
             }
         }
 
         self._polygons = Some(polygons);
+        Ok(())
+    }
+
+    /// make polygon of vtx_cnt vertices starting with start_vertex
+    fn make_polygon(
+        &self,
+        number: usize,
+        start_vertex: usize,
+        vtx_cnt: usize,
+        path: &ReaderElementPointer,
+    ) -> Result<GeoPolygon, SchemaError> {
+        if number >= self._prim_count {
+            return Err(SchemaError::new(
+                format!("more primitives than primitivecount {}", self._prim_count),
+                path,
+            ));
+        }
+        if start_vertex.checked_add(vtx_cnt).is_none_or(|end| end > self._vertex_nums_to_point_nums.len()) {
+            return Err(SchemaError::new(
+                format!(
+                    "polygon vertices are out of range, there are only {} vertices in topology",
+                    self._vertex_nums_to_point_nums.len()
+                ),
+                path,
+            ));
+        }
+        Ok(GeoPolygon {
+            number,
+            vertices: (start_vertex..start_vertex + vtx_cnt)
+                .map(|vtx_num| GeoVertex {
+                    ptnum: self.vtx_to_ptnum(vtx_num),
+                    vtxnum: vtx_num,
+                })
+                .collect(),
+        })
     }
 
-    pub fn point_attribute_names(&'a self) -> impl Iterator + 'a {
+    /// get names of point attributes, in no particular order
+    ///
+    /// point attributes have to be parsed beforehand
+    pub fn point_attribute_names(&self) -> impl Iterator<Item = &str> {
         let attrib_map = if let Some(x) = &self._point_attributes {
             x
         } else {
             panic!("point attributes were not parsed!");
         };
 
-        attrib_map.keys()
+        attrib_map.keys().copied()
     }
 
     /// get vertex attribute
@@ -980,6 +1912,86 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
         attrib_map.get(name)
     }
 
+    /// get detail attribute, its only value is number 0
+    ///
+    /// detail attributes have to be parsed beforehand
+    pub fn detail_attribute(&self, name: &str) -> Option<&GeoAttributeKind> {
+        let attrib_map = if let Some(x) = &self._detail_attributes {
+            x
+        } else {
+            panic!("detail attributes were not parsed!");
+        };
+
+        attrib_map.get(name)
+    }
+
+    /// parse attributes of given class
+    ///
+    pub fn parse_class_attributes(&mut self, class: AttributeClass) -> Result<(), SchemaError> {
+        match class {
+            AttributeClass::Point => self.parse_point_attributes(),
+            AttributeClass::Vertex => self.parse_vertex_attributes(),
+            AttributeClass::Primitive => self.parse_primitive_attributes(),
+            AttributeClass::Detail => self.parse_detail_attributes(),
+        }
+    }
+
+    fn class_attributes(&self, class: AttributeClass) -> &HashMap<&'a str, GeoAttributeKind> {
+        let attrib_map = match class {
+            AttributeClass::Point => &self._point_attributes,
+            AttributeClass::Vertex => &self._vertex_attributes,
+            AttributeClass::Primitive => &self._prim_attributes,
+            AttributeClass::Detail => &self._detail_attributes,
+        };
+        if let Some(x) = attrib_map {
+            x
+        } else {
+            panic!("{} attributes were not parsed!", class);
+        }
+    }
+
+    /// get attribute of given class
+    ///
+    /// attributes of that class have to be parsed beforehand
+    pub fn attribute(&self, class: AttributeClass, name: &str) -> Option<&GeoAttributeKind> {
+        self.class_attributes(class).get(name)
+    }
+
+    /// get names of attributes of given class, in no particular order
+    ///
+    /// attributes of that class have to be parsed beforehand
+    pub fn attribute_names(&self, class: AttributeClass) -> impl Iterator<Item = &str> {
+        self.class_attributes(class).keys().copied()
+    }
+
+    /// get names of attributes of given class, that are present, but were not loaded
+    /// as parse options skipped their data
+    ///
+    /// attributes of that class have to be parsed beforehand
+    pub fn skipped_attribute_names(&self, class: AttributeClass) -> impl Iterator<Item = &str> {
+        // same as other getters, it's a misuse to ask before parsing
+        self.class_attributes(class);
+        self._skipped_attributes.iter().filter(move |(c, _)| *c == class).map(|(_, name)| *name)
+    }
+
+    /// true if attribute is present, but was not loaded as parse options skipped its data
+    ///
+    /// attributes of that class have to be parsed beforehand
+    pub fn is_attribute_skipped(&self, class: AttributeClass, name: &str) -> bool {
+        self.skipped_attribute_names(class).any(|x| x == name)
+    }
+
+    /// get number of elements of given class
+    ///
+    pub fn element_count(&self, class: AttributeClass) -> usize {
+        match class {
+            AttributeClass::Point => self._point_count,
+            AttributeClass::Vertex => self._vertex_count,
+            AttributeClass::Primitive => self._prim_count,
+            AttributeClass::Detail => 1,
+        }
+    }
+
     /// get point number of the point given vertex belongs to
     ///
     pub fn vtx_to_ptnum(&self, vtx_num: usize) -> usize {
@@ -997,6 +2009,135 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
         }
     }
 
+    /// primitive blocks of types that are not supported, they are skipped by parse_primitives,
+    /// given as number of the first primitive in the block, number of primitives in it and the block itself
+    ///
+    /// primitives have to be parsed beforehand
+    pub fn unsupported_primitive_blocks(&self) -> &[(usize, usize, &'a ReaderElement)] {
+        self.polygons();
+        &self._unsupported_primitive_blocks
+    }
+
+    /// point number of every vertex
+    ///
+    pub fn vertex_points(&self) -> &[usize] {
+        &self._vertex_nums_to_point_nums
+    }
+
+    /// take parsed attributes of given class out of the parser, leaving no attributes of that class
+    ///
+    /// attributes of that class have to be parsed beforehand
+    pub fn take_class_attributes(&mut self, class: AttributeClass) -> HashMap<&'a str, GeoAttributeKind> {
+        let attrib_map = match class {
+            AttributeClass::Point => &mut self._point_attributes,
+            AttributeClass::Vertex => &mut self._vertex_attributes,
+            AttributeClass::Primitive => &mut self._prim_attributes,
+            AttributeClass::Detail => &mut self._detail_attributes,
+        };
+        match attrib_map {
+            Some(x) => std::mem::take(x),
+            None => panic!("{} attributes were not parsed!", class),
+        }
+    }
+
+    /// parse point, vertex, primitive and edge groups
+    ///
+    pub fn parse_groups(&mut self) -> Result<(), SchemaError> {
+        if self._groups.is_some() {
+            return Ok(());
+        }
+        let mut groups = Vec::new();
+        for (class, key) in [
+            (AttributeClass::Point, "pointgroups"),
+            (AttributeClass::Vertex, "vertexgroups"),
+            (AttributeClass::Primitive, "primitivegroups"),
+        ] {
+            for (name, data, data_path) in Self::group_blocks(self.structure, key)? {
+                let (selection, selection_path) = require_from_kv_array(data, "selection", &data_path)?;
+                groups.push((class, name, parse_group_selection(selection, &selection_path, self.element_count(class))?));
+            }
+        }
+        let mut edge_groups = Vec::new();
+        for (name, data, data_path) in Self::group_blocks(self.structure, "edgegroups")? {
+            edge_groups.push((name, parse_edge_group_points(data, &data_path, self._point_count)?));
+        }
+        self._groups = Some(groups);
+        self._edge_groups = Some(edge_groups);
+        Ok(())
+    }
+
+    /// name, data and path to data of every group block under given key
+    ///
+    /// group blocks look like [["name","group1"],["selection",[...]]]
+    fn group_blocks(
+        structure: &'a ReaderElement,
+        groups_key: &str,
+    ) -> Result<Vec<(&'a str, &'a ReaderElement, ReaderElementPointer)>, SchemaError> {
+        let (blocks, blocks_path) = match get_from_kv_array(structure, groups_key, &ReaderElementPointer::new())? {
+            Some((ReaderElement::Array(x), path)) => (x, path),
+            Some((_, path)) => return Err(SchemaError::new(format!("{} must be an array", groups_key), &path)),
+            None => return Ok(Vec::new()),
+        };
+        let mut result = Vec::with_capacity(blocks.len());
+        for (i, block) in blocks.iter().enumerate() {
+            let block_path = blocks_path.with_array_index(i);
+            let (header, data) = match block {
+                ReaderElement::Array(x) if x.len() == 2 => (&x[0], &x[1]),
+                _ => {
+                    diagnostics::warning(&format!("unrecognized group block type at '{}', skipping", block_path));
+                    continue;
+                }
+            };
+            let name = get_text_from_kv_array(header, "name", &block_path.with_array_index(0))?;
+            result.push((name.as_str(), data, block_path.with_array_index(1)));
+        }
+        Ok(result)
+    }
+
+    fn groups(&self) -> &[(AttributeClass, &'a str, ElementGroup)] {
+        if let Some(x) = &self._groups {
+            x
+        } else {
+            panic!("groups were not parsed!");
+        }
+    }
+
+    /// get names of groups of given class, in the order they are in the structure
+    ///
+    /// groups have to be parsed beforehand
+    pub fn group_names(&self, class: AttributeClass) -> impl Iterator<Item = &str> {
+        self.groups().iter().filter(move |(c, _, _)| *c == class).map(|(_, name, _)| *name)
+    }
+
+    /// get group of given class
+    ///
+    /// groups have to be parsed beforehand
+    pub fn group(&self, class: AttributeClass, name: &str) -> Option<&ElementGroup> {
+        self.groups().iter().find(|(c, n, _)| *c == class && *n == name).map(|(_, _, group)| group)
+    }
+
+    fn edge_groups(&self) -> &[(&'a str, EdgeGroup)] {
+        if let Some(x) = &self._edge_groups {
+            x
+        } else {
+            panic!("groups were not parsed!");
+        }
+    }
+
+    /// get names of edge groups, in the order they are in the structure
+    ///
+    /// groups have to be parsed beforehand
+    pub fn edge_group_names(&self) -> impl Iterator<Item = &str> {
+        self.edge_groups().iter().map(|(name, _)| *name)
+    }
+
+    /// get edge group
+    ///
+    /// groups have to be parsed beforehand
+    pub fn edge_group(&self, name: &str) -> Option<&EdgeGroup> {
+        self.edge_groups().iter().find(|(n, _)| *n == name).map(|(_, group)| group)
+    }
+
     /// get primitive count
     ///
     pub fn primitive_count(&self) -> usize {
@@ -1017,68 +2158,298 @@ impl<'a> HoudiniGeoSchemaParser<'a> {
 
     /// write attributes into a structure with the same layout as original
     ///
-    pub fn write_to_strucutre(attr_kind: GeoAttributeKind, structure: &mut ReaderElement) {
-        match attr_kind {
+    /// values are written as float64 or int64 rawpagedata, strings get their strings and indices replaced
+    pub fn write_to_strucutre(attr_kind: GeoAttributeKind, structure: &mut ReaderElement) -> Result<(), SchemaError> {
+        // keys of the data block to replace, the last one is the one that holds values and must be there
+        let (attr_path, mut replacements) = match attr_kind {
             GeoAttributeKind::Float64(attr) => {
-                let attrib_elem_arr = if let Some(ReaderElement::Array(x)) = attr.path_to_element.locate_key_in_mut(structure) {
-                    x
-                } else {
-                    panic!("structure does not match location")
-                };
-                // we know that this attrib_elem must be pointint to an array of 2 values
-
-                let second_block = if let ReaderElement::Array(x) = &mut attrib_elem_arr[1] {
-                    x
-                } else {
-                    panic!("bad schema! second attr block is not an array");
-                };
-
                 let tuple_size = attr.tuple_size();
-                let rawpagedata = ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(attr.data));
-
-                let mut i: u32 = 0;
-                let mut block_iter = second_block.iter_mut();
-                let values = loop {
-                    let elem = block_iter.next().expect("bad schema! values not found in attrib");
-                    i += 1; // ++ in the start, so next check is for %2==0 instead of ==1
-                    if i % 2 == 0 {
-                        continue;
-                    };
-                    match elem {
-                        ReaderElement::Text(s) if s == "values" => {
-                            // we can break only cuz we know that values always come last in schema
-                            break block_iter.next().expect("bad schema! no values?");
-                        }
-                        ReaderElement::Text(s) if s == "size" => {
-                            *block_iter.next().expect("bad schema! no size value") = ReaderElement::Int(tuple_size as i64);
-                            i += 1;
-                        }
-                        ReaderElement::Text(s) if s == "storage" => {
-                            *block_iter.next().expect("bad schema! no storage value") =
-                                ReaderElement::Text("fpreal64".to_owned()); // TODO: support different types
-                            i += 1;
-                        }
-                        ReaderElement::Text(_) => (),
-                        _ => {
-                            panic!("bad schema! expecting string key");
-                        }
-                    }
-                };
-
-                *values = ReaderElement::Array(vec![
-                    ReaderElement::Text("size".to_owned()),
-                    ReaderElement::Int(tuple_size as i64),
-                    ReaderElement::Text("storage".to_owned()),
-                    ReaderElement::Text("fpreal64".to_owned()), // TODO: support different types
-                    ReaderElement::Text("pagesize".to_owned()),
-                    ReaderElement::Int(1024),
-                    ReaderElement::Text("rawpagedata".to_owned()),
-                    rawpagedata,
+                let rawpagedata = attr.storage.encode_floats(attr.data);
+                (attr.path_to_element, numeric_replacements(tuple_size, attr.storage, rawpagedata))
+            }
+            GeoAttributeKind::Int64(attr) => {
+                let tuple_size = attr.tuple_size();
+                let rawpagedata = attr.storage.encode_ints(attr.data);
+                (attr.path_to_element, numeric_replacements(tuple_size, attr.storage, rawpagedata))
+            }
+            GeoAttributeKind::String(attr) => {
+                let strings = attr.tokens.iter().map(|x| ReaderElement::Text(x.as_str().into())).collect();
+                let indices = attr.data.iter().map(|x| *x as i32).collect();
+                let indices = ReaderElement::Array(vec![
+                    ReaderElement::Text("size".into()),
+                    ReaderElement::Int(1),
+                    ReaderElement::Text("storage".into()),
+                    ReaderElement::Text("int32".into()),
+                    ReaderElement::Text("arrays".into()),
+                    ReaderElement::Array(vec![ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(indices))]),
                 ]);
+                (attr.path_to_element, vec![("strings", Some(ReaderElement::Array(strings))), ("indices", Some(indices))])
             }
+        };
+
+        let attrib_elem_arr = match attr_path.locate_key_in_mut(structure) {
+            Some(ReaderElement::Array(x)) if x.len() == 2 => x,
             _ => {
-                panic!("not implemented yet");
+                let message = "structure does not match location, new attributes must be added with add_attribute";
+                return Err(SchemaError::new(message.to_owned(), &attr_path));
+            }
+        };
+        // we know that this attrib_elem must be pointint to an array of 2 values
+
+        let data_path = attr_path.with_array_index(1);
+        let second_block = if let ReaderElement::Array(x) = &mut attrib_elem_arr[1] {
+            x
+        } else {
+            return Err(SchemaError::new("second attr block is not an array".to_owned(), &data_path));
+        };
+
+        for (pair_i, pair) in second_block.chunks_exact_mut(2).enumerate() {
+            let key = match &pair[0] {
+                ReaderElement::Text(s) => s.clone(),
+                _ => {
+                    return Err(SchemaError::new("expecting string key".to_owned(), &data_path.with_array_index(2 * pair_i)));
+                }
+            };
+            if let Some((_, value)) = replacements.iter_mut().find(|(k, _)| key == *k) {
+                if let Some(value) = value.take() {
+                    pair[1] = value;
+                }
+            }
+        }
+        match replacements.last() {
+            Some((key, Some(_))) => Err(SchemaError::new(format!("{} not found in attrib", key), &data_path)),
+            _ => Ok(()),
+        }
+    }
+
+    /// add attribute that is not in the structure yet
+    ///
+    /// attribute must have a value for every element of its class, tuple size, storage and defaults are taken from it,
+    /// typeinfo, like "vector" or "color", goes to attribute options.
+    /// returned attribute points to the added block, so it can be changed and written with write_to_strucutre later
+    pub fn add_attribute(
+        class: AttributeClass,
+        name: &str,
+        mut attr_kind: GeoAttributeKind,
+        typeinfo: Option<&str>,
+        structure: &mut ReaderElement,
+    ) -> Result<GeoAttributeKind, SchemaError> {
+        let root = ReaderElementPointer::new();
+        let element_count = HoudiniGeoSchemaParser::new(structure)?.element_count(class);
+        if attr_kind.len() != element_count {
+            let (count, expected) = (attr_kind.len(), element_count);
+            let message = format!("attribute {} has {} elements, geometry has {} {} elements", name, count, expected, class);
+            return Err(SchemaError::new(message, &root));
+        }
+        let mut options = OrderedMap::new();
+        if let Some(typeinfo) = typeinfo {
+            options.insert("type".into(), typeinfo_option(typeinfo));
+        }
+        let block = attribute_block(name, &attr_kind, &options);
+
+        let root_arr = match structure {
+            ReaderElement::Array(x) => x,
+            _ => return Err(SchemaError::new("geometry must be a kv array".to_owned(), &root)),
+        };
+        let attributes_idx = match kv_array_key_index(root_arr, "attributes") {
+            Some(i) => i,
+            None => {
+                // houdini has attributes right before primitives
+                let i = kv_array_key_index(root_arr, "primitives").unwrap_or(root_arr.len());
+                root_arr.splice(i..i, [ReaderElement::Text("attributes".into()), ReaderElement::Array(Vec::new())]);
+                i
+            }
+        };
+        let attributes_path = root.with_kv_array_key("attributes", attributes_idx + 1);
+        let attributes = match &mut root_arr[attributes_idx + 1] {
+            ReaderElement::Array(x) => x,
+            _ => return Err(SchemaError::new("attributes must be an array".to_owned(), &attributes_path)),
+        };
+
+        let key = attributes_key(class);
+        let class_idx = match kv_array_key_index(attributes, key) {
+            Some(i) => i,
+            None => {
+                // appended, so locations of attributes that are already there stay valid
+                attributes.push(ReaderElement::Text(key.into()));
+                attributes.push(ReaderElement::Array(Vec::new()));
+                attributes.len() - 2
             }
+        };
+        let class_path = attributes_path.with_kv_array_key(key, class_idx + 1);
+        let class_attributes = match &mut attributes[class_idx + 1] {
+            ReaderElement::Array(x) => x,
+            _ => return Err(SchemaError::new(format!("{} must be an array", key), &class_path)),
+        };
+        let exists = class_attributes.iter().any(|x| match x {
+            ReaderElement::Array(block) => block.first().and_then(|x| find_text_in_kv_array(x, "name")) == Some(name),
+            _ => false,
+        });
+        if exists {
+            return Err(SchemaError::new(format!("{} attribute {} already exists", class, name), &class_path));
         }
+
+        attr_kind.set_path(class_path.with_array_index(class_attributes.len()));
+        class_attributes.push(block);
+        Ok(attr_kind)
+    }
+}
+
+/// default values from attribute data block, empty if there are none
+fn parse_defaults<T>(
+    data_block: &ReaderElement,
+    data_path: &ReaderElementPointer,
+    tuple_size: usize,
+    mapper: &dyn Fn(&ReaderElement) -> Option<T>,
+) -> Result<Vec<T>, SchemaError> {
+    let (defaults, defaults_path) = match get_from_kv_array(data_block, "defaults", data_path)? {
+        Some(x) => x,
+        None => return Ok(Vec::new()),
+    };
+    let (values, values_path) = require_from_kv_array(defaults, "values", &defaults_path)?;
+    let values = match values.resolved() {
+        ReaderElement::Array(x) => Cow::Borrowed(x.as_slice()),
+        ReaderElement::UniformArray(x) => Cow::Owned(x.to_elements()),
+        _ => return Err(SchemaError::new("defaults values must be an array".to_owned(), &values_path)),
+    };
+    if values.len() > 1 && values.len() != tuple_size {
+        return Err(SchemaError::new(format!("defaults are not of declared size {}", tuple_size), &values_path));
+    }
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            mapper(x).ok_or_else(|| SchemaError::new("unexpected type of value".to_owned(), &values_path.with_array_index(i)))
+        })
+        .collect()
+}
+
+/// houdini key of attributes of the class, in the "attributes" block
+fn attributes_key(class: AttributeClass) -> &'static str {
+    match class {
+        AttributeClass::Vertex => "vertexattributes",
+        AttributeClass::Point => "pointattributes",
+        AttributeClass::Primitive => "primitiveattributes",
+        AttributeClass::Detail => "globalattributes",
+    }
+}
+
+/// typeinfo of an attribute as it is in attribute options, like {"type":"string","value":"vector"}
+pub(crate) fn typeinfo_option(typeinfo: &str) -> ReaderElement {
+    let mut option = OrderedMap::new();
+    option.insert("type".into(), ReaderElement::Text("string".into()));
+    option.insert("value".into(), ReaderElement::Text(typeinfo.into()));
+    ReaderElement::KeyValueObject(option)
+}
+
+pub(crate) fn push_kv(arr: &mut Vec<ReaderElement>, key: &str, value: ReaderElement) {
+    arr.push(ReaderElement::Text(key.into()));
+    arr.push(value);
+}
+
+/// text value of the key in a kv array, if it is there and is text
+fn find_text_in_kv_array<'a>(arr: &'a ReaderElement, key: &str) -> Option<&'a str> {
+    match arr {
+        ReaderElement::Array(arr) => match kv_array_key_index(arr, key).map(|i| &arr[i + 1]) {
+            Some(ReaderElement::Text(x)) => Some(x.as_str()),
+            _ => None,
+        },
+        _ => None,
     }
 }
+
+/// position of the key in a kv array
+fn kv_array_key_index(arr: &[ReaderElement], key: &str) -> Option<usize> {
+    arr.chunks_exact(2).position(|pair| matches!(&pair[0], ReaderElement::Text(k) if *k == key)).map(|i| 2 * i)
+}
+
+/// attribute in the same layout houdini writes it in
+pub(crate) fn attribute_block(
+    name: &str,
+    attr: &GeoAttributeKind,
+    options: &OrderedMap<SharedStr, ReaderElement>,
+) -> ReaderElement {
+    let attrib_type = match attr {
+        GeoAttributeKind::String(_) => "string",
+        _ => "numeric",
+    };
+    let mut metadata = Vec::new();
+    push_kv(&mut metadata, "scope", ReaderElement::Text("public".into()));
+    push_kv(&mut metadata, "type", ReaderElement::Text(attrib_type.into()));
+    push_kv(&mut metadata, "name", ReaderElement::Text(name.into()));
+    push_kv(&mut metadata, "options", ReaderElement::KeyValueObject(options.clone()));
+
+    let tuple_size = attr.tuple_size() as i64;
+    // defaults are always 64 bit, houdini writes them so too
+    let (storage, default_storage, defaults, values) = match attr {
+        GeoAttributeKind::Float64(x) => {
+            let defaults = x.defaults().iter().map(|x| ReaderElement::Float(*x)).collect::<Vec<_>>();
+            (x.storage(), "fpreal64", defaults, x.storage().encode_floats(x.data().to_vec()))
+        }
+        GeoAttributeKind::Int64(x) => {
+            let defaults = x.defaults().iter().map(|x| ReaderElement::Int(*x)).collect::<Vec<_>>();
+            (x.storage(), "int64", defaults, x.storage().encode_ints(x.data().to_vec()))
+        }
+        GeoAttributeKind::String(x) => {
+            let strings = x.tokens().iter().map(|x| ReaderElement::Text(x.as_str().into())).collect();
+            let indices = UniformArrayType::UniformArrayTi32(x.indices().iter().map(|x| *x as i32).collect());
+            let mut indices_block = Vec::new();
+            push_kv(&mut indices_block, "size", ReaderElement::Int(1));
+            push_kv(&mut indices_block, "storage", ReaderElement::Text("int32".into()));
+            push_kv(&mut indices_block, "arrays", ReaderElement::Array(vec![ReaderElement::UniformArray(indices)]));
+
+            let mut data = Vec::new();
+            push_kv(&mut data, "size", ReaderElement::Int(1));
+            push_kv(&mut data, "storage", ReaderElement::Text("int32".into()));
+            push_kv(&mut data, "strings", ReaderElement::Array(strings));
+            push_kv(&mut data, "indices", ReaderElement::Array(indices_block));
+            return ReaderElement::Array(vec![ReaderElement::Array(metadata), ReaderElement::Array(data)]);
+        }
+    };
+    let defaults = match defaults.is_empty() {
+        true if storage.is_float() => vec![ReaderElement::Float(0.0)],
+        true => vec![ReaderElement::Int(0)],
+        false => defaults,
+    };
+
+    let mut defaults_block = Vec::new();
+    push_kv(&mut defaults_block, "size", ReaderElement::Int(defaults.len() as i64));
+    push_kv(&mut defaults_block, "storage", ReaderElement::Text(default_storage.into()));
+    push_kv(&mut defaults_block, "values", ReaderElement::Array(defaults));
+
+    let mut values_block = Vec::new();
+    push_kv(&mut values_block, "size", ReaderElement::Int(tuple_size));
+    push_kv(&mut values_block, "storage", ReaderElement::Text(storage.name().into()));
+    push_kv(&mut values_block, "pagesize", ReaderElement::Int(1024));
+    push_kv(&mut values_block, "rawpagedata", ReaderElement::UniformArray(values));
+
+    let mut data = Vec::new();
+    push_kv(&mut data, "size", ReaderElement::Int(tuple_size));
+    push_kv(&mut data, "storage", ReaderElement::Text(storage.name().into()));
+    push_kv(&mut data, "defaults", ReaderElement::Array(defaults_block));
+    push_kv(&mut data, "values", ReaderElement::Array(values_block));
+    ReaderElement::Array(vec![ReaderElement::Array(metadata), ReaderElement::Array(data)])
+}
+
+/// replacements of numeric attribute data block keys, for write_to_strucutre
+fn numeric_replacements(
+    tuple_size: usize,
+    storage: AttributeStorage,
+    rawpagedata: UniformArrayType,
+) -> Vec<(&'static str, Option<ReaderElement>)> {
+    let values = ReaderElement::Array(vec![
+        ReaderElement::Text("size".into()),
+        ReaderElement::Int(tuple_size as i64),
+        ReaderElement::Text("storage".into()),
+        ReaderElement::Text(storage.name().into()),
+        ReaderElement::Text("pagesize".into()),
+        ReaderElement::Int(1024),
+        ReaderElement::Text("rawpagedata".into()),
+        ReaderElement::UniformArray(rawpagedata),
+    ]);
+    vec![
+        ("size", Some(ReaderElement::Int(tuple_size as i64))),
+        ("storage", Some(ReaderElement::Text(storage.name().into()))),
+        ("values", Some(values)),
+    ]
+}
@@ -1,66 +1,166 @@
 use crate::expressions::{
-    self, evaluate_expression_precompiled_with_bindings, BindingValue, ExpressionError,
+    self, evaluate_expression_precompiled_with_bindings, BindingValue, EvaluationError, ExpressionError,
     PrecompiledCode,
 };
-use crate::geo_struct::ReaderElement;
+use crate::geo_struct::{ReaderElement, ReaderElementPointer};
 use crate::houdini_geo_schema::{
-    GeoAttribute, GeoAttributeKind, HoudiniGeoSchemaParser, TupleGeoAttribute, TupleGeoAttributeChunk,
+    AttributeClass, AttributeStorage, GeoAttribute, GeoAttributeKind, HoudiniGeoSchemaParser, SchemaError, TupleGeoAttribute,
+    TupleGeoAttributeChunk,
 };
+use std::fmt;
 use std::num::NonZeroUsize;
 use std::thread;
 
+#[derive(Debug)]
+pub enum ManipulatorError {
+    ExpressionError(ExpressionError),
+    SchemaError(SchemaError),
+}
+
+impl fmt::Display for ManipulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManipulatorError::ExpressionError(ExpressionError::CompilationError(e)) => write!(f, "{}", e),
+            ManipulatorError::ExpressionError(ExpressionError::EvaluationError(e)) => write!(f, "{}", e),
+            ManipulatorError::SchemaError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<SchemaError> for ManipulatorError {
+    fn from(err: SchemaError) -> Self {
+        ManipulatorError::SchemaError(err)
+    }
+}
+
+impl From<EvaluationError> for ManipulatorError {
+    fn from(err: EvaluationError) -> Self {
+        ManipulatorError::ExpressionError(ExpressionError::EvaluationError(err))
+    }
+}
+
 pub struct HoudiniGeoSchemaManipulator<'a> {
     result_geo_data: ReaderElement,
     schema_parser: HoudiniGeoSchemaParser<'a>,
+    target_storage: Option<AttributeStorage>,
 }
 
 impl<'a> HoudiniGeoSchemaManipulator<'a> {
-    pub fn new(geo_data: &'a ReaderElement) -> HoudiniGeoSchemaManipulator<'a> {
-        HoudiniGeoSchemaManipulator {
+    pub fn new(geo_data: &'a ReaderElement) -> Result<HoudiniGeoSchemaManipulator<'a>, SchemaError> {
+        Ok(HoudiniGeoSchemaManipulator {
             result_geo_data: geo_data.clone(),
-            schema_parser: HoudiniGeoSchemaParser::new(&geo_data),
-        }
+            schema_parser: HoudiniGeoSchemaParser::new(&geo_data)?,
+            target_storage: None,
+        })
+    }
+
+    /// storage to write target attributes in, by default they keep storage they were read with
+    pub fn set_target_storage(&mut self, storage: AttributeStorage) {
+        self.target_storage = Some(storage);
     }
 
     pub fn into_result(self) -> ReaderElement {
         self.result_geo_data
     }
 
-    pub fn run_over_point_attributes(&mut self, expression: &str, target_attribute_name: &str) -> Result<(), ExpressionError> {
+    pub fn run_over_point_attributes(&mut self, expression: &str, target_attribute_name: &str) -> Result<(), ManipulatorError> {
         let precomp = match expressions::precompile_expression(expression) {
             Ok(x) => x,
             Err(e) => {
-                return Err(ExpressionError::CompilationError(e));
+                return Err(ManipulatorError::ExpressionError(ExpressionError::CompilationError(e)));
             }
         };
-        match self.run_over_point_attributes_precompiled(&precomp, target_attribute_name) {
-            _ => Ok(())  // TODO: provide error reports from run_over_point_attributes_precompiled
-        }
+        self.run_over_point_attributes_precompiled(&precomp, target_attribute_name, None)
+    }
+
+    /// same as run_over_point_attributes, but points that are not in the point group keep their values
+    pub fn run_over_point_group(
+        &mut self,
+        expression: &str,
+        target_attribute_name: &str,
+        group_name: &str,
+    ) -> Result<(), ManipulatorError> {
+        let precomp = match expressions::precompile_expression(expression) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(ManipulatorError::ExpressionError(ExpressionError::CompilationError(e)));
+            }
+        };
+        self.run_over_point_attributes_precompiled(&precomp, target_attribute_name, Some(group_name))
     }
 
-    pub fn run_over_point_attributes_precompiled(&mut self, precomp: &PrecompiledCode, target_attribute_name: &str) {
-        self.schema_parser.parse_point_attributes();
+    /// run precompiled expression over all points, or just over points of the point group if one is given
+    pub fn run_over_point_attributes_precompiled(
+        &mut self,
+        precomp: &PrecompiledCode,
+        target_attribute_name: &str,
+        group_name: Option<&str>,
+    ) -> Result<(), ManipulatorError> {
+        self.schema_parser.parse_point_attributes()?;
 
-        let target_attribute_kind = if let Some(x) = self.schema_parser.point_attribute(target_attribute_name) {
-            x
-        } else {
-            panic!("no target point attribute '{}' found", target_attribute_name);
+        let group = match group_name {
+            Some(name) => {
+                self.schema_parser.parse_groups()?;
+                match self.schema_parser.group(AttributeClass::Point, name) {
+                    Some(x) => Some(x.members()),
+                    None => {
+                        return Err(
+                            SchemaError::new(format!("no point group '{}' found", name), &ReaderElementPointer::new()).into(),
+                        );
+                    }
+                }
+            }
+            None => None,
         };
 
-        // TODO: this is all a prototype placeholder for now
         let mut bind_attrs = Vec::new();
         for attr_name in precomp.binding_names() {
-            if let Some(attr) = self.schema_parser.point_attribute(attr_name) {
-                bind_attrs.push(attr);
-            } else {
-                // panic for now, maybe TODO some defaults later
-                panic!("requested attribute {} not found", attr_name);
+            match self.schema_parser.point_attribute(attr_name) {
+                Some(attr @ GeoAttributeKind::Float64(x)) if x.tuple_size() == 1 || x.tuple_size() == 3 => {
+                    bind_attrs.push(attr);
+                }
+                Some(_) => {
+                    return Err(SchemaError::new(
+                        format!(
+                            "point attribute '{}' can not be used in expressions, only float and vector ones can",
+                            attr_name
+                        ),
+                        &ReaderElementPointer::new(),
+                    )
+                    .into());
+                }
+                None => {
+                    return Err(SchemaError::new(
+                        format!("no point attribute '{}' found", attr_name),
+                        &ReaderElementPointer::new(),
+                    )
+                    .into());
+                }
             }
         }
 
+        let target_attribute_kind = match self.schema_parser.point_attribute(target_attribute_name) {
+            Some(x) => x.clone(),
+            None => {
+                // new attribute, as wide as expression result is
+                let point_count = self.schema_parser.element_count(AttributeClass::Point);
+                let tuple_size = expression_result_size(precomp, &bind_attrs, point_count)?;
+                let attr = TupleGeoAttribute::new(tuple_size, vec![0.0; tuple_size * point_count]);
+                HoudiniGeoSchemaParser::add_attribute(
+                    AttributeClass::Point,
+                    target_attribute_name,
+                    GeoAttributeKind::Float64(attr),
+                    None,
+                    &mut self.result_geo_data,
+                )?
+            }
+        };
+
         match target_attribute_kind {
-            GeoAttributeKind::Float64(target_attr_source) => {
-                let mut target_attr = target_attr_source.clone();
+            GeoAttributeKind::Float64(mut target_attr) => {
+                if let Some(storage) = self.target_storage {
+                    target_attr.set_storage(storage)?;
+                }
 
                 let min_thread_chunk = 1024_usize; // TODO: make a parameter !
 
@@ -77,28 +177,37 @@ impl<'a> HoudiniGeoSchemaManipulator<'a> {
                         let mut handles = Vec::new(); // TODO: with capacity
 
                         for chunk in chunks.iter_mut() {
-                            let thread_handle = scope.spawn(|| {
-                                TupleGeoAttributeChunk::run_over_f64(&precomp, chunk, &bind_attrs);
-                            });
+                            let thread_handle =
+                                scope.spawn(|| TupleGeoAttributeChunk::run_over_f64(precomp, chunk, &bind_attrs, group));
                             handles.push(thread_handle);
                         }
+                        // all threads are joined by the scope, even if an error is returned early
                         for handle in handles {
-                            handle.join().expect("thread joinint failure!");
+                            handle.join().expect("thread joinint failure!")?;
                         }
-                    });
+                        Ok::<(), ManipulatorError>(())
+                    })?;
                 } else {
                     // single thread
-                    TupleGeoAttribute::run_over_f64(&precomp, &mut target_attr, &bind_attrs);
+                    TupleGeoAttribute::run_over_f64(precomp, &mut target_attr, &bind_attrs, group)?;
                 }
 
-                HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Float64(target_attr), &mut self.result_geo_data);
+                HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Float64(target_attr), &mut self.result_geo_data)?;
             }
             _ => {
-                panic!("not yet implemented!");
+                return Err(SchemaError::new(
+                    format!(
+                        "expressions can only be written to float attributes, '{}' is not one",
+                        target_attribute_name
+                    ),
+                    &ReaderElementPointer::new(),
+                )
+                .into());
             }
         }
 
         // HoudiniGeoSchemaParser::get_point_attrib_element_mut(&mut self.result_geo_data)
+        Ok(())
     }
 }
 
@@ -106,49 +215,84 @@ impl<'a> HoudiniGeoSchemaManipulator<'a> {
 /// trait to help implement same function for several similar types
 ///
 trait RunOverF64Attribute<'b> {
-    fn run_over_f64(precomp: &PrecompiledCode, target_attr: &mut Self, bind_attrs: &Vec<&GeoAttributeKind>);
+    /// group, if given, is the membership flag of every element, elements not in it are left as they are
+    fn run_over_f64(
+        precomp: &PrecompiledCode,
+        target_attr: &mut Self,
+        bind_attrs: &[&GeoAttributeKind],
+        group: Option<&[bool]>,
+    ) -> Result<(), ManipulatorError>;
 }
 
 macro_rules! _helper_run_over_f64 {
     ($ftype:ty) => {
         impl<'b> RunOverF64Attribute<'b> for $ftype {
-            fn run_over_f64(precomp: &PrecompiledCode, target_attr: &mut $ftype, bind_attrs: &Vec<&GeoAttributeKind>) {
+            fn run_over_f64(
+                precomp: &PrecompiledCode,
+                target_attr: &mut $ftype,
+                bind_attrs: &[&GeoAttributeKind],
+                group: Option<&[bool]>,
+            ) -> Result<(), ManipulatorError> {
                 let mut values = precomp.clone_binding_values();
 
                 for elem in target_attr.get_element_numbers_range() {
-                    for (bvalue, attr_kind) in values.iter_mut().zip(bind_attrs.iter()) {
-                        // TODO: this matching inside biiig loop is highly ineffective - surely we can match everything beforehand?
-                        match attr_kind {
-                            GeoAttributeKind::Float64(attr) => match attr.tuple_size() {
-                                1 => {
-                                    *bvalue = BindingValue::Float(attr.value(elem)[0]);
-                                }
-                                3 => {
-                                    *bvalue = BindingValue::Vector3(attr.value(elem).try_into().expect("impossibru"));
-                                }
-                                i => {
-                                    panic!("attrib tuples of size {} are not yet supported in expressions", i);
-                                }
-                            },
-                            _ => {
-                                panic!("not yet unplemented!");
-                            }
-                        }
+                    if group.is_some_and(|members| !members[elem]) {
+                        continue;
                     }
-                    let val =
-                        evaluate_expression_precompiled_with_bindings(precomp, &values).expect("failed to evaluate expression");
-                    match val {
+                    bind_element_values(&mut values, bind_attrs, elem);
+                    match evaluate_expression_precompiled_with_bindings(precomp, &values)? {
                         BindingValue::Float(f) => target_attr.set_value(elem, &[f]),
                         BindingValue::Vector3(v) => target_attr.set_value(elem, &v.as_slice()[..target_attr.tuple_size()]),
                         _ => {
-                            panic!("only f64 attribs are supported in this prototype");
+                            return Err(SchemaError::new(
+                                "only float and vector expression results can be written".to_owned(),
+                                &ReaderElementPointer::new(),
+                            )
+                            .into());
                         }
                     };
                 }
+                Ok(())
             }
         }
     };
 }
 
+/// set binding values to values of attributes of the element, attributes are checked to be float or vector beforehand
+fn bind_element_values(values: &mut [BindingValue], bind_attrs: &[&GeoAttributeKind], elem: usize) {
+    for (bvalue, attr_kind) in values.iter_mut().zip(bind_attrs.iter()) {
+        // TODO: this matching inside biiig loop is highly ineffective - surely we can match everything beforehand?
+        match attr_kind {
+            GeoAttributeKind::Float64(attr) => match attr.tuple_size() {
+                1 => {
+                    *bvalue = BindingValue::Float(attr.value(elem)[0]);
+                }
+                3 => {
+                    *bvalue = BindingValue::Vector3(attr.value(elem).try_into().expect("impossibru"));
+                }
+                i => unreachable!("attrib tuples of size {} are not supported in expressions", i),
+            },
+            _ => unreachable!("only float attributes are bound"),
+        }
+    }
+}
+
+/// tuple size of expression result, as it evaluates for the first element, 1 if there are no elements
+fn expression_result_size(
+    precomp: &PrecompiledCode,
+    bind_attrs: &[&GeoAttributeKind],
+    element_count: usize,
+) -> Result<usize, EvaluationError> {
+    if element_count == 0 {
+        return Ok(1);
+    }
+    let mut values = precomp.clone_binding_values();
+    bind_element_values(&mut values, bind_attrs, 0);
+    match evaluate_expression_precompiled_with_bindings(precomp, &values)? {
+        BindingValue::Vector3(_) => Ok(3),
+        _ => Ok(1),
+    }
+}
+
 _helper_run_over_f64!(TupleGeoAttributeChunk<'b, f64>);
 _helper_run_over_f64!(TupleGeoAttribute<f64>);
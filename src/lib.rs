@@ -1,18 +1,34 @@
 mod geo_parsing;
 mod geo_struct;
+mod geometry;
 mod houdini_geo_schema;
 mod stl_converter;
 mod obj_converter;
 mod f16_half;
 mod convert_from_trait;
+mod file_format;
+mod ordered_map;
+mod shared_str;
+pub mod compression;
+pub mod diagnostics;
 pub mod expressions;
 pub mod houdini_geo_schema_manipulator;
 pub mod geo_struct_serializer;
 pub mod bgeo_struct_serializer;
 pub mod bgeo_constants;
 
-pub use crate::geo_parsing::{parse_ascii, parse_binary, parse};
-pub use crate::geo_struct::{ReaderElement, UniformArrayType};
-pub use crate::houdini_geo_schema::{HoudiniGeoSchemaParser, GeoAttributeKind, GeoAttribute};
+pub use crate::geo_parsing::{
+    parse_ascii, parse_binary, parse, try_parse_ascii, try_parse_binary, try_parse, try_parse_mapped,
+    try_parse_mapped_with_options, try_parse_with_options, GeoParseError, MappedFile, ParseOptions,
+};
+pub use crate::geometry::{GeoPrimitive, Geometry, GeometryAttribute};
+pub use crate::geo_struct::{LazyUniformArray, ReaderElement, ReaderElementPointer, TupleArray, UniformArrayType};
+pub use crate::houdini_geo_schema::{
+    HoudiniGeoSchemaParser, GeoAttributeKind, GeoAttribute, AttributeClass, AttributeHeader, GeoHeader, SchemaError,
+    TupleGeoAttribute, TokenGeoAttribute, ElementGroup, EdgeGroup, AttributeStorage,
+};
 pub use crate::stl_converter::{create_stl_solid, serialize_stl};
 pub use crate::obj_converter::serialize_obj;
+pub use crate::file_format::FileFormat;
+pub use crate::ordered_map::OrderedMap;
+pub use crate::shared_str::SharedStr;
@@ -1,54 +1,161 @@
+use geoconverter::diagnostics::{self, DiagnosticLevel};
 use geoconverter::expressions::precompile_expression;
-use geoconverter::houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator;
-use geoconverter::{create_stl_solid, parse, serialize_obj, serialize_stl, HoudiniGeoSchemaParser, ReaderElement};
+use geoconverter::houdini_geo_schema_manipulator::{HoudiniGeoSchemaManipulator, ManipulatorError};
+use geoconverter::bgeo_struct_serializer::Endianness;
+use geoconverter::{
+    create_stl_solid, serialize_obj, serialize_stl, try_parse_mapped_with_options, try_parse_with_options, AttributeClass,
+    AttributeHeader, AttributeStorage, ElementGroup, FileFormat, GeoAttribute, GeoAttributeKind, GeoHeader, Geometry,
+    HoudiniGeoSchemaParser, MappedFile, ParseOptions, ReaderElement, ReaderElementPointer, SchemaError,
+};
 use std::env::args;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
-enum ConvertionType {
-    Obj,
-    Stl,
-    Geo,
-    Bgeo,
-}
-
 enum InputType {
     Stdin(io::StdinLock<'static>),
     File(io::BufReader<File>),
 }
 
+impl InputType {
+    /// open input file, no path or `-` stand for stdin
+    fn open(path: Option<&str>, help: &'static str) -> Result<InputType, ArgumentParsingError> {
+        match path {
+            None | Some("-") => Ok(InputType::Stdin(io::stdin().lock())),
+            Some(path) => match File::open(path) {
+                Ok(file) => Ok(InputType::File(io::BufReader::new(file))),
+                Err(e) => Err(ArgumentParsingError {
+                    ohnoo: format!("failed to open input file '{}': {}", path, e),
+                    help,
+                }),
+            },
+        }
+    }
+
+    fn as_buf_read(&mut self) -> &mut dyn BufRead {
+        match self {
+            InputType::File(x) => x,
+            InputType::Stdin(x) => x,
+        }
+    }
+}
+
 enum OutputType {
     Stdout(io::StdoutLock<'static>),
     File(io::BufWriter<File>),
 }
 
+impl OutputType {
+    /// create output file, no path or `-` stand for stdout
+    fn create(path: Option<&str>, help: &'static str) -> Result<OutputType, ArgumentParsingError> {
+        match path {
+            None | Some("-") => Ok(OutputType::Stdout(io::stdout().lock())),
+            Some(path) => match File::create(path) {
+                Ok(file) => Ok(OutputType::File(io::BufWriter::new(file))),
+                Err(e) => Err(ArgumentParsingError {
+                    ohnoo: format!("failed to create output file '{}': {}", path, e),
+                    help,
+                }),
+            },
+        }
+    }
+}
+
 pub enum VerbocityLevel {
     Silent,
     Verbose
 }
 
 struct ArgumentOptions {
-    convertion_type: ConvertionType,
+    /// None if neither given explicitly nor known from output extension
+    convertion_type: Option<FileFormat>,
     input_type: InputType,
+    input_path: Option<String>,
     output_type: OutputType,
     expression: Option<String>,
+    /// point group for the expression, primitive group for obj and stl output
+    group: Option<String>,
+    /// storage to write expression target attribute in, None keeps the one it was read with
+    storage: Option<AttributeStorage>,
+    endianness: Endianness,
     verbocity: VerbocityLevel,
     just_print_help: bool,
 }
 
+/// options of commands that just read a single geometry
+struct InspectOptions {
+    input_type: InputType,
+    input_path: Option<String>,
+    verbocity: VerbocityLevel,
+    strict: bool,
+}
+
+struct DiffOptions {
+    first: (InputType, Option<String>),
+    second: (InputType, Option<String>),
+    tolerance: f64,
+    verbocity: VerbocityLevel,
+}
+
+struct QueryOptions {
+    attribute: String,
+    class: AttributeClass,
+    range: Option<Range<usize>>,
+    input_type: InputType,
+    input_path: Option<String>,
+    verbocity: VerbocityLevel,
+}
+
+enum Command {
+    Convert(ArgumentOptions),
+    Exec(ArgumentOptions),
+    Info(InspectOptions),
+    Validate(InspectOptions),
+    Diff(DiffOptions),
+    Query(QueryOptions),
+    /// general help if None, or given command's help
+    Help(Option<&'static str>),
+}
+
 const HELP_MESSAGE: &str = "
-usage: geoconverter [-h] [-v] [e expression] [-t type] [input_file] [output_file]
-    
-    -t type (default=obj)   Type of output file, available types are obj,stl,geo,bgeo
-    -e expression           Expression to run over a point attribute. 
+usage: geoconverter <command> [options] [args]
+       geoconverter [convert options] [input_file] [output_file]
+
+    convert     Convert geometry into another format, this is what happens if no command is given
+    exec        Run an expression over a point attribute and write the geometry back
+    info        Print element counts, attributes and bounds of the geometry, reading just its header
+    validate    Check that geometry can be fully read
+    diff        Compare two geometries
+    query       Print values of an attribute
+    help        Print help of a command
+
+Run 'geoconverter help <command>' or 'geoconverter <command> -h' to see command's options.
+Input is taken from stdin if no input file is given, and output (converted geometry or
+any printed info) is written to stdout if no output file is given, so commands can be piped.
+";
+
+const CONVERT_HELP: &str = "
+usage: geoconverter [convert] [-h] [-v] [-e expression] [-g group] [--storage type]
+                    [-t type] [--endian order] [input_file] [output_file]
+
+    -t type                 Type of output file, available types are obj,stl,geo,bgeo,bgeo.sc,geo.gz,bgeo.gz
+                            if not provided - it's taken from output file extension,
+                            and if that is not known either (or output is stdout) - obj is assumed
+    --endian order          Byte order of bgeo output, little (default) or big
+    -e expression           Expression to run over a point attribute.
                             It should have a form of '@attr = expression', where 'attr' is some
-                            existing point attribute on geometry, 
+                            existing point attribute on geometry,
                             expression may have bindings to other attributes using '@otherattr' syntax
-    -v                      Print some verbose info to stderr
-    -h                      Print this help message and exit, other args are ignored
+    -g group                Only work on elements of this group: expression runs over points of the point group,
+                            obj and stl output gets polygons of the primitive group
+    --storage type          Storage to write expression target attribute in, fpreal16, fpreal32 or fpreal64,
+                            by default attribute keeps storage it was read with
+    -v                      Print some verbose info and timings to stderr, warnings are printed there always
+    -h, --help              Print this help message and exit, other args are ignored
 
-If last 2 arguments are file paths - 
+If last 2 arguments are file paths -
   first is interpreted as input file path,
   second is interpreted as output file path
 If just ONE file path provided - it's interpreted as output file path
@@ -56,28 +163,177 @@ If just ONE file path provided - it's interpreted as output file path
 If NO file paths provided -
   input is taken from stdin
   output is written to stdout
+Input type is detected from the data itself, compressed inputs are decompressed automatically
 ";
 
+const EXEC_HELP: &str = "
+usage: geoconverter exec [-h] [-v] [-g group] [--storage type] [-t type] [--endian order] -e expression
+                         [input_file] [output_file]
+
+    -e expression           Expression to run over a point attribute, required.
+                            It should have a form of '@attr = expression', where 'attr' is some
+                            existing point attribute on geometry,
+                            expression may have bindings to other attributes using '@otherattr' syntax
+    -g group                Only run expression over points of this point group
+    --storage type          Storage to write target attribute in, fpreal16, fpreal32 or fpreal64,
+                            by default attribute keeps storage it was read with
+    -t type                 Type of output file, available types are obj,stl,geo,bgeo,bgeo.sc,geo.gz,bgeo.gz
+                            if not provided - it's taken from output file extension,
+                            and if that is not known either (or output is stdout) - input type is kept
+    --endian order          Byte order of bgeo output, little (default) or big
+    -v                      Print some verbose info and timings to stderr, warnings are printed there always
+    -h, --help              Print this help message and exit, other args are ignored
+
+File paths are treated the same way as in convert command
+";
+
+const INFO_HELP: &str = "
+usage: geoconverter info [-h] [-v] [input_file]
+
+Print input format, element counts, attributes of the geometry to stdout,
+and bounding box, software and date from its info block.
+Only the header of the geometry is read, so it's fast even for huge caches
+
+    -v                      Print some verbose info to stderr, warnings are printed there always
+    -h, --help              Print this help message and exit, other args are ignored
+
+If no input file is provided - input is taken from stdin
+";
+
+const VALIDATE_HELP: &str = "
+usage: geoconverter validate [-h] [-v] [-s] [input_file]
+
+Read the geometry fully, including all attributes and primitives, and print 'ok' if that worked.
+Exit code is 1 if geometry is not valid, problems are printed to stderr
+
+    -s                      Strict, treat warnings (like skipped unsupported attributes) as errors
+    -v                      Print some verbose info to stderr, warnings are printed there always
+    -h, --help              Print this help message and exit, other args are ignored
+
+If no input file is provided - input is taken from stdin
+";
+
+const DIFF_HELP: &str = "
+usage: geoconverter diff [-h] [-v] [--tolerance value] first_file second_file
+
+Compare element counts, topology, attributes and attribute values of two geometries,
+differences are printed to stdout. Exit code is 0 if geometries are the same, 1 otherwise.
+Float values are compared at the precision they are stored in, so a fpreal32 attribute matches its ascii form
+
+    --tolerance value (default=0)   Max absolute difference of float values to still count them as equal
+    -v                              Print some verbose info to stderr, warnings are printed there always
+    -h, --help                      Print this help message and exit, other args are ignored
+
+One of the file paths may be '-', then that geometry is taken from stdin
+";
+
+const QUERY_HELP: &str = "
+usage: geoconverter query [-h] [-v] [-c class] [-r range] attribute [input_file]
+
+Print values of an attribute to stdout, one element per line, tuple components separated by spaces
+
+    -c class (default=point)   Class of the attribute: point, vertex, prim or detail
+    -r range                   Only print these elements, like '5', '5:10', ':10' or '5:'
+    -v                         Print some verbose info to stderr, warnings are printed there always
+    -h, --help                 Print this help message and exit, other args are ignored
+
+If no input file is provided - input is taken from stdin
+";
+
+/// warnings emitted so far, for strict validation
+static WARNING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 fn main() {
     let mut argv = args();
     argv.next().expect("zero argument not provided? unexpected");
 
-    let mut options = match parse_arguments(&mut argv) {
+    let command = match parse_command(&mut argv) {
         Ok(x) => x,
         Err(e) => {
-            println!("Error parsing arguments: {}\n\n", e.ohnoo);
-            print!("{}", HELP_MESSAGE);
+            eprintln!("Error parsing arguments: {}\n\n", e.ohnoo);
+            eprint!("{}", e.help);
             std::process::exit(2);
         }
     };
 
-    if options.just_print_help {
-        print!("{}", HELP_MESSAGE);
-        std::process::exit(0);
+    match command {
+        Command::Help(help) => {
+            print!("{}", help.unwrap_or(HELP_MESSAGE));
+        }
+        Command::Convert(options) if options.just_print_help => print!("{}", CONVERT_HELP),
+        Command::Exec(options) if options.just_print_help => print!("{}", EXEC_HELP),
+        // no type and no known output extension - convert falls back to obj, while exec keeps input type
+        Command::Convert(options) => run_convert(options, Some(FileFormat::Obj)),
+        Command::Exec(options) => run_convert(options, None),
+        Command::Info(options) => run_info(options),
+        Command::Validate(options) => run_validate(options),
+        Command::Diff(options) => run_diff(options),
+        Command::Query(options) => run_query(options),
+    }
+}
+
+fn install_diagnostics_handler(verbocity: &VerbocityLevel) {
+    // library diagnostics must never end up in stdout, as output geometry may be written there
+    let min_diagnostic_level = match verbocity {
+        VerbocityLevel::Verbose => DiagnosticLevel::Info,
+        VerbocityLevel::Silent => DiagnosticLevel::Warning,
+    };
+    diagnostics::set_handler(move |level, message| {
+        if level >= DiagnosticLevel::Warning {
+            WARNING_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+        if level >= min_diagnostic_level {
+            eprintln!("{}: {}", level, message);
+        }
+    });
+}
+
+/// read and parse input geometry, exits with an error message if that is not possible
+///
+/// input format is returned too, if it was recognized
+///
+/// uncompressed bgeo files are memory mapped, so big arrays are only decoded if they are needed
+fn read_geometry(
+    input: &mut InputType,
+    input_path: Option<&str>,
+    parse_options: &ParseOptions,
+) -> (ReaderElement, Option<FileFormat>) {
+    let head = input.as_buf_read().fill_buf().unwrap_or_else(|err| {
+        eprintln!("failed to read input: {}", err);
+        std::process::exit(1);
+    });
+    let format = FileFormat::sniff(head, input_path);
+    match format {
+        Some(format) if !format.is_readable() => {
+            eprintln!("input looks like {}, but only geo/bgeo (possibly compressed) can be read", format);
+            std::process::exit(1);
+        }
+        Some(format) => diagnostics::emit(DiagnosticLevel::Info, &format!("input detected as {}", format)),
+        None => diagnostics::emit(DiagnosticLevel::Info, "input format is unknown, trying to read it as ascii geo"),
     }
+    let res = match (format, &*input) {
+        (Some(FileFormat::Bgeo), InputType::File(file)) => match MappedFile::map(file.get_ref()) {
+            Ok(mapped) => try_parse_mapped_with_options(mapped, parse_options),
+            Err(err) => {
+                diagnostics::emit(DiagnosticLevel::Info, &format!("failed to map input file ({}), reading it instead", err));
+                try_parse_with_options(input.as_buf_read(), parse_options)
+            }
+        },
+        _ => try_parse_with_options(input.as_buf_read(), parse_options),
+    };
+    let res = res.unwrap_or_else(|err| {
+        eprintln!("Input parsing error:");
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    (res, format)
+}
+
+fn run_convert(mut options: ArgumentOptions, default_type: Option<FileFormat>) {
+    install_diagnostics_handler(&options.verbocity);
 
     let mut benchmark;
-    
+
 
     macro_rules! log{
         ($template:literal $(, $($arg:expr),*)?) => {
@@ -91,20 +347,20 @@ fn main() {
     let expr = if let Some(expr) = &options.expression {
         // for now we only expect expressions in form of `@attribute = expression`
         if !expr.starts_with("@") {
-            println!("for now only simple binding assignments are supported, like @foo=@bar*3+2, should start with @");
-            println!("but found: '{}'", expr);
+            eprintln!("for now only simple binding assignments are supported, like @foo=@bar*3+2, should start with @");
+            eprintln!("but found: '{}'", expr);
             std::process::exit(1);
         }
-        
+
         if let Some((first, second)) = expr.split_once("=") {
             Some((first[1..].trim(), precompile_expression(second.trim()).unwrap_or_else(|err| {
-                println!("Expression error:");
-                println!("{}", err);
+                eprintln!("Expression error:");
+                eprintln!("{}", err);
                 std::process::exit(1);
             })))
         } else {
-            println!("for now only simple binding assignments are supported, like @foo=@bar*3+2, shoudl start with @<attr_name>=...");
-            println!("but found: '{}'", expr);
+            eprintln!("for now only simple binding assignments are supported, like @foo=@bar*3+2, shoudl start with @<attr_name>=...");
+            eprintln!("but found: '{}'", expr);
             std::process::exit(1);
         }
     } else {
@@ -113,19 +369,44 @@ fn main() {
 
     benchmark = Instant::now();
 
+    // obj and stl only need a few attributes, so the rest is not even decoded,
+    // unless there's an expression, as it can use any attribute
+    let parse_options = match (expr.is_some(), options.convertion_type.or(default_type)) {
+        (false, Some(FileFormat::Obj | FileFormat::Stl)) => {
+            ["P", "N", "Cd", "uv"].iter().fold(ParseOptions::new(), |opts, name| opts.keep_attribute(name))
+        }
+        _ => ParseOptions::new(),
+    };
+
     // input parsing
-    let res = parse(match options.input_type {
-        InputType::File(ref mut x) => x,
-        InputType::Stdin(ref mut x) => x,
-    });
+    let (res, input_format) = read_geometry(&mut options.input_type, options.input_path.as_deref(), &parse_options);
     log!("input read took {}s");
 
+    // if input format is unknown, but we managed to parse it - it was ascii geo
+    let convertion_type = options
+        .convertion_type
+        .or(default_type)
+        .or(input_format)
+        .unwrap_or(FileFormat::Geo);
+
     // processing
     let res = if let Some((target_attr_name, precomp_expr)) = expr {
-        let mut manip = HoudiniGeoSchemaManipulator::new(&res);
-        
+        let mut manip = HoudiniGeoSchemaManipulator::new(&res).unwrap_or_else(|err| exit_with_schema_error(err));
+        if let Some(storage) = options.storage {
+            manip.set_target_storage(storage);
+        }
+
         benchmark = Instant::now();
-        manip.run_over_point_attributes_precompiled(&precomp_expr, target_attr_name);
+        manip
+            .run_over_point_attributes_precompiled(&precomp_expr, target_attr_name, options.group.as_deref())
+            .unwrap_or_else(|err| match err {
+                ManipulatorError::SchemaError(err) => exit_with_schema_error(err),
+                ManipulatorError::ExpressionError(_) => {
+                    eprintln!("Expression error:");
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            });
         log!("processing took {}s");
 
         manip.into_result()
@@ -142,11 +423,18 @@ fn main() {
     benchmark = Instant::now();
 
     // convertion
-    match options.convertion_type {
-        ConvertionType::Obj => convert_to_obj(&res, out_ref),
-        ConvertionType::Stl => convert_to_stl(&res, out_ref),
-        ConvertionType::Geo => geoconverter::geo_struct_serializer::to_json(&res, out_ref),
-        ConvertionType::Bgeo => geoconverter::bgeo_struct_serializer::to_bjson(&res, out_ref),
+    match convertion_type {
+        FileFormat::Obj => {
+            convert_to_obj(&res, options.group.as_deref(), out_ref).unwrap_or_else(|err| exit_with_schema_error(err))
+        }
+        FileFormat::Stl => {
+            convert_to_stl(&res, options.group.as_deref(), out_ref).unwrap_or_else(|err| exit_with_schema_error(err))
+        }
+        FileFormat::Geo => geoconverter::geo_struct_serializer::to_json(&res, out_ref),
+        FileFormat::Bgeo => geoconverter::bgeo_struct_serializer::to_bjson_endian(&res, out_ref, options.endianness),
+        FileFormat::BgeoSc => geoconverter::bgeo_struct_serializer::to_bjson_sc_endian(&res, out_ref, options.endianness),
+        FileFormat::GeoGz => geoconverter::geo_struct_serializer::to_json_gz(&res, out_ref),
+        FileFormat::BgeoGz => geoconverter::bgeo_struct_serializer::to_bjson_gz_endian(&res, out_ref, options.endianness),
     }
 
     // don't forget to flush (but does it matter in the end of the program?)
@@ -157,21 +445,429 @@ fn main() {
     log!("convertion and write took {}s");
 }
 
+/// parse geometry schema with all attributes and primitives
+fn parse_schema(res: &ReaderElement) -> Result<HoudiniGeoSchemaParser<'_>, SchemaError> {
+    let mut schema = HoudiniGeoSchemaParser::new(res)?;
+    for class in AttributeClass::ALL {
+        schema.parse_class_attributes(class)?;
+    }
+    schema.parse_primitives()?;
+    Ok(schema)
+}
+
+/// attribute names sorted, as they are stored in no particular order
+fn sorted_attribute_names<'s>(schema: &'s HoudiniGeoSchemaParser, class: AttributeClass) -> Vec<&'s str> {
+    let mut names: Vec<&str> = schema.attribute_names(class).collect();
+    names.sort_unstable();
+    names
+}
+
+/// element value, with tuple components separated by spaces
+fn format_attribute_value(attr: &GeoAttributeKind, number: usize) -> String {
+    match attr {
+        GeoAttributeKind::Float64(attr) => attr.value(number).iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" "),
+        GeoAttributeKind::Int64(attr) => attr.value(number).iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" "),
+        GeoAttributeKind::String(attr) => attr.value(number).to_owned(),
+    }
+}
+
+fn run_info(mut options: InspectOptions) {
+    install_diagnostics_handler(&options.verbocity);
+
+    // only the header is read, so it's fast even for huge caches
+    let (res, format) = read_geometry(&mut options.input_type, options.input_path.as_deref(), &ParseOptions::header_only());
+    let header = GeoHeader::new(&res).unwrap_or_else(|err| exit_with_schema_error(err));
+
+    let mut out = io::stdout().lock();
+    write_info(&header, format, &mut out).and_then(|_| out.flush()).unwrap_or_else(|err| exit_with_write_error(err));
+}
+
+fn write_info(header: &GeoHeader, format: Option<FileFormat>, out: &mut dyn Write) -> io::Result<()> {
+    if let Some(format) = format {
+        writeln!(out, "format: {}", format)?;
+    }
+    writeln!(out, "points: {}", header.point_count)?;
+    writeln!(out, "vertices: {}", header.vertex_count)?;
+    writeln!(out, "primitives: {}", header.primitive_count)?;
+    for class in AttributeClass::ALL {
+        writeln!(out, "{} attributes:", class)?;
+        let mut attribs: Vec<&AttributeHeader> = header.class_attributes(class).collect();
+        attribs.sort_unstable_by_key(|x| x.name.as_str());
+        for attr in attribs {
+            let tuple_size = attr.tuple_size.map_or("?".to_owned(), |x| x.to_string());
+            writeln!(out, "    {} {}[{}]", attr.name, attr.type_name(), tuple_size)?;
+        }
+    }
+    if let Some((min, max)) = header.bounds() {
+        writeln!(out, "bounds: min {} {} {}, max {} {} {}", min[0], min[1], min[2], max[0], max[1], max[2])?;
+    }
+    for key in ["software", "date"] {
+        if let Some(text) = header.info_text(key) {
+            writeln!(out, "{}: {}", key, text)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_validate(mut options: InspectOptions) {
+    install_diagnostics_handler(&options.verbocity);
+
+    let (res, _) = read_geometry(&mut options.input_type, options.input_path.as_deref(), &ParseOptions::new());
+    let schema = parse_schema(&res).unwrap_or_else(|err| exit_with_schema_error(err));
+
+    match schema.point_attribute("P") {
+        Some(GeoAttributeKind::Float64(p_attr)) if p_attr.tuple_size() >= 3 => (),
+        _ => {
+            eprintln!("no float P attribute with at least 3 components");
+            std::process::exit(1);
+        }
+    }
+    let warnings = WARNING_COUNT.load(Ordering::Relaxed);
+    if options.strict && warnings > 0 {
+        eprintln!("{} warnings, geometry is not valid in strict mode", warnings);
+        std::process::exit(1);
+    }
+    println!("ok");
+}
+
+fn run_diff(mut options: DiffOptions) {
+    install_diagnostics_handler(&options.verbocity);
+
+    let (first_res, _) = read_geometry(&mut options.first.0, options.first.1.as_deref(), &ParseOptions::new());
+    let (second_res, _) = read_geometry(&mut options.second.0, options.second.1.as_deref(), &ParseOptions::new());
+    let first = parse_schema(&first_res).unwrap_or_else(|err| exit_with_schema_error(err));
+    let second = parse_schema(&second_res).unwrap_or_else(|err| exit_with_schema_error(err));
+
+    let mut out = io::stdout().lock();
+    let differences = diff_geometry(&first, &second, options.tolerance, &mut out)
+        .and_then(|differences| out.flush().map(|_| differences))
+        .unwrap_or_else(|err| exit_with_write_error(err));
+    if differences > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// print differences between geometries, returns number of differences found
+fn diff_geometry(
+    first: &HoudiniGeoSchemaParser,
+    second: &HoudiniGeoSchemaParser,
+    tolerance: f64,
+    out: &mut dyn Write,
+) -> io::Result<usize> {
+    let mut differences = 0;
+    let mut report = |message: String| -> io::Result<()> {
+        writeln!(out, "{}", message)?;
+        differences += 1;
+        Ok(())
+    };
+
+    for class in AttributeClass::ALL {
+        let (first_count, second_count) = (first.element_count(class), second.element_count(class));
+        if first_count != second_count {
+            report(format!("{} count: {} != {}", class, first_count, second_count))?;
+        }
+    }
+
+    if first.polygons().len() != second.polygons().len() {
+        report(format!("polygon count: {} != {}", first.polygons().len(), second.polygons().len()))?;
+    } else {
+        for (first_prim, second_prim) in first.polygons().iter().zip(second.polygons()) {
+            let first_points = first_prim.vertices.iter().map(|v| v.ptnum);
+            let second_points = second_prim.vertices.iter().map(|v| v.ptnum);
+            if !first_points.eq(second_points) {
+                report(format!("primitive {}: points differ", first_prim.number))?;
+            }
+        }
+    }
+
+    for class in AttributeClass::ALL {
+        let first_names = sorted_attribute_names(first, class);
+        let second_names = sorted_attribute_names(second, class);
+        for name in second_names.iter().filter(|name| !first_names.contains(name)) {
+            report(format!("{} attribute '{}': only in second", class, name))?;
+        }
+        for name in first_names {
+            let first_attr = first.attribute(class, name).expect("attribute names must match attributes");
+            let second_attr = match second.attribute(class, name) {
+                Some(x) => x,
+                None => {
+                    report(format!("{} attribute '{}': only in first", class, name))?;
+                    continue;
+                }
+            };
+            if first_attr.type_name() != second_attr.type_name() || first_attr.tuple_size() != second_attr.tuple_size() {
+                report(format!(
+                    "{} attribute '{}': {}[{}] != {}[{}]",
+                    class,
+                    name,
+                    first_attr.type_name(),
+                    first_attr.tuple_size(),
+                    second_attr.type_name(),
+                    second_attr.tuple_size()
+                ))?;
+                continue;
+            }
+
+            // floats are compared at the precision of the less precise storage, so 32 bit values match their ascii form
+            let storage = [first_attr.storage(), second_attr.storage()].into_iter().flatten().min_by_key(|x| match x {
+                AttributeStorage::Fpreal16 => 0,
+                AttributeStorage::Fpreal32 => 1,
+                _ => 2,
+            });
+            let round = |x: f64| storage.map_or(x, |storage| storage.round_float(x));
+            let values_equal = |number: usize| match (first_attr, second_attr) {
+                (GeoAttributeKind::Float64(a), GeoAttributeKind::Float64(b)) => {
+                    a.value(number).iter().zip(b.value(number)).all(|(x, y)| (round(*x) - round(*y)).abs() <= tolerance)
+                }
+                (GeoAttributeKind::Int64(a), GeoAttributeKind::Int64(b)) => a.value(number) == b.value(number),
+                (GeoAttributeKind::String(a), GeoAttributeKind::String(b)) => a.value(number) == b.value(number),
+                _ => false,
+            };
+            let mut differing = (0..first_attr.len().min(second_attr.len())).filter(|&i| !values_equal(i));
+            if let Some(first_differing) = differing.next() {
+                report(format!(
+                    "{} attribute '{}': {} values differ, first at {}: '{}' != '{}'",
+                    class,
+                    name,
+                    differing.count() + 1,
+                    first_differing,
+                    format_attribute_value(first_attr, first_differing),
+                    format_attribute_value(second_attr, first_differing)
+                ))?;
+            }
+        }
+    }
+
+    Ok(differences)
+}
+
+fn run_query(mut options: QueryOptions) {
+    install_diagnostics_handler(&options.verbocity);
+
+    let (res, _) = read_geometry(&mut options.input_type, options.input_path.as_deref(), &ParseOptions::new());
+    let mut schema = HoudiniGeoSchemaParser::new(&res).unwrap_or_else(|err| exit_with_schema_error(err));
+    schema
+        .parse_class_attributes(options.class)
+        .unwrap_or_else(|err| exit_with_schema_error(err));
+
+    let attr = schema.attribute(options.class, &options.attribute).unwrap_or_else(|| {
+        eprintln!("no {} attribute '{}' found", options.class, options.attribute);
+        std::process::exit(1);
+    });
+    let range = options.range.take().unwrap_or(0..attr.len());
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    (range.start.min(attr.len())..range.end.min(attr.len()))
+        .try_for_each(|number| writeln!(out, "{}", format_attribute_value(attr, number)))
+        .and_then(|_| out.flush())
+        .unwrap_or_else(|err| exit_with_write_error(err));
+}
+
 enum ExpectedFlag {
     NotExpecting,
     ExpectingType,
-    ExpectingExpression
+    ExpectingExpression,
+    ExpectingGroup,
+    ExpectingStorage,
+    ExpectingEndianness,
 }
 
 struct ArgumentParsingError {
     ohnoo: String,
+    /// help message of the command that failed to parse
+    help: &'static str,
+}
+
+fn parse_command(argv: &mut dyn Iterator<Item = String>) -> Result<Command, ArgumentParsingError> {
+    let mut argv = argv.peekable();
+    let command = match argv.peek().map(String::as_str) {
+        Some("convert" | "exec" | "info" | "validate" | "diff" | "query" | "help") => argv.next().expect("just peeked"),
+        Some("-h" | "--help") => return Ok(Command::Help(None)),
+        // no command given - it's a plain old conversion
+        _ => return Ok(Command::Convert(parse_arguments(&mut argv)?)),
+    };
+
+    match command.as_str() {
+        "convert" => Ok(Command::Convert(parse_arguments(&mut argv)?)),
+        "exec" => {
+            let options = parse_arguments(&mut argv).map_err(|e| ArgumentParsingError { help: EXEC_HELP, ..e })?;
+            if options.expression.is_none() && !options.just_print_help {
+                return Err(ArgumentParsingError {
+                    ohnoo: "exec needs an expression, provide it with -e".to_owned(),
+                    help: EXEC_HELP,
+                });
+            }
+            Ok(Command::Exec(options))
+        }
+        "info" => parse_inspect_arguments(&mut argv, false),
+        "validate" => parse_inspect_arguments(&mut argv, true),
+        "diff" => parse_diff_arguments(&mut argv),
+        "query" => parse_query_arguments(&mut argv),
+        _ => match argv.next().as_deref() {
+            None => Ok(Command::Help(None)),
+            Some("convert") => Ok(Command::Help(Some(CONVERT_HELP))),
+            Some("exec") => Ok(Command::Help(Some(EXEC_HELP))),
+            Some("info") => Ok(Command::Help(Some(INFO_HELP))),
+            Some("validate") => Ok(Command::Help(Some(VALIDATE_HELP))),
+            Some("diff") => Ok(Command::Help(Some(DIFF_HELP))),
+            Some("query") => Ok(Command::Help(Some(QUERY_HELP))),
+            Some(s) => Err(ArgumentParsingError {
+                ohnoo: format!("unknown command '{}'", s),
+                help: HELP_MESSAGE,
+            }),
+        },
+    }
+}
+
+fn unexpected_argument(arg: &str, help: &'static str) -> ArgumentParsingError {
+    ArgumentParsingError {
+        ohnoo: format!("unexpected argument '{}'", arg),
+        help,
+    }
+}
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') && arg != "-"
+}
+
+/// arguments of info and validate commands
+fn parse_inspect_arguments(argv: &mut dyn Iterator<Item = String>, validate: bool) -> Result<Command, ArgumentParsingError> {
+    let help = if validate { VALIDATE_HELP } else { INFO_HELP };
+    let mut input_path: Option<String> = None;
+    let mut verbocity = VerbocityLevel::Silent;
+    let mut strict = false;
+
+    for arg in argv {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help(Some(help))),
+            "-v" => verbocity = VerbocityLevel::Verbose,
+            "-s" if validate => strict = true,
+            s if is_flag(s) || input_path.is_some() => return Err(unexpected_argument(s, help)),
+            _ => input_path = Some(arg),
+        }
+    }
+
+    let options = InspectOptions {
+        input_type: InputType::open(input_path.as_deref(), help)?,
+        input_path,
+        verbocity,
+        strict,
+    };
+    Ok(if validate { Command::Validate(options) } else { Command::Info(options) })
+}
+
+fn parse_diff_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<Command, ArgumentParsingError> {
+    let mut paths: Vec<String> = Vec::new();
+    let mut tolerance = 0.0;
+    let mut verbocity = VerbocityLevel::Silent;
+
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help(Some(DIFF_HELP))),
+            "-v" => verbocity = VerbocityLevel::Verbose,
+            "--tolerance" => {
+                tolerance = match argv.next().map(|x| x.parse::<f64>()) {
+                    Some(Ok(x)) if x >= 0.0 => x,
+                    _ => {
+                        return Err(ArgumentParsingError {
+                            ohnoo: "tolerance must be a non-negative number".to_owned(),
+                            help: DIFF_HELP,
+                        })
+                    }
+                }
+            }
+            s if is_flag(s) || paths.len() == 2 => return Err(unexpected_argument(s, DIFF_HELP)),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        return Err(ArgumentParsingError {
+            ohnoo: "diff needs exactly two geometries to compare".to_owned(),
+            help: DIFF_HELP,
+        });
+    }
+    if paths.iter().all(|p| p == "-") {
+        return Err(ArgumentParsingError {
+            ohnoo: "only one of the geometries can be read from stdin".to_owned(),
+            help: DIFF_HELP,
+        });
+    }
+    let second_path = paths.pop().filter(|p| p != "-");
+    let first_path = paths.pop().filter(|p| p != "-");
+    Ok(Command::Diff(DiffOptions {
+        first: (InputType::open(first_path.as_deref(), DIFF_HELP)?, first_path),
+        second: (InputType::open(second_path.as_deref(), DIFF_HELP)?, second_path),
+        tolerance,
+        verbocity,
+    }))
+}
+
+/// parse element range like `5`, `5:10`, `:10` or `5:`
+fn parse_range(range: &str) -> Option<Range<usize>> {
+    match range.split_once(':') {
+        None => {
+            let number: usize = range.parse().ok()?;
+            Some(number..number.saturating_add(1))
+        }
+        Some((start, end)) => {
+            let start = if start.is_empty() { 0 } else { start.parse().ok()? };
+            let end = if end.is_empty() { usize::MAX } else { end.parse().ok()? };
+            Some(start..end)
+        }
+    }
+}
+
+fn parse_query_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<Command, ArgumentParsingError> {
+    let mut attribute: Option<String> = None;
+    let mut input_path: Option<String> = None;
+    let mut class = AttributeClass::Point;
+    let mut range = None;
+    let mut verbocity = VerbocityLevel::Silent;
+
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help(Some(QUERY_HELP))),
+            "-v" => verbocity = VerbocityLevel::Verbose,
+            "-c" => {
+                class = argv.next().and_then(|x| AttributeClass::from_name(&x)).ok_or_else(|| ArgumentParsingError {
+                    ohnoo: "class must be one of point, vertex, prim, detail".to_owned(),
+                    help: QUERY_HELP,
+                })?;
+            }
+            "-r" => {
+                range = Some(argv.next().and_then(|x| parse_range(&x)).ok_or_else(|| ArgumentParsingError {
+                    ohnoo: "range must look like '5', '5:10', ':10' or '5:'".to_owned(),
+                    help: QUERY_HELP,
+                })?);
+            }
+            s if is_flag(s) || input_path.is_some() => return Err(unexpected_argument(s, QUERY_HELP)),
+            _ if attribute.is_none() => attribute = Some(arg),
+            _ => input_path = Some(arg),
+        }
+    }
+
+    Ok(Command::Query(QueryOptions {
+        attribute: attribute.ok_or_else(|| ArgumentParsingError {
+            ohnoo: "attribute name is required".to_owned(),
+            help: QUERY_HELP,
+        })?,
+        class,
+        range,
+        input_type: InputType::open(input_path.as_deref(), QUERY_HELP)?,
+        input_path,
+        verbocity,
+    }))
 }
 
 fn parse_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<ArgumentOptions, ArgumentParsingError> {
-    let mut convertion_type = ConvertionType::Obj;
-    let mut input_type: Option<InputType> = None;
-    let mut output_type: Option<OutputType> = None;
+    let mut convertion_type: Option<FileFormat> = None;
+    let mut input_file_path: Option<String> = None;
+    let mut output_file_path: Option<String> = None;
     let mut expression: Option<String> = None;
+    let mut group: Option<String> = None;
+    let mut storage: Option<AttributeStorage> = None;
+    let mut endianness = Endianness::Little;
     let mut flags = ExpectedFlag::NotExpecting;
     let mut stashed_path: Option<String> = None;
     let mut verbocity = VerbocityLevel::Silent;
@@ -179,7 +875,7 @@ fn parse_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<ArgumentOpt
 
     for arg in argv {
         match (arg.as_str(), &flags) {
-            ("-h", ExpectedFlag::NotExpecting) => {
+            ("-h" | "--help", ExpectedFlag::NotExpecting) => {
                 just_print_help = true;
                 break; // we don't care about other args now
             }
@@ -192,17 +888,23 @@ fn parse_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<ArgumentOpt
             ("-e", ExpectedFlag::NotExpecting) => {
                 flags = ExpectedFlag::ExpectingExpression;
             }
+            ("-g", ExpectedFlag::NotExpecting) => {
+                flags = ExpectedFlag::ExpectingGroup;
+            }
+            ("--storage", ExpectedFlag::NotExpecting) => {
+                flags = ExpectedFlag::ExpectingStorage;
+            }
+            ("--endian", ExpectedFlag::NotExpecting) => {
+                flags = ExpectedFlag::ExpectingEndianness;
+            }
             (t, ExpectedFlag::ExpectingType) => {
                 flags = ExpectedFlag::NotExpecting;
-                convertion_type = match t {
-                    "obj" => ConvertionType::Obj,
-                    "stl" => ConvertionType::Stl,
-                    "geo" | "json" => ConvertionType::Geo,
-                    "bgeo" => ConvertionType::Bgeo,
-                    s => {
-                        println!("wtf is type {}?", s);
+                convertion_type = match FileFormat::from_type_name(t) {
+                    Some(format) => Some(format),
+                    None => {
                         return Err(ArgumentParsingError {
-                            ohnoo: format!("unknown type '{}'", s),
+                            ohnoo: format!("unknown type '{}'", t),
+                            help: CONVERT_HELP,
                         });
                     }
                 }
@@ -211,56 +913,117 @@ fn parse_arguments(argv: &mut dyn Iterator<Item = String>) -> Result<ArgumentOpt
                 flags = ExpectedFlag::NotExpecting;
                 expression = Some(exp.to_owned());
             }
+            (name, ExpectedFlag::ExpectingGroup) => {
+                flags = ExpectedFlag::NotExpecting;
+                group = Some(name.to_owned());
+            }
+            (name, ExpectedFlag::ExpectingStorage) => {
+                flags = ExpectedFlag::NotExpecting;
+                // expression targets are float attributes only
+                storage = match AttributeStorage::from_name(name) {
+                    Some(x) if x.is_float() => Some(x),
+                    _ => {
+                        return Err(ArgumentParsingError {
+                            ohnoo: format!("unknown storage '{}', expected fpreal16, fpreal32 or fpreal64", name),
+                            help: CONVERT_HELP,
+                        });
+                    }
+                };
+            }
+            (order, ExpectedFlag::ExpectingEndianness) => {
+                flags = ExpectedFlag::NotExpecting;
+                endianness = Endianness::from_name(order).ok_or_else(|| ArgumentParsingError {
+                    ohnoo: format!("unknown endianness '{}', expected little or big", order),
+                    help: CONVERT_HELP,
+                })?;
+            }
             (file_path, ExpectedFlag::NotExpecting) => {
                 match &stashed_path {
                     None => {
                         stashed_path = Some(arg);
                     }
                     Some(input_path) => {  // else it's the second positional argument, so we are ready to assign
-                        input_type = Some(InputType::File(io::BufReader::new(
-                            File::open(input_path).expect("failed to open input file"),
-                        )));
-                        output_type = Some(OutputType::File(io::BufWriter::new(
-                            File::create(file_path).expect("could not create output file"),
-                        )));
+                        input_file_path = Some(input_path.to_owned());
+                        output_file_path = Some(file_path.to_owned());
                     }
                 }
             }
         }
     }
-    if let None = output_type {
-        if let Some(file_path) = stashed_path {
-            output_type = Some(OutputType::File(io::BufWriter::new(
-                File::create(file_path).expect("could not create output file")
-            )));
-        } else {
-            output_type = Some(OutputType::Stdout(io::stdout().lock()));
-        }
+    if output_file_path.is_none() {
+        output_file_path = stashed_path;
     }
-    if let None = input_type {
-        input_type = Some(InputType::Stdin(io::stdin().lock()));
+
+    // nothing is opened or created if just help is asked for
+    let (input_type, output_type) = if just_print_help {
+        (InputType::Stdin(io::stdin().lock()), OutputType::Stdout(io::stdout().lock()))
+    } else {
+        (
+            InputType::open(input_file_path.as_deref(), CONVERT_HELP)?,
+            OutputType::create(output_file_path.as_deref(), CONVERT_HELP)?,
+        )
     };
 
+    // explicit type wins over output extension, what to do if we have neither is up to the command
+    let convertion_type = convertion_type.or_else(|| output_file_path.as_deref().and_then(FileFormat::from_path));
+
     Ok(ArgumentOptions {
         convertion_type,
-        input_type: input_type.expect("impossible!"),
-        output_type: output_type.expect("impossible!"),
+        input_type,
+        input_path: input_file_path,
+        output_type,
         expression,
+        group,
+        storage,
+        endianness,
         verbocity,
         just_print_help
     })
 }
 
-fn convert_to_stl(res: &ReaderElement, out: &mut dyn io::Write) {
-    let stlsolid = create_stl_solid(&mut HoudiniGeoSchemaParser::new(res));
+/// read geometry for obj or stl export, along with the primitive group to export if one is given
+fn geometry_for_export(res: &ReaderElement, group: Option<&str>) -> Result<(Geometry, Option<ElementGroup>), SchemaError> {
+    let geometry = Geometry::from_structure(res)?;
+    let group = match group {
+        Some(name) => match geometry.group(AttributeClass::Primitive, name) {
+            Some(x) => Some(x.clone()),
+            None => {
+                let message = format!("no primitive group '{}' found", name);
+                return Err(SchemaError { message, path: ReaderElementPointer::new() });
+            }
+        },
+        None => None,
+    };
+    Ok((geometry, group))
+}
+
+fn convert_to_stl(res: &ReaderElement, group: Option<&str>, out: &mut dyn io::Write) -> Result<(), SchemaError> {
+    let (geometry, group) = geometry_for_export(res, group)?;
+    let stlsolid = create_stl_solid(&geometry, group.as_ref());
 
     serialize_stl(&stlsolid, out);
+    Ok(())
 }
 
-fn convert_to_obj(res: &ReaderElement, out: &mut dyn io::Write) {
-    let mut schema_parser = HoudiniGeoSchemaParser::new(res);
+fn convert_to_obj(res: &ReaderElement, group: Option<&str>, out: &mut dyn io::Write) -> Result<(), SchemaError> {
+    let (geometry, group) = geometry_for_export(res, group)?;
+    serialize_obj(&geometry, group.as_ref(), out);
+    Ok(())
+}
 
-    serialize_obj(&mut schema_parser, out);
+fn exit_with_schema_error(err: SchemaError) -> ! {
+    eprintln!("Geometry schema error:");
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+/// output closed early, like when piped into `head`, is not an error, nobody is reading anymore
+fn exit_with_write_error(err: io::Error) -> ! {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        std::process::exit(0);
+    }
+    eprintln!("failed to write output: {}", err);
+    std::process::exit(1);
 }
 
 ///
@@ -298,10 +1061,14 @@ mod tests {
         // check1
         match parse_arguments(&mut vec![].into_iter()) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Obj,
+                convertion_type: None,
                 input_type: InputType::Stdin(_),
+                input_path: None,
                 output_type: OutputType::Stdout(_),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -318,10 +1085,14 @@ mod tests {
         // check2
         match parse_arguments(&mut vec![foo_in.path.to_owned(), foo_out.path.to_owned()].into_iter()) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Obj,
+                convertion_type: None,
                 input_type: InputType::File(mut fi),
+                input_path: Some(_),
                 output_type: OutputType::File(mut fo),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -341,10 +1112,14 @@ mod tests {
         // check3
         match parse_arguments(&mut vec![foo_out.path.to_owned()].into_iter()) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Obj,
+                convertion_type: None,
                 input_type: InputType::Stdin(_),
+                input_path: None,
                 output_type: OutputType::File(_),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -358,10 +1133,14 @@ mod tests {
         // check4
         match parse_arguments(&mut vec!["-t".to_owned(), "bgeo".to_owned(), foo_out.path.to_owned()].into_iter()) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Bgeo,
+                convertion_type: Some(FileFormat::Bgeo),
                 input_type: InputType::Stdin(_),
+                input_path: None,
                 output_type: OutputType::File(_),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -375,10 +1154,14 @@ mod tests {
         // check5
         match parse_arguments(&mut vec![foo_out.path.to_owned(), "-t".to_owned(), "geo".to_owned()].into_iter()) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Geo,
+                convertion_type: Some(FileFormat::Geo),
                 input_type: InputType::Stdin(_),
+                input_path: None,
                 output_type: OutputType::File(_),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -400,10 +1183,14 @@ mod tests {
             .into_iter(),
         ) {
             Ok(ArgumentOptions {
-                convertion_type: ConvertionType::Geo,
+                convertion_type: Some(FileFormat::Geo),
                 input_type: InputType::File(_),
+                input_path: Some(_),
                 output_type: OutputType::File(_),
                 expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
                 verbocity: VerbocityLevel::Silent,
                 just_print_help: false,
             }) => {
@@ -413,5 +1200,260 @@ mod tests {
                 assert!(false, "argument parsing failed");
             }
         }
+
+        // check7
+        match parse_arguments(&mut vec!["-t".to_owned(), "bgeo.sc".to_owned(), foo_out.path.to_owned()].into_iter()) {
+            Ok(ArgumentOptions {
+                convertion_type: Some(FileFormat::BgeoSc),
+                input_type: InputType::Stdin(_),
+                input_path: None,
+                output_type: OutputType::File(_),
+                expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
+                verbocity: VerbocityLevel::Silent,
+                just_print_help: false,
+            }) => {
+                println!("check7 succ!");
+            }
+            _ => {
+                assert!(false, "argument parsing failed");
+            }
+        }
+
+        // check8
+        match parse_arguments(&mut vec!["-t".to_owned(), "bgeo.gz".to_owned(), foo_out.path.to_owned()].into_iter()) {
+            Ok(ArgumentOptions {
+                convertion_type: Some(FileFormat::BgeoGz),
+                input_type: InputType::Stdin(_),
+                input_path: None,
+                output_type: OutputType::File(_),
+                expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
+                verbocity: VerbocityLevel::Silent,
+                just_print_help: false,
+            }) => {
+                println!("check8 succ!");
+            }
+            _ => {
+                assert!(false, "argument parsing failed");
+            }
+        }
+
+        let foo_in_gz = TempFile::new("temp_foo_in.geo.gz");
+        let foo_out_sc = TempFile::new("temp_foo_out.bgeo.sc");
+
+        // check9
+        match parse_arguments(&mut vec![foo_in_gz.path.to_owned(), foo_out_sc.path.to_owned()].into_iter()) {
+            Ok(ArgumentOptions {
+                convertion_type: Some(FileFormat::BgeoSc),
+                input_type: InputType::File(_),
+                input_path: Some(path),
+                output_type: OutputType::File(_),
+                expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
+                verbocity: VerbocityLevel::Silent,
+                just_print_help: false,
+            }) if path == foo_in_gz.path => {
+                println!("check9 succ!");
+            }
+            _ => {
+                assert!(false, "argument parsing failed");
+            }
+        }
+
+        // check10
+        match parse_arguments(&mut vec!["-t".to_owned(), "stl".to_owned(), foo_out_sc.path.to_owned()].into_iter()) {
+            Ok(ArgumentOptions {
+                convertion_type: Some(FileFormat::Stl),
+                input_type: InputType::Stdin(_),
+                input_path: None,
+                output_type: OutputType::File(_),
+                expression: None,
+                group: None,
+                storage: None,
+                endianness: Endianness::Little,
+                verbocity: VerbocityLevel::Silent,
+                just_print_help: false,
+            }) => {
+                println!("check10 succ!");
+            }
+            _ => {
+                assert!(false, "argument parsing failed");
+            }
+        }
+
+        // check11
+        match parse_arguments(&mut vec!["-g".to_owned(), "top".to_owned(), "-e".to_owned(), "@P=@P*2".to_owned()].into_iter()) {
+            Ok(ArgumentOptions {
+                expression: Some(_),
+                group: Some(group),
+                ..
+            }) if group == "top" => {
+                println!("check11 succ!");
+            }
+            _ => panic!("argument parsing failed"),
+        }
+    }
+
+    fn args(args: &[&str]) -> std::vec::IntoIter<String> {
+        args.iter().map(|x| x.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn command_parser() {
+        let foo_in = TempFile::new("temp_command_foo_in");
+
+        // old style invocation is still a conversion
+        assert!(matches!(parse_command(&mut args(&["-t", "geo"])), Ok(Command::Convert(_))));
+        assert!(matches!(parse_command(&mut args(&["-h"])), Ok(Command::Help(None))));
+        assert!(matches!(parse_command(&mut args(&["help", "diff"])), Ok(Command::Help(Some(DIFF_HELP)))));
+        assert!(matches!(parse_command(&mut args(&["query", "-h"])), Ok(Command::Help(Some(QUERY_HELP)))));
+        // --help is never taken for a file name
+        assert!(matches!(parse_command(&mut args(&["--help"])), Ok(Command::Help(None))));
+        assert!(matches!(parse_command(&mut args(&["info", "--help"])), Ok(Command::Help(Some(INFO_HELP)))));
+        assert!(matches!(parse_command(&mut args(&["diff", "--help"])), Ok(Command::Help(Some(DIFF_HELP)))));
+        assert!(matches!(parse_command(&mut args(&["query", "--help"])), Ok(Command::Help(Some(QUERY_HELP)))));
+        assert!(matches!(
+            parse_command(&mut args(&["convert", "--help"])),
+            Ok(Command::Convert(ArgumentOptions { just_print_help: true, output_type: OutputType::Stdout(_), .. }))
+        ));
+        assert!(!std::path::Path::new("--help").exists());
+        assert!(matches!(parse_command(&mut args(&["help", "nope"])), Err(_)));
+
+        match parse_command(&mut args(&["convert", "-t", "stl"])) {
+            Ok(Command::Convert(ArgumentOptions {
+                convertion_type: Some(FileFormat::Stl),
+                input_type: InputType::Stdin(_),
+                output_type: OutputType::Stdout(_),
+                ..
+            })) => (),
+            _ => panic!("convert parsing failed"),
+        }
+        match parse_command(&mut args(&["convert", "--endian", "big", "-t", "bgeo"])) {
+            Ok(Command::Convert(ArgumentOptions {
+                convertion_type: Some(FileFormat::Bgeo),
+                endianness: Endianness::Big,
+                ..
+            })) => (),
+            _ => panic!("convert parsing with endianness failed"),
+        }
+        assert!(matches!(parse_command(&mut args(&["--endian", "middle"])), Err(_)));
+
+        match parse_command(&mut args(&["exec", "-e", "@P=@P*2"])) {
+            Ok(Command::Exec(ArgumentOptions {
+                convertion_type: None,
+                expression: Some(_),
+                group: None,
+                endianness: Endianness::Little,
+                ..
+            })) => (),
+            _ => panic!("exec parsing failed"),
+        }
+        assert!(matches!(parse_command(&mut args(&["exec", "-t", "geo"])), Err(ArgumentParsingError { help: EXEC_HELP, .. })));
+        match parse_command(&mut args(&["exec", "--storage", "fpreal16", "-e", "@P=@P*2"])) {
+            Ok(Command::Exec(ArgumentOptions {
+                storage: Some(AttributeStorage::Fpreal16),
+                ..
+            })) => (),
+            _ => panic!("exec parsing with storage failed"),
+        }
+        assert!(parse_command(&mut args(&["exec", "--storage", "int8", "-e", "@P=@P*2"])).is_err());
+
+        // missing input or unwritable output are errors, not panics, and no output is created for missing input
+        let missing_out = "temp_command_missing_out.obj";
+        assert!(parse_command(&mut args(&["convert", "temp_command_no_such_input.geo", missing_out])).is_err());
+        assert!(!std::path::Path::new(missing_out).exists());
+        assert!(parse_command(&mut args(&["convert", foo_in.path, "no_such_dir/out.obj"])).is_err());
+
+        match parse_command(&mut args(&["validate", "-s", foo_in.path])) {
+            Ok(Command::Validate(InspectOptions {
+                input_type: InputType::File(_),
+                input_path: Some(_),
+                strict: true,
+                ..
+            })) => (),
+            _ => panic!("validate parsing failed"),
+        }
+        assert!(matches!(parse_command(&mut args(&["info", "-s"])), Err(_)));
+        assert!(matches!(parse_command(&mut args(&["info", foo_in.path, foo_in.path])), Err(_)));
+
+        match parse_command(&mut args(&["diff", "--tolerance", "0.01", "-", foo_in.path])) {
+            Ok(Command::Diff(DiffOptions {
+                first: (InputType::Stdin(_), None),
+                second: (InputType::File(_), Some(_)),
+                tolerance,
+                ..
+            })) if tolerance == 0.01 => (),
+            _ => panic!("diff parsing failed"),
+        }
+        assert!(matches!(parse_command(&mut args(&["diff", foo_in.path])), Err(_)));
+        assert!(matches!(parse_command(&mut args(&["diff", "-", "-"])), Err(_)));
+
+        match parse_command(&mut args(&["query", "-c", "prim", "-r", "2:", "Cd"])) {
+            Ok(Command::Query(QueryOptions {
+                class: AttributeClass::Primitive,
+                range: Some(range),
+                input_type: InputType::Stdin(_),
+                ..
+            })) if range == (2..usize::MAX) => (),
+            _ => panic!("query parsing failed"),
+        }
+        assert!(matches!(parse_command(&mut args(&["query"])), Err(_)));
+        assert!(matches!(
+            parse_command(&mut args(&["query", "-c", "detail", "shot"])),
+            Ok(Command::Query(QueryOptions { class: AttributeClass::Detail, .. }))
+        ));
+        assert!(parse_command(&mut args(&["query", "-c", "object", "P"])).is_err());
+
+        assert_eq!(Some(5..6), parse_range("5"));
+        assert_eq!(Some(0..10), parse_range(":10"));
+        assert_eq!(None, parse_range("a:b"));
+    }
+
+    #[test]
+    fn diff_after_ascii_roundtrip() {
+        // boxattr has 32 bit float attributes, their ascii form is shortest text of the 32 bit value
+        let res = geoconverter::parse(&mut File::open("tests/boxattr.bgeo").expect("failed to open test file"));
+        let mut geo = Vec::new();
+        geoconverter::geo_struct_serializer::to_json(&res, &mut geo);
+        let reparsed = geoconverter::parse(&mut &geo[..]);
+
+        let (first, second) = (parse_schema(&res).unwrap(), parse_schema(&reparsed).unwrap());
+        let mut out = Vec::new();
+        assert_eq!(0, diff_geometry(&first, &second, 0.0, &mut out).unwrap(), "{}", String::from_utf8_lossy(&out));
+    }
+
+    /// writer whose reader has gone away
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_output_is_an_error() {
+        let text = std::fs::read_to_string("tests/box.geo").expect("failed to read test file");
+        let res = geoconverter::parse(&mut text.as_bytes());
+        let other = geoconverter::parse(&mut text.replacen("[0.5,-0.5,0.5]", "[0.5,-0.5,1.5]", 1).as_bytes());
+        let header = GeoHeader::new(&res).unwrap();
+        let err = write_info(&header, Some(FileFormat::Geo), &mut ClosedPipe).unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
+
+        let (first, second) = (parse_schema(&res).unwrap(), parse_schema(&other).unwrap());
+        assert_eq!(0, diff_geometry(&first, &first, 0.0, &mut ClosedPipe).unwrap());
+        let err = diff_geometry(&first, &second, 0.0, &mut ClosedPipe).unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
     }
 }
@@ -1,30 +1,25 @@
 use crate::{
-    houdini_geo_schema::{GeoAttribute, GeoAttributeKind, HoudiniGeoSchemaParser},
-    ReaderElement,
+    diagnostics,
+    geometry::Geometry,
+    houdini_geo_schema::{AttributeClass, ElementGroup, GeoAttribute, GeoAttributeKind},
 };
 use std::io::Write;
 
-pub fn serialize_obj<F: ?Sized>(geo_schema: &mut HoudiniGeoSchemaParser, file: &mut F)
-where
-    F: Write,
-{
-    geo_schema.parse_primitives();
-    geo_schema.parse_point_attributes();
-    geo_schema.parse_vertex_attributes();
-
-    let p_attr = if let Some(GeoAttributeKind::Float64(x)) = geo_schema.point_attribute("P") {
+/// write geometry as obj, if primitive group is given - only its polygons are written, but all the points
+pub fn serialize_obj<F: ?Sized + Write>(geometry: &Geometry, group: Option<&ElementGroup>, file: &mut F) {
+    let p_attr = if let Some(GeoAttributeKind::Float64(x)) = geometry.attribute(AttributeClass::Point, "P") {
         x
     } else {
         panic!("no p float3 attr");
     };
 
     //writing P (Cd)
-    match geo_schema.point_attribute("Cd") {
+    match geometry.attribute(AttributeClass::Point, "Cd") {
         Some(GeoAttributeKind::Float64(cd_attr)) => {
-            for ptnum in 0..geo_schema.point_count() {
+            for ptnum in 0..geometry.point_count() {
                 let p = p_attr.value(ptnum);
                 let cd: &[f64] = cd_attr.value(ptnum);
-                file.write(
+                file.write_all(
                     format!(
                         "v {} {} {} {} {} {}\n",
                         p[0], p[1], p[2], cd[0], cd[1], cd[2]
@@ -35,9 +30,9 @@ where
             }
         }
         None => {
-            for ptnum in 0..geo_schema.point_count() {
+            for ptnum in 0..geometry.point_count() {
                 let p = p_attr.value(ptnum);
-                file.write(format!("v {} {} {}\n", p[0], p[1], p[2]).as_bytes())
+                file.write_all(format!("v {} {} {}\n", p[0], p[1], p[2]).as_bytes())
                     .expect("io error");
             }
         }
@@ -45,26 +40,26 @@ where
     };
 
     // writing uv
-    let have_uvs = if let Some(GeoAttributeKind::Float64(uv_attr)) = geo_schema.vertex_attribute("uv") {
+    let have_uvs = if let Some(GeoAttributeKind::Float64(uv_attr)) = geometry.attribute(AttributeClass::Vertex, "uv") {
         match uv_attr.tuple_size(){
             3 => {
-                for vtxnum in 0..geo_schema.vertex_count() {
+                for vtxnum in 0..geometry.vertex_count() {
                     let uv = uv_attr.value(vtxnum);
-                    file.write(format!("vt {} {} {}\n", uv[0], uv[1], uv[2]).as_bytes())
+                    file.write_all(format!("vt {} {} {}\n", uv[0], uv[1], uv[2]).as_bytes())
                         .expect("io error");
                 }
                 true
             }
             2 => {
-                for vtxnum in 0..geo_schema.vertex_count() {
+                for vtxnum in 0..geometry.vertex_count() {
                     let uv = uv_attr.value(vtxnum);
-                    file.write(format!("vt {} {} 0\n", uv[0], uv[1]).as_bytes())
+                    file.write_all(format!("vt {} {} 0\n", uv[0], uv[1]).as_bytes())
                         .expect("io error");
                 }
                 true
             }
             x => {
-                println!("float uv attribute found, but it's of unexpected size {}, skipping", x);
+                diagnostics::warning(&format!("float uv attribute found, but it's of unexpected size {}, skipping", x));
                 false
             }
         }
@@ -72,30 +67,30 @@ where
 
     // writing faces
     if have_uvs {
-        for prim in geo_schema.polygons() {
-            file.write(b"f").expect("io error");
+        for (_, vertices) in geometry.polygons().filter(|(primnum, _)| group.is_none_or(|g| g.contains(*primnum))) {
+            file.write_all(b"f").expect("io error");
 
             // obj expects opposite winding order starting at same vertex, and vertex indices start at 1, no 0
-            let vtxcount = prim.vertices.len();
+            let vtxcount = vertices.len();
             for i in 0..vtxcount {
-                let vtx = &prim.vertices[(vtxcount-i)%vtxcount];
-                file.write(format!(" {}/{}", vtx.ptnum + 1, vtx.vtxnum + 1).as_bytes())
+                let vtxnum = vertices.start + (vtxcount-i)%vtxcount;
+                file.write_all(format!(" {}/{}", geometry.vtx_to_ptnum(vtxnum) + 1, vtxnum + 1).as_bytes())
                     .expect("io error");
             }
-            file.write(b"\n").expect("io error");
+            file.write_all(b"\n").expect("io error");
         }
     } else {
-        for prim in geo_schema.polygons() {
-            file.write(b"f").expect("io error");
+        for (_, vertices) in geometry.polygons().filter(|(primnum, _)| group.is_none_or(|g| g.contains(*primnum))) {
+            file.write_all(b"f").expect("io error");
 
             // obj expects opposite winding order starting at same vertex, and vertex indices start at 1, no 0
-            let vtxcount = prim.vertices.len();
+            let vtxcount = vertices.len();
             for i in 0..vtxcount {
-                let vtx = &prim.vertices[(vtxcount-i)%vtxcount];
-                file.write(format!(" {}", vtx.ptnum + 1).as_bytes())
+                let vtxnum = vertices.start + (vtxcount-i)%vtxcount;
+                file.write_all(format!(" {}", geometry.vtx_to_ptnum(vtxnum) + 1).as_bytes())
                     .expect("io error");
             }
-            file.write(b"\n").expect("io error");
+            file.write_all(b"\n").expect("io error");
         }
     }
 }
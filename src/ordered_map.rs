@@ -0,0 +1,165 @@
+// map that remembers the order keys were inserted in,
+// so that geo structures are written back exactly in the order they were read
+//
+// maps in geo files are small (info blocks, attribute options and such),
+// so lookups are a simple linear scan, which is cheaper than hashing at these sizes
+
+use std::borrow::Borrow;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K, V> OrderedMap<K, V> {
+    pub fn new() -> OrderedMap<K, V> {
+        OrderedMap { entries: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> OrderedMap<K, V> {
+        OrderedMap {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// iterate over key-value pairs in insertion order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&K, &mut V)> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl<K: Eq, V> OrderedMap<K, V> {
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.entries.iter().position(|(k, _)| k.borrow() == key)
+    }
+
+    /// insert a value, if key is already present - value is replaced, but key keeps its original place
+    ///
+    /// old value is returned in that case
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|i| &mut self.entries[i].1)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).is_some()
+    }
+
+    /// remove a key, order of the rest of the keys is preserved
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|i| self.entries.remove(i).1)
+    }
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Eq, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = OrderedMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut map: OrderedMap<String, i32> = ["zeta", "alpha", "mid"].iter().map(|k| (k.to_string(), 0)).collect();
+        assert_eq!(None, map.insert("beta".to_owned(), 1));
+        assert_eq!(Some(0), map.insert("alpha".to_owned(), 2));
+        assert_eq!(vec!["zeta", "alpha", "mid", "beta"], map.keys().collect::<Vec<_>>());
+        assert_eq!(Some(&2), map.get("alpha"));
+
+        assert_eq!(Some(2), map.remove("alpha"));
+        assert!(!map.contains_key("alpha"));
+        *map.get_mut("mid").expect("just inserted") = 3;
+        assert_eq!(
+            vec![("zeta".to_owned(), 0), ("mid".to_owned(), 3), ("beta".to_owned(), 1)],
+            map.into_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// immutable reference counted string for text in geo structures
+//
+// geo files repeat the same few strings over and over: kv array keys like "name", "size" or "storage",
+// attribute types, token references in bgeo. parsers intern those, so every repeated string
+// is a pointer to the same allocation instead of a fresh String
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SharedStr(Arc<str>);
+
+impl SharedStr {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// true if both point to the same allocation, which is the case for interned equal strings
+    pub fn ptr_eq(&self, other: &SharedStr) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+// must hash same as str, so that interner can be looked up by &str
+impl Hash for SharedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for SharedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(x: &str) -> Self {
+        SharedStr(Arc::from(x))
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(x: String) -> Self {
+        SharedStr(Arc::from(x))
+    }
+}
+
+impl From<SharedStr> for String {
+    fn from(x: SharedStr) -> Self {
+        x.as_str().to_owned()
+    }
+}
+
+impl PartialEq<str> for SharedStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SharedStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for SharedStr {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<SharedStr> for str {
+    fn eq(&self, other: &SharedStr) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<SharedStr> for &str {
+    fn eq(&self, other: &SharedStr) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// set of strings seen so far, each distinct string is allocated once
+#[derive(Default)]
+pub(crate) struct StringInterner {
+    strings: HashSet<SharedStr>,
+}
+
+impl StringInterner {
+    pub(crate) fn new() -> StringInterner {
+        StringInterner::default()
+    }
+
+    pub(crate) fn intern(&mut self, text: &str) -> SharedStr {
+        if let Some(x) = self.strings.get(text) {
+            return x.clone();
+        }
+        let x = SharedStr::from(text);
+        self.strings.insert(x.clone());
+        x
+    }
+}
+
+///
+/// --------------------------------------------------------------
+///                            TESTS
+/// --------------------------------------------------------------
+///
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_strings_share_allocation() {
+        let mut interner = StringInterner::new();
+        let a = interner.intern("name");
+        let owned = String::from("name");
+        let b = interner.intern(&owned);
+        let c = interner.intern("size");
+        assert!(a.ptr_eq(&b));
+        assert!(!a.ptr_eq(&c));
+        assert_eq!(a, "name");
+        assert_eq!("size", c);
+        assert!(!a.ptr_eq(&SharedStr::from("name")));
+    }
+}
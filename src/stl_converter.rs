@@ -1,5 +1,7 @@
 use std::io::prelude::*;
-use crate::houdini_geo_schema::{GeoAttribute, GeoAttributeKind, HoudiniGeoSchemaParser};
+use crate::diagnostics;
+use crate::geometry::Geometry;
+use crate::houdini_geo_schema::{AttributeClass, ElementGroup, GeoAttribute, GeoAttributeKind};
 
 #[derive(Debug)]
 pub struct StlSolid<T> {
@@ -12,56 +14,51 @@ pub struct StlFacet<T> {
     vertices: [[T; 3]; 3],
 }
 
-pub fn create_stl_solid(geo_schema: &mut HoudiniGeoSchemaParser) -> StlSolid<f64> {
-    let mut stl_faces = Vec::with_capacity(geo_schema.primitive_count()*2);  // this is ROUGH estimation (assume all prims are polys, all 4-gons)
+/// make stl solid out of geometry polygons, if primitive group is given - only out of polygons in it
+pub fn create_stl_solid(geometry: &Geometry, group: Option<&ElementGroup>) -> StlSolid<f64> {
+    let mut stl_faces = Vec::with_capacity(geometry.primitive_count()*2);  // this is ROUGH estimation (assume all prims are polys, all 4-gons)
 
-    geo_schema.parse_point_attributes();
-    geo_schema.parse_primitive_attributes();
-    geo_schema.parse_primitives();
-
-    let p_attr = if let Some(GeoAttributeKind::Float64(x)) = geo_schema.point_attribute("P") {
+    let p_attr = if let Some(GeoAttributeKind::Float64(x)) = geometry.attribute(AttributeClass::Point, "P") {
         x
     } else {
         panic!("unexpected P attrib type");
     };
-    let n_attr = if let Some(GeoAttributeKind::Float64(x)) = geo_schema.primitive_attribute("N") {
+    let n_attr = if let Some(GeoAttributeKind::Float64(x)) = geometry.attribute(AttributeClass::Primitive, "N") {
         Some(x)
     } else {
-        println!("no N attrib that is float3");
+        diagnostics::info("no N attrib that is float3");
         None
     };
 
-    for poly in geo_schema.polygons() {
-        //println!("poly {:?}", poly);
-        let mut vertex_iter = poly.vertices.iter();
+    for (primnum, vertices) in geometry.polygons().filter(|(primnum, _)| group.is_none_or(|g| g.contains(*primnum))) {
+        let mut vertex_iter = vertices.map(|vtxnum| geometry.vtx_to_ptnum(vtxnum));
 
         let first_vtx = vertex_iter.next().expect("no vertices?? bad polygon, BAD!");
         let second_vtx = vertex_iter
             .next()
             .expect("single vertex?? bad polygon, BAD!");
         let first_p: [f64; 3] = p_attr
-            .value(first_vtx.ptnum)
+            .value(first_vtx)
             .try_into()
             .expect("bad P, not a float3");
         let mut prev_p = p_attr
-            .value(second_vtx.ptnum)
+            .value(second_vtx)
             .try_into()
             .expect("bad P, not a float3");
 
         let n: [f64; 3] = if let Some(n_attr) = n_attr {
             n_attr
-                .value(poly.number)
+                .value(primnum)
                 .try_into()
                 .expect("bad N, not a float3")
         } else {
             [0.0, 0.0, 0.0]
         };
 
-        for vtx in vertex_iter {
-            //println!("{:?}", p_attr.value(vtx.ptnum));
+        for ptnum in vertex_iter {
 
             let p = p_attr
-                .value(vtx.ptnum)
+                .value(ptnum)
                 .try_into()
                 .expect("bad P, not a float3");
             stl_faces.push(StlFacet {
@@ -1,7 +1,11 @@
-use geoconverter::{ReaderElement, UniformArrayType};
+use geoconverter::{
+    AttributeClass, AttributeStorage, GeoAttribute, GeoAttributeKind, Geometry, GeometryAttribute, HoudiniGeoSchemaParser,
+    ParseOptions, ReaderElement, TokenGeoAttribute, TupleGeoAttribute, UniformArrayType,
+};
 
 use std::fs::File;
 use std::io::{BufReader, Read};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn parse_bgeo_box() {
@@ -30,7 +34,11 @@ fn parse_box_helper(filepath: &str, parser: & dyn Fn(&mut dyn Read) -> ReaderEle
 
     // geoconverter::geo_struct_serializer::preview(&elem);
 
-    if let ReaderElement::Array(root_arr) = &elem {
+    check_box_element(&elem);
+}
+
+fn check_box_element(elem: &ReaderElement) {
+    if let ReaderElement::Array(root_arr) = elem {
         if let ReaderElement::Text(x) = &root_arr[0] {
             assert_eq!(x, "fileversion");
         } else {
@@ -58,6 +66,13 @@ fn parse_box_helper(filepath: &str, parser: & dyn Fn(&mut dyn Read) -> ReaderEle
                                 assert_eq!(ind_arr[i], *idx as i16);
                         }
                     }
+                    // ascii parser packs small ints into the narrowest type
+                    ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(ind_arr)) => {
+                        assert_eq!(ind_arr.len(), expected_indices.len());
+                        for (i, idx) in expected_indices.iter().enumerate() {
+                            assert_eq!(ind_arr[i], *idx as i8);
+                        }
+                    }
                     _ => {
                         assert!(false, "index array is of unexpected type: {:?}", &pref_arr[1]);
                     }
@@ -73,3 +88,968 @@ fn parse_box_helper(filepath: &str, parser: & dyn Fn(&mut dyn Read) -> ReaderEle
     }
     // TBD
 }
+
+#[test]
+fn parse_bgeo_sc_box_autodetect() {
+    // wrap box.bgeo into two uncompressed (memcpyed) blosc chunks
+    let data = std::fs::read("./tests/box.bgeo").expect("failed to read test file");
+    let mut stream = Vec::new();
+    for part in data.chunks(data.len() / 2 + 1) {
+        stream.extend_from_slice(&[2, 1, 0x02, 1]);
+        stream.extend_from_slice(&(part.len() as u32).to_le_bytes());
+        stream.extend_from_slice(&(part.len() as u32).to_le_bytes());
+        stream.extend_from_slice(&(part.len() as u32 + 16).to_le_bytes());
+        stream.extend_from_slice(part);
+    }
+    check_box_element(&geoconverter::parse(&mut &stream[..]));
+}
+
+#[test]
+fn parse_gz_box_autodetect() {
+    for path in ["./tests/box.bgeo", "./tests/box.geo"] {
+        let data = std::fs::read(path).expect("failed to read test file");
+        let mut stream = Vec::new();
+        let mut writer = geoconverter::compression::GzipWriter::new(&mut stream);
+        std::io::Write::write_all(&mut writer, &data).expect("failed to compress");
+        writer.finish().expect("failed to compress");
+        check_box_element(&geoconverter::parse(&mut &stream[..]));
+    }
+}
+
+#[test]
+fn parse_truncated_bgeo_error() {
+    let data = std::fs::read("./tests/box.bgeo").expect("failed to read test file");
+    for cut in [0, 1, 3, 5, 6, data.len() / 2, data.len() - 1] {
+        let err = geoconverter::try_parse(&mut &data[..cut]).expect_err("truncated file must not parse");
+        assert!(err.offset <= cut, "offset {} is past the end {}", err.offset, cut);
+        assert!(err.line_column.is_none());
+    }
+
+    let err = geoconverter::try_parse_binary(&mut &data[..data.len() / 2]).expect_err("truncated file must not parse");
+    assert_eq!(data.len() / 2, err.offset);
+    assert!(!err.path.is_empty());
+    assert!(!err.expected.is_empty());
+}
+
+#[test]
+fn parse_malformed_geo_error() {
+    let text = "[\n  \"a\", 1,\n  \"b\", [1, 2 x]\n]";
+    let err = geoconverter::try_parse(&mut text.as_bytes()).expect_err("malformed file must not parse");
+    assert_eq!(Some((3, 14)), err.line_column);
+    assert_eq!(25, err.offset);
+    assert_eq!(vec!["b".to_owned()], err.path);
+    assert_eq!("',' or ']'", err.expected);
+
+    let text = "[\"attributes\", {\"P\": [\"values\", [1, tru]]}]";
+    let err = geoconverter::try_parse_ascii(&mut text.as_bytes()).expect_err("malformed file must not parse");
+    assert_eq!("attributes/P/values/1", err.path_string());
+    assert_eq!(Some((1, 37)), err.line_column);
+
+    let err = geoconverter::try_parse(&mut "[\"unterminated".as_bytes()).expect_err("malformed file must not parse");
+    assert_eq!(14, err.offset);
+}
+
+#[test]
+fn map_key_order_roundtrip() {
+    let source = br#"["info",{"software":"Houdini","date":"today","artist":"me","bounds":[0,1],"attribute":{"z":1,"a":2}}]"#;
+    let elem = geoconverter::parse_ascii(&mut &source[..]);
+
+    let check_order = |elem: &ReaderElement| {
+        let info = match elem {
+            ReaderElement::Array(arr) => match &arr[1] {
+                ReaderElement::KeyValueObject(info) => info,
+                _ => panic!("info must be a map"),
+            },
+            _ => panic!("root must be an array"),
+        };
+        assert_eq!(vec!["software", "date", "artist", "bounds", "attribute"], info.keys().collect::<Vec<_>>());
+        match info.get("attribute") {
+            Some(ReaderElement::KeyValueObject(attr)) => assert_eq!(vec!["z", "a"], attr.keys().collect::<Vec<_>>()),
+            _ => panic!("attribute must be a map"),
+        }
+    };
+    check_order(&elem);
+
+    let mut json = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&elem, &mut json);
+    check_order(&geoconverter::parse(&mut &json[..]));
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    check_order(&geoconverter::parse(&mut &bjson[..]));
+
+    // and output is the same every time
+    let mut json_again = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&geoconverter::parse(&mut &json[..]), &mut json_again);
+    assert_eq!(json, json_again);
+}
+
+#[test]
+fn json_escapes_and_null_roundtrip() {
+    let source = concat!(
+        r#"["quote\" back\\ slash\/ \b\f\n\r\t", "café \ud83d\ude00 \u001f", "lone \ud800!", "#,
+        r#"null, {"key\"with\nnewline": null}]"#
+    );
+    let elem = geoconverter::parse_ascii(&mut source.as_bytes());
+
+    let check = |elem: &ReaderElement| {
+        let arr = match elem {
+            ReaderElement::Array(arr) => arr,
+            _ => panic!("root must be an array"),
+        };
+        assert!(matches!(&arr[0], ReaderElement::Text(x) if x == "quote\" back\\ slash/ \u{8}\u{c}\n\r\t"));
+        assert!(matches!(&arr[1], ReaderElement::Text(x) if x == "café 😀 \u{1f}"));
+        assert!(matches!(&arr[2], ReaderElement::Text(x) if x == "lone \u{fffd}!"));
+        assert!(matches!(&arr[3], ReaderElement::None));
+        match &arr[4] {
+            ReaderElement::KeyValueObject(map) => {
+                assert!(matches!(map.get("key\"with\nnewline"), Some(ReaderElement::None)))
+            }
+            _ => panic!("expected a map"),
+        }
+    };
+    check(&elem);
+
+    let mut json = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&elem, &mut json);
+    let json_text = String::from_utf8(json.clone()).expect("json must be utf8");
+    assert!(json_text.contains(r#""quote\" back\\ slash/ \b\f\n\r\t""#));
+    assert!(json_text.contains(r#""café 😀 \u001f""#));
+    assert!(json_text.contains("null"));
+    check(&geoconverter::parse(&mut &json[..]));
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    check(&geoconverter::parse(&mut &bjson[..]));
+
+    let err = geoconverter::try_parse_ascii(&mut &br#"["bad \q escape"]"#[..]).expect_err("bad escape must fail");
+    assert_eq!(7, err.offset);
+}
+
+#[test]
+fn bgeo_writer_tokenizes_repeated_strings() {
+    let elem = geoconverter::parse(&mut File::open("./tests/boxattr.bgeo").expect("failed to open test file"));
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+
+    // repeated keys are defined once and referenced after
+    let occurrences = |needle: &[u8]| bjson.windows(needle.len()).filter(|w| *w == needle).count();
+    assert_eq!(1, occurrences(b"storage"));
+    assert_eq!(1, occurrences(b"values"));
+    // while strings used once are still inline
+    assert_eq!(1, occurrences(b"\x27\x03foo"));
+
+    let reparsed = geoconverter::parse(&mut &bjson[..]);
+    let mut json = Vec::new();
+    let mut reparsed_json = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&elem, &mut json);
+    geoconverter::geo_struct_serializer::to_json(&reparsed, &mut reparsed_json);
+    assert_eq!(json, reparsed_json);
+}
+
+#[test]
+fn bgeo_writer_packs_numbers() {
+    let elem = geoconverter::parse(&mut File::open("./tests/box.geo").expect("failed to open test file"));
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    let reparsed = geoconverter::parse(&mut &bjson[..]);
+
+    // topology indices from ascii geo come back as a uniform array of the narrowest type
+    let topology = match &reparsed {
+        ReaderElement::Array(root_arr) => &root_arr[13],
+        _ => panic!("root is not an array"),
+    };
+    let pointref = match topology {
+        ReaderElement::Array(topo_arr) => &topo_arr[1],
+        _ => panic!("topology is not an array"),
+    };
+    match pointref {
+        ReaderElement::Array(pref_arr) => assert!(
+            matches!(pref_arr[1], ReaderElement::UniformArray(UniformArrayType::UniformArrayTi8(_))),
+            "indices are not a uniform int8 array: {:?}",
+            pref_arr[1]
+        ),
+        _ => panic!("pointref is not an array"),
+    }
+
+    assert!(same_values(&elem, &reparsed));
+
+    // small ints take 2 bytes instead of 9
+    let mut int_bytes = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&ReaderElement::Int(-3), &mut int_bytes);
+    assert_eq!(&[geoconverter::bgeo_constants::JID_INT8, 0xfd], &int_bytes[5..]);
+    let mut int_bytes = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&ReaderElement::Int(40000), &mut int_bytes);
+    assert_eq!(geoconverter::bgeo_constants::JID_UINT16, int_bytes[5]);
+    assert_eq!(8, int_bytes.len());
+}
+
+#[test]
+fn bgeo_writer_keeps_half_floats() {
+    let halfs = vec![0.0_f32, 1.0, -0.5, 0.099975586, 65504.0];
+    let elem = ReaderElement::UniformArray(UniformArrayType::UniformArrayTf16(halfs.clone()));
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    // magic, uniform array type, length and 2 bytes per value
+    assert_eq!(5 + 3 + 2 * halfs.len(), bjson.len());
+
+    match geoconverter::parse(&mut &bjson[..]) {
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTf16(vec)) => assert_eq!(halfs, vec),
+        x => panic!("unexpected element {:?}", x),
+    }
+}
+
+#[test]
+fn bgeo_writer_big_endian() {
+    use geoconverter::bgeo_struct_serializer::{to_bjson, to_bjson_endian, Endianness};
+
+    let elem = geoconverter::parse(&mut File::open("./tests/boxattr.bgeo").expect("failed to open test file"));
+
+    let mut le = Vec::new();
+    let mut be = Vec::new();
+    to_bjson(&elem, &mut le);
+    to_bjson_endian(&elem, &mut be, Endianness::Big);
+    assert_eq!(b"\x7fbJSN", &be[..5]);
+    assert_eq!(le.len(), be.len());
+    assert!(same_values(&elem, &geoconverter::parse(&mut &be[..])));
+
+    let numbers = ReaderElement::Array(vec![
+        ReaderElement::Int(-300),
+        ReaderElement::Float(0.25),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTf16(vec![1.0, -2.0])),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTbool(vec![true; 32])),
+    ]);
+    let mut be = Vec::new();
+    to_bjson_endian(&numbers, &mut be, Endianness::Big);
+    assert_eq!(&[0x12, 0xfe, 0xd4], &be[6..9]);
+    assert!(same_values(&numbers, &geoconverter::parse(&mut &be[..])));
+}
+
+#[test]
+fn uniform_string_arrays() {
+    let data: &[u8] = &[
+        0x7f, b'N', b'S', b'J', b'b', // little endian magic
+        0x5b, // array begin
+        0x2b, 0, 3, b'f', b'o', b'o', // define token 0
+        0x26, 0, // and use it
+        0x40, 0x26, 3, 0, 0, 0, // uniform array of token references
+        0x40, 0x27, 2, 1, b'a', 2, b'b', b'c', // uniform array of strings
+        0x5d, // array end
+    ];
+    let elem = geoconverter::try_parse(&mut &data[..]).expect("failed to parse uniform string arrays");
+    let arr = match &elem {
+        ReaderElement::Array(arr) => arr,
+        x => panic!("unexpected element {:?}", x),
+    };
+    assert_eq!(3, arr.len());
+    match &arr[1] {
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTstring(vec)) => assert_eq!(vec!["foo"; 3], *vec),
+        x => panic!("unexpected element {:?}", x),
+    }
+    match &arr[2] {
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTstring(vec)) => assert_eq!(vec!["a", "bc"], *vec),
+        x => panic!("unexpected element {:?}", x),
+    }
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    assert!(same_values(&elem, &geoconverter::parse(&mut &bjson[..])));
+    let mut json = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&elem, &mut json);
+    assert!(same_values(&elem, &geoconverter::parse(&mut &json[..])));
+
+    // token references must refer to defined tokens
+    let undefined: &[u8] = &[0x7f, b'N', b'S', b'J', b'b', 0x40, 0x26, 1, 5];
+    assert!(geoconverter::try_parse(&mut &undefined[..]).is_err());
+}
+
+#[test]
+fn large_uniform_arrays() {
+    use geoconverter::bgeo_struct_serializer::{to_bjson_endian, Endianness};
+
+    // bigger than a single read block
+    let ints: Vec<i32> = (0..100_000).map(|x| x * 7 - 350_000).collect();
+    let floats: Vec<f32> = (0..100_000).map(|x| x as f32 * 0.25).collect();
+    let elem = ReaderElement::Array(vec![
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(ints.clone())),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTf32(floats.clone())),
+    ]);
+    for endianness in [Endianness::Little, Endianness::Big] {
+        let mut bjson = Vec::new();
+        to_bjson_endian(&elem, &mut bjson, endianness);
+        match geoconverter::parse(&mut &bjson[..]) {
+            ReaderElement::Array(arr) => match (&arr[0], &arr[1]) {
+                (
+                    ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(i)),
+                    ReaderElement::UniformArray(UniformArrayType::UniformArrayTf32(f)),
+                ) => {
+                    assert_eq!(&ints, i);
+                    assert_eq!(&floats, f);
+                }
+                x => panic!("unexpected elements {:?}", x),
+            },
+            x => panic!("unexpected element {:?}", x),
+        }
+
+        let err = geoconverter::try_parse(&mut &bjson[..bjson.len() - 3]).expect_err("truncated array must not parse");
+        assert!(err.offset <= bjson.len() - 3);
+    }
+}
+
+#[test]
+fn mapped_lazy_arrays() {
+    use geoconverter::bgeo_struct_serializer::{to_bjson, to_bjson_endian, Endianness};
+    use geoconverter::{try_parse_mapped, MappedFile};
+
+    let ints: Vec<i32> = (0..10_000).map(|x| x * 3 - 15_000).collect();
+    let floats: Vec<f64> = (0..10_000).map(|x| x as f64 * 0.5).collect();
+    let elem = ReaderElement::Array(vec![
+        ReaderElement::Text("ints".into()),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(ints.clone())),
+        ReaderElement::Text("floats".into()),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(floats.clone())),
+        ReaderElement::Text("small".into()),
+        ReaderElement::UniformArray(UniformArrayType::UniformArrayTi32(vec![1, 2, 3])),
+    ]);
+    let path = std::env::temp_dir().join(format!("geoconverter_mapped_test_{}.bgeo", std::process::id()));
+    for endianness in [Endianness::Little, Endianness::Big] {
+        let mut bjson = Vec::new();
+        to_bjson_endian(&elem, &mut bjson, endianness);
+        std::fs::write(&path, &bjson).expect("failed to write temp file");
+        let parsed = try_parse_mapped(MappedFile::open(&path).expect("failed to map temp file")).expect("must parse");
+
+        let arr = match &parsed {
+            ReaderElement::Array(arr) => arr,
+            x => panic!("unexpected element {:?}", x),
+        };
+        let (lazy_ints, lazy_floats) = match (&arr[1], &arr[3], &arr[5]) {
+            (ReaderElement::LazyUniformArray(i), ReaderElement::LazyUniformArray(f), ReaderElement::UniformArray(_)) => (i, f),
+            x => panic!("unexpected elements {:?}", x),
+        };
+        assert_eq!(ints.len(), lazy_ints.len());
+        assert!(!lazy_ints.is_decoded() && !lazy_floats.is_decoded());
+
+        // writing in the same endianness just copies array data
+        let mut copy = Vec::new();
+        to_bjson_endian(&parsed, &mut copy, endianness);
+        assert_eq!(bjson, copy);
+        assert!(!lazy_ints.is_decoded() && !lazy_floats.is_decoded());
+
+        // only accessed array gets decoded
+        match lazy_floats.get() {
+            UniformArrayType::UniformArrayTf64(f) => assert_eq!(&floats, f),
+            x => panic!("unexpected array {:?}", x),
+        }
+        assert!(!lazy_ints.is_decoded() && lazy_floats.is_decoded());
+
+        let mut little = Vec::new();
+        to_bjson(&parsed, &mut little);
+        assert!(same_values(&elem, &geoconverter::parse(&mut &little[..])));
+    }
+    std::fs::remove_file(&path).expect("failed to remove temp file");
+}
+
+#[test]
+fn skip_attributes_while_parsing() {
+    use geoconverter::{AttributeClass, GeoAttribute, GeoAttributeKind, HoudiniGeoSchemaParser, MappedFile, ParseOptions};
+
+    let bgeo = std::fs::read("./tests/boxattr.bgeo").expect("failed to read test file");
+    let full = geoconverter::parse(&mut &bgeo[..]);
+
+    let only_p = ParseOptions::new().keep_attribute("P");
+    let parsed = [
+        geoconverter::try_parse_with_options(&mut &bgeo[..], &only_p).expect("must parse"),
+        geoconverter::try_parse_mapped_with_options(MappedFile::open("./tests/boxattr.bgeo").expect("must map"), &only_p)
+            .expect("must parse"),
+    ];
+
+    let mut full_schema = HoudiniGeoSchemaParser::new(&full).expect("must parse schema");
+    full_schema.parse_point_attributes().expect("must parse attributes");
+    let full_p = match full_schema.point_attribute("P") {
+        Some(GeoAttributeKind::Float64(p)) => p,
+        _ => panic!("no P in the box"),
+    };
+    for elem in parsed.iter() {
+        let mut schema = HoudiniGeoSchemaParser::new(elem).expect("must parse schema");
+        schema.parse_point_attributes().expect("must parse attributes");
+        schema.parse_primitives().expect("must parse primitives");
+        assert!(schema.point_attribute("foo").is_none());
+        assert!(schema.is_attribute_skipped(AttributeClass::Point, "foo"));
+        assert!(!schema.is_attribute_skipped(AttributeClass::Point, "P"));
+        match schema.point_attribute("P") {
+            Some(GeoAttributeKind::Float64(p)) => {
+                assert_eq!(full_p.len(), p.len());
+                assert!((0..p.len()).all(|i| full_p.value(i) == p.value(i)));
+            }
+            _ => panic!("P must be loaded"),
+        }
+    }
+
+    let points = ParseOptions::new().keep_class(AttributeClass::Point);
+    let elem = geoconverter::try_parse_with_options(&mut &bgeo[..], &points).expect("must parse");
+    let mut schema = HoudiniGeoSchemaParser::new(&elem).expect("must parse schema");
+    schema.parse_point_attributes().expect("must parse attributes");
+    assert!(schema.point_attribute("foo").is_some());
+    assert_eq!(0, schema.skipped_attribute_names(AttributeClass::Point).count());
+
+    // ascii geo skips them too
+    let geo = std::fs::read("./tests/box.geo").expect("failed to read test file");
+    let only_foo = ParseOptions::new().keep_attribute("foo");
+    let elem = geoconverter::try_parse_with_options(&mut &geo[..], &only_foo).expect("must parse");
+    let mut schema = HoudiniGeoSchemaParser::new(&elem).expect("must parse schema");
+    schema.parse_point_attributes().expect("must parse attributes");
+    schema.parse_primitives().expect("must parse primitives");
+    assert!(schema.point_attribute("P").is_none());
+    assert_eq!(vec!["P"], schema.skipped_attribute_names(AttributeClass::Point).collect::<Vec<_>>());
+}
+
+#[test]
+fn header_only_parsing() {
+    use geoconverter::{AttributeClass, GeoHeader, ParseOptions};
+
+    // cut right before primitives key, it's a token definition in bgeo
+    let cases = [("./tests/boxattr.bgeo", &b"+,\nprimitives"[..]), ("./tests/box.geo", &b"\"primitives\""[..])];
+    for (path, key_start) in cases {
+        let data = std::fs::read(path).expect("failed to read test file");
+        let cut = data.windows(key_start.len()).rposition(|x| x == key_start).expect("primitives must be there");
+        let truncated = &data[..cut];
+        assert!(geoconverter::try_parse(&mut &truncated[..]).is_err());
+
+        // header is known before primitives, so the rest is not read at all
+        let elem = geoconverter::try_parse_with_options(&mut &truncated[..], &ParseOptions::header_only())
+            .expect("header must parse");
+        let header = GeoHeader::new(&elem).expect("must be a header");
+        let full_header = GeoHeader::new(&geoconverter::parse(&mut &data[..])).expect("must be a header");
+
+        assert_eq!((8, 24, 6), (header.point_count, header.vertex_count, header.primitive_count));
+        assert_eq!(Some(([-0.5; 3], [0.5; 3])), header.bounds());
+        assert_eq!(Some("Houdini 19.5.569"), header.info_text("software"));
+        let describe = |header: &GeoHeader| -> Vec<String> {
+            header
+                .class_attributes(AttributeClass::Point)
+                .map(|x| format!("{} {}[{:?}]", x.name, x.type_name(), x.tuple_size))
+                .collect()
+        };
+        assert_eq!(describe(&full_header), describe(&header));
+        assert_eq!("P float[Some(3)]", describe(&header)[0]);
+        assert_eq!(0, header.class_attributes(AttributeClass::Vertex).count());
+
+        // topology is not kept
+        let arr = match &elem {
+            ReaderElement::Array(arr) => arr,
+            x => panic!("unexpected element {:?}", x),
+        };
+        let topology = arr.chunks(2).find(|pair| matches!(&pair[0], ReaderElement::Text(t) if t == "topology"));
+        assert!(matches!(topology, Some([_, ReaderElement::None])));
+    }
+}
+
+/// number of packed arrays and of values in them, panics on arrays of numbers that were left unpacked
+fn count_packed_arrays(elem: &ReaderElement) -> (usize, usize) {
+    match elem {
+        ReaderElement::UniformArray(x) => (1, x.len()),
+        ReaderElement::TupleArray(x) => (1, x.values().len()),
+        ReaderElement::Array(arr) => {
+            let numbers = arr.iter().all(|x| matches!(x, ReaderElement::Int(_) | ReaderElement::Float(_)));
+            assert!(arr.is_empty() || !numbers, "array of numbers is not packed: {:?}", arr);
+            arr.iter().map(count_packed_arrays).fold((0, 0), |(a, b), (c, d)| (a + c, b + d))
+        }
+        ReaderElement::KeyValueObject(map) => {
+            map.values().map(count_packed_arrays).fold((0, 0), |(a, b), (c, d)| (a + c, b + d))
+        }
+        _ => (0, 0),
+    }
+}
+
+#[test]
+fn ascii_number_arrays_are_packed() {
+    let elem = geoconverter::parse(&mut File::open("./tests/box.geo").expect("failed to open test file"));
+    // bounds, vertex indices, polygon run lengths, defaults and P tuples, all in one array
+    let (arrays, values) = count_packed_arrays(&elem);
+    assert_eq!(1 + 1 + 1 + 1 + 1, arrays);
+    assert_eq!(6 + 24 + 2 + 1 + 8 * 3, values);
+
+    let tuples = find_in_kv_path(&elem, &["attributes", "pointattributes"]);
+    let tuples = match tuples {
+        ReaderElement::Array(attrs) => match &attrs[0] {
+            ReaderElement::Array(blocks) => find_in_kv_path(&blocks[1], &["values", "tuples"]),
+            x => panic!("unexpected attribute {:?}", x),
+        },
+        x => panic!("unexpected attributes {:?}", x),
+    };
+    match tuples {
+        ReaderElement::TupleArray(tuples) => {
+            assert_eq!((8, 3), (tuples.len(), tuples.tuple_size()));
+            assert!(matches!(tuples.values(), UniformArrayType::UniformArrayTf64(_)));
+        }
+        x => panic!("unexpected tuples {:?}", x),
+    }
+}
+
+/// value under the chain of kv array keys
+fn find_in_kv_path<'a>(elem: &'a ReaderElement, keys: &[&str]) -> &'a ReaderElement {
+    keys.iter().fold(elem, |elem, key| match elem {
+        ReaderElement::Array(arr) => arr
+            .chunks_exact(2)
+            .find(|pair| matches!(&pair[0], ReaderElement::Text(k) if k == key))
+            .map(|pair| &pair[1])
+            .unwrap_or_else(|| panic!("no key {}", key)),
+        x => panic!("not a kv array {:?}", x),
+    })
+}
+
+#[test]
+fn compact_parsed_elements() {
+    assert!(std::mem::size_of::<ReaderElement>() <= 32);
+
+    let geo = br#"["name","ints","values",[1,2,300],"name","floats","values",[0.5,0,1.5],
+        "name","bools","values",[true,false],"name","mixed","values",[1,"a"],"name","empty","values",[],
+        "maps",[{"type":1},{"type":2}]]"#;
+    let elem = geoconverter::parse(&mut &geo[..]);
+    let arr = match &elem {
+        ReaderElement::Array(arr) => arr,
+        x => panic!("unexpected element {:?}", x),
+    };
+    // arrays of numbers are packed right away, ints mixed with floats become floats
+    assert!(matches!(&arr[3], ReaderElement::UniformArray(UniformArrayType::UniformArrayTi16(x)) if *x == [1, 2, 300]));
+    assert!(matches!(&arr[7], ReaderElement::UniformArray(UniformArrayType::UniformArrayTf64(x)) if *x == [0.5, 0.0, 1.5]));
+    assert!(matches!(&arr[11], ReaderElement::UniformArray(UniformArrayType::UniformArrayTbool(x)) if *x == [true, false]));
+    assert!(matches!(&arr[15], ReaderElement::Array(x) if x.len() == 2));
+    assert!(matches!(&arr[19], ReaderElement::Array(x) if x.is_empty()));
+
+    // repeated keys are the same string
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    for bytes in [geo.to_vec(), bjson] {
+        let elem = geoconverter::parse(&mut &bytes[..]);
+        let keys = match &elem {
+            ReaderElement::Array(arr) => arr
+                .iter()
+                .filter_map(|x| match x {
+                    ReaderElement::Text(t) if t == "name" => Some(t.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            x => panic!("unexpected element {:?}", x),
+        };
+        assert_eq!(5, keys.len());
+        assert!(keys.iter().all(|x| x.ptr_eq(&keys[0])));
+
+        // map keys too
+        let map_keys = match find_in_kv_path(&elem, &["maps"]) {
+            ReaderElement::Array(maps) => maps
+                .iter()
+                .flat_map(|x| match x {
+                    ReaderElement::KeyValueObject(map) => map.keys().cloned().collect(),
+                    _ => Vec::new(),
+                })
+                .collect::<Vec<_>>(),
+            x => panic!("unexpected maps {:?}", x),
+        };
+        assert_eq!(2, map_keys.len());
+        assert!(map_keys[0].ptr_eq(&map_keys[1]));
+    }
+}
+
+#[test]
+fn geometry_roundtrip() {
+    for filepath in ["./tests/box.geo", "./tests/boxattr.bgeo"] {
+        let elem = geoconverter::parse(&mut File::open(filepath).expect("failed to open test file"));
+        let geometry = Geometry::from_structure(&elem).expect("failed to read geometry");
+        assert_eq!(8, geometry.point_count());
+        assert_eq!(6, geometry.primitive_count());
+        assert_eq!(24, geometry.vertex_count());
+
+        let mut bjson = Vec::new();
+        geoconverter::bgeo_struct_serializer::to_bjson(&geometry.to_structure(), &mut bjson);
+        let reparsed = geoconverter::parse(&mut &bjson[..]);
+        let reparsed = Geometry::from_structure(&reparsed).expect("failed to read written geometry");
+
+        assert_eq!(geometry.fileversion, reparsed.fileversion);
+        assert_eq!(geometry.polygons().collect::<Vec<_>>(), reparsed.polygons().collect::<Vec<_>>());
+        assert!((0..geometry.vertex_count()).all(|vtx| geometry.vtx_to_ptnum(vtx) == reparsed.vtx_to_ptnum(vtx)));
+        for class in AttributeClass::ALL {
+            let names = geometry.attribute_names(class).collect::<Vec<_>>();
+            assert_eq!(names, reparsed.attribute_names(class).collect::<Vec<_>>());
+            for name in names {
+                assert!(
+                    same_attribute(geometry.attribute(class, name).unwrap(), reparsed.attribute(class, name).unwrap()),
+                    "{} attribute {} differs after roundtrip",
+                    class,
+                    name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn attribute_storage() {
+    let elem = geoconverter::parse(&mut File::open("./tests/boxattr.bgeo").expect("failed to open test file"));
+    let mut schema = HoudiniGeoSchemaParser::new(&elem).expect("failed to read geometry");
+    schema.parse_point_attributes().unwrap();
+    let mut pos = match schema.point_attribute("P") {
+        Some(GeoAttributeKind::Float64(x)) => x.clone(),
+        _ => panic!("no float P attribute"),
+    };
+    assert_eq!(AttributeStorage::Fpreal32, pos.storage());
+    let bjson_size = |elem: &ReaderElement| {
+        let mut bjson = Vec::new();
+        geoconverter::bgeo_struct_serializer::to_bjson(elem, &mut bjson);
+        bjson.len()
+    };
+
+    // written back in storage it was read with, so size does not change
+    let mut written = elem.clone();
+    HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Float64(pos.clone()), &mut written).unwrap();
+    assert_eq!(bjson_size(&elem), bjson_size(&written));
+
+    // unless storage is changed explicitly
+    pos.set_storage(AttributeStorage::Fpreal64).unwrap();
+    HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Float64(pos.clone()), &mut written).unwrap();
+    assert_eq!(bjson_size(&elem) + 8 * 4 * 3, bjson_size(&written));
+    let mut schema = HoudiniGeoSchemaParser::new(&written).expect("failed to read written geometry");
+    schema.parse_point_attributes().unwrap();
+    let reparsed = schema.point_attribute("P").unwrap();
+    assert_eq!(Some(AttributeStorage::Fpreal64), reparsed.storage());
+    assert!(same_attribute(&GeoAttributeKind::Float64(pos), reparsed));
+
+    // ints are clamped to fit smaller storage
+    let mut geometry = Geometry::from_structure(&elem).unwrap();
+    let mut ids = TupleGeoAttribute::new(1, (0..8).map(|x| x * 100 - 300).collect());
+    assert_eq!(AttributeStorage::Int64, ids.storage());
+    let err = ids.set_storage(AttributeStorage::Fpreal32).expect_err("int attribute took float storage");
+    assert_eq!("storage fpreal32 does not fit int attribute values", err.message);
+    assert_eq!(AttributeStorage::Int64, ids.storage());
+    ids.set_storage(AttributeStorage::Uint8).unwrap();
+    geometry.insert_attribute(AttributeClass::Point, "id", GeometryAttribute::new(GeoAttributeKind::Int64(ids)));
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let warnings_in_handler = warnings.clone();
+    geoconverter::diagnostics::set_handler(move |_, message| {
+        warnings_in_handler.lock().expect("poisoned").push(message.to_owned());
+    });
+    let written = geometry.to_structure();
+    geoconverter::diagnostics::reset_handler();
+    let expected = "5 values do not fit uint8 storage, clamping them to [0, 255]".to_owned();
+    assert!(warnings.lock().expect("poisoned").contains(&expected));
+    let reparsed = Geometry::from_structure(&written).unwrap();
+    match &reparsed.attribute(AttributeClass::Point, "id").unwrap() {
+        GeoAttributeKind::Int64(x) => {
+            assert_eq!(AttributeStorage::Uint8, x.storage());
+            assert_eq!(vec![0, 0, 0, 0, 100, 200, 255, 255], x.data());
+        }
+        _ => panic!("no int id attribute"),
+    }
+    assert_eq!(Some(AttributeStorage::Fpreal32), reparsed.attribute(AttributeClass::Point, "P").unwrap().storage());
+}
+
+#[test]
+fn add_attributes() {
+    let mut elem = geoconverter::parse(&mut File::open("./tests/box.geo").expect("failed to open test file"));
+
+    let mut color = TupleGeoAttribute::new(3, vec![0.5; 8 * 3]);
+    color.set_storage(AttributeStorage::Fpreal32).unwrap();
+    color.set_defaults(vec![1.0, 1.0, 1.0]);
+    let color = GeoAttributeKind::Float64(color);
+    let color = HoudiniGeoSchemaParser::add_attribute(AttributeClass::Point, "Cd", color, Some("color"), &mut elem).unwrap();
+    let id = GeoAttributeKind::Int64(TupleGeoAttribute::new(1, (0..24).collect()));
+    HoudiniGeoSchemaParser::add_attribute(AttributeClass::Vertex, "id", id, None, &mut elem).unwrap();
+    let name = GeoAttributeKind::String(TokenGeoAttribute::new(vec!["side".to_owned()], vec![0; 6]));
+    HoudiniGeoSchemaParser::add_attribute(AttributeClass::Primitive, "name", name, None, &mut elem).unwrap();
+    let frame = GeoAttributeKind::Int64(TupleGeoAttribute::new(1, vec![1001]));
+    HoudiniGeoSchemaParser::add_attribute(AttributeClass::Detail, "frame", frame, None, &mut elem).unwrap();
+
+    // same name again, or wrong number of elements
+    let again = GeoAttributeKind::Float64(TupleGeoAttribute::new(1, vec![0.0; 8]));
+    assert!(HoudiniGeoSchemaParser::add_attribute(AttributeClass::Point, "Cd", again, None, &mut elem).is_err());
+    let short = GeoAttributeKind::Float64(TupleGeoAttribute::new(1, vec![0.0; 7]));
+    assert!(HoudiniGeoSchemaParser::add_attribute(AttributeClass::Point, "mask", short, None, &mut elem).is_err());
+
+    // added attribute can be changed and written as any other
+    let mut color = match color {
+        GeoAttributeKind::Float64(x) => x,
+        _ => panic!("attribute changed type"),
+    };
+    color.set_value(0, &[0.25, 0.25, 0.25]);
+    HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Float64(color), &mut elem).unwrap();
+
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    let reparsed = geoconverter::parse(&mut &bjson[..]);
+    let mut geometry = Geometry::from_structure(&reparsed).expect("failed to read written geometry");
+    let classes = AttributeClass::ALL.map(|class| geometry.attribute_names(class).collect::<Vec<_>>().join(" "));
+    assert_eq!(["P Cd", "id", "name", "frame"], classes);
+    assert_eq!(Some("color"), geometry.attributes(AttributeClass::Point).get("Cd").unwrap().typeinfo());
+    assert_eq!(Some("point"), geometry.attributes(AttributeClass::Point).get("P").unwrap().typeinfo());
+    match geometry.attribute(AttributeClass::Primitive, "name").unwrap() {
+        GeoAttributeKind::String(x) => assert_eq!("side", x.value(5)),
+        _ => panic!("no string name attribute"),
+    }
+    match geometry.attribute(AttributeClass::Point, "Cd").unwrap() {
+        GeoAttributeKind::Float64(x) => {
+            assert_eq!(AttributeStorage::Fpreal32, x.storage());
+            assert_eq!(&[1.0, 1.0, 1.0], x.defaults());
+            assert_eq!(&[0.25, 0.25, 0.25, 0.5, 0.5, 0.5], &x.data()[..6]);
+        }
+        _ => panic!("no float Cd attribute"),
+    }
+
+    // new points get the defaults
+    let new_point = geometry.add_points(1);
+    match geometry.attribute(AttributeClass::Point, "Cd").unwrap() {
+        GeoAttributeKind::Float64(x) => assert_eq!(&[1.0, 1.0, 1.0], x.value(new_point)),
+        _ => panic!("no float Cd attribute"),
+    }
+}
+
+#[test]
+fn geometry_empty_polygon() {
+    let text = std::fs::read_to_string("./tests/box.geo").expect("failed to read test file");
+    let text = text
+        .replacen("\"primitivecount\",6", "\"primitivecount\",7", 1)
+        .replacen("\"nvertices_rle\",[4,6]", "\"nvertices\",[4,0,4,4,4,4,4]", 1);
+    let elem = geoconverter::parse(&mut text.as_bytes());
+    let geometry = Geometry::from_structure(&elem).expect("failed to read geometry");
+    let expected = vec![(0, 0..4), (1, 4..4), (2, 4..8), (3, 8..12), (4, 12..16), (5, 16..20), (6, 20..24)];
+    assert_eq!(expected, geometry.polygons().collect::<Vec<_>>());
+
+    // still a single polygon run when written
+    let written = geometry.to_structure();
+    assert!(matches!(find_in_kv_path(&written, &["primitives"]), ReaderElement::Array(blocks) if blocks.len() == 1));
+    let reparsed = Geometry::from_structure(&written).expect("failed to read written geometry");
+    assert_eq!(expected, reparsed.polygons().collect::<Vec<_>>());
+}
+
+#[test]
+fn geometry_from_scratch() {
+    let mut geometry = Geometry::new();
+    let first = geometry.add_points(4);
+    let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    geometry.insert_attribute(
+        AttributeClass::Point,
+        "P",
+        GeometryAttribute::new(GeoAttributeKind::Float64(TupleGeoAttribute::new(3, positions.clone()))),
+    );
+    geometry.add_polygon(&[first, first + 1, first + 2]);
+    geometry.add_polygon(&[first, first + 2, first + 3]);
+    geometry.insert_attribute(
+        AttributeClass::Primitive,
+        "name",
+        GeometryAttribute::new(GeoAttributeKind::String(TokenGeoAttribute::new(vec!["tri".to_owned()], vec![0, 0]))),
+    );
+
+    let elem = geometry.to_structure();
+    let mut schema = HoudiniGeoSchemaParser::new(&elem).expect("written geometry is not valid");
+    schema.parse_primitives().unwrap();
+    schema.parse_point_attributes().unwrap();
+    assert_eq!(4, schema.point_count());
+    assert_eq!(2, schema.primitive_count());
+    assert_eq!(
+        vec![vec![0, 1, 2], vec![0, 2, 3]],
+        schema.polygons().iter().map(|p| p.vertices.iter().map(|v| v.ptnum).collect::<Vec<_>>()).collect::<Vec<_>>()
+    );
+    match schema.point_attribute("P") {
+        Some(GeoAttributeKind::Float64(p)) => assert_eq!(&positions[..], p.data()),
+        _ => panic!("no P attribute"),
+    }
+}
+
+#[test]
+fn detail_attributes() {
+    let globals = concat!(
+        r#""attributes",["globalattributes",["#,
+        r#"[["scope","public","type","numeric","name","frame","options",{}],"#,
+        r#"["size",1,"storage","int32","values",["size",1,"storage","int32","arrays",[[1001]]]]],"#,
+        r#"[["scope","public","type","string","name","shot","options",{}],"#,
+        r#"["size",1,"storage","int32","strings",["sh010"],"indices",["size",1,"storage","int32","arrays",[[0]]]]]"#,
+        r#"],"#
+    );
+    let text = std::fs::read_to_string("./tests/box.geo").expect("failed to read test file");
+    let mut elem = geoconverter::parse(&mut text.replacen("\"attributes\",[", globals, 1).as_bytes());
+
+    let mut schema = HoudiniGeoSchemaParser::new(&elem).expect("failed to read geometry");
+    schema.parse_detail_attributes().unwrap();
+    assert_eq!(1, schema.element_count(AttributeClass::Detail));
+    let mut frame = match schema.detail_attribute("frame") {
+        Some(GeoAttributeKind::Int64(x)) => x.clone(),
+        _ => panic!("no int frame attribute"),
+    };
+    assert_eq!(&[1001], frame.data());
+    let mut shot = match schema.detail_attribute("shot") {
+        Some(GeoAttributeKind::String(x)) => x.clone(),
+        _ => panic!("no string shot attribute"),
+    };
+    assert_eq!("sh010", shot.value(0));
+
+    // edit and write back
+    frame.set_value(0, &[1002]);
+    shot.set_value(0, &"sh020".to_owned());
+    HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::Int64(frame), &mut elem).unwrap();
+    HoudiniGeoSchemaParser::write_to_strucutre(GeoAttributeKind::String(shot), &mut elem).unwrap();
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&elem, &mut bjson);
+    let reparsed = geoconverter::parse(&mut &bjson[..]);
+    let mut schema = HoudiniGeoSchemaParser::new(&reparsed).expect("failed to read written geometry");
+    schema.parse_detail_attributes().unwrap();
+    assert_eq!("1002 sh020", detail_values(schema.detail_attribute("frame"), schema.detail_attribute("shot")));
+
+    let header_elem = geoconverter::try_parse_with_options(&mut &bjson[..], &ParseOptions::header_only()).unwrap();
+    let header = geoconverter::GeoHeader::new(&header_elem).unwrap();
+    let types = header.class_attributes(AttributeClass::Detail).map(|x| (x.name.as_str(), x.type_name())).collect::<Vec<_>>();
+    assert_eq!(vec![("frame", "int"), ("shot", "string")], types);
+
+    // detail attributes can be kept alone while parsing, and survive a geometry roundtrip
+    let options = ParseOptions::new().keep_class(AttributeClass::Detail);
+    let elem = geoconverter::try_parse_with_options(&mut &bjson[..], &options).unwrap();
+    let geometry = Geometry::from_structure(&elem).unwrap();
+    assert_eq!(0, geometry.attribute_names(AttributeClass::Point).count());
+    let written = geometry.to_structure();
+    let mut schema = HoudiniGeoSchemaParser::new(&written).expect("failed to read geometry");
+    schema.parse_detail_attributes().unwrap();
+    assert_eq!("1002 sh020", detail_values(schema.detail_attribute("frame"), schema.detail_attribute("shot")));
+}
+
+#[test]
+fn groups() {
+    let groups = concat!(
+        r#""pointgroups",["#,
+        r#"[["name","top"],["selection",["unordered",["i8",[0,0,1,1,0,0,1,1]]]]],"#,
+        r#"[["name","front"],["selection",["unordered",["boolRLE",[2,true,6,false]]]]]],"#,
+        r#""primitivegroups",[[["name","picked"],["selection",["ordered",["i32",[2,0]]]]]],"#,
+        r#""vertexgroups",[[["name","corner"],["selection",["defaults",["size",1,"storage","int8","values",[0]],"#,
+        r#""unordered",["i8",[true,false,false,false,false,false,false,false,false,false,false,false,"#,
+        r#"false,false,false,false,false,false,false,false,false,false,false,false]]]]]],"#,
+        r#""edgegroups",[[["name","seam"],["points",[0,1,1,3]]]],"#,
+        r#""primitives",["#
+    );
+    let text = std::fs::read_to_string("./tests/box.geo").expect("failed to read test file");
+    let elem = geoconverter::parse(&mut text.replacen("\"primitives\",[", groups, 1).as_bytes());
+
+    let check = |elem: &ReaderElement| {
+        let mut schema = HoudiniGeoSchemaParser::new(elem).expect("failed to read geometry");
+        schema.parse_groups().unwrap();
+        assert_eq!(vec!["top", "front"], schema.group_names(AttributeClass::Point).collect::<Vec<_>>());
+        let top = schema.group(AttributeClass::Point, "top").unwrap();
+        assert_eq!(vec![2, 3, 6, 7], top.iter().collect::<Vec<_>>());
+        assert!(top.contains(3) && !top.contains(4) && !top.contains(100));
+        assert!(!top.is_ordered());
+        assert_eq!(vec![0, 1], schema.group(AttributeClass::Point, "front").unwrap().iter().collect::<Vec<_>>());
+        let picked = schema.group(AttributeClass::Primitive, "picked").unwrap();
+        assert!(picked.is_ordered());
+        assert_eq!(vec![2, 0], picked.iter().collect::<Vec<_>>());
+        assert_eq!(1, schema.group(AttributeClass::Vertex, "corner").unwrap().len());
+        assert!(schema.group(AttributeClass::Primitive, "top").is_none());
+        assert_eq!(vec!["seam"], schema.edge_group_names().collect::<Vec<_>>());
+        let seam = schema.edge_group("seam").unwrap();
+        assert_eq!(&[(0, 1), (1, 3)], seam.edges());
+        assert!(seam.contains(1, 0) && !seam.contains(0, 3));
+    };
+    check(&elem);
+
+    // groups survive geometry roundtrip
+    let geometry = Geometry::from_structure(&elem).unwrap();
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&geometry.to_structure(), &mut bjson);
+    check(&geoconverter::parse(&mut &bjson[..]));
+
+    // and exporters can be restricted to a primitive group
+    let picked = geometry.group(AttributeClass::Primitive, "picked");
+    let facet_count = |group| {
+        let mut stl = Vec::new();
+        geoconverter::serialize_stl(&geoconverter::create_stl_solid(&geometry, group), &mut stl);
+        String::from_utf8(stl).unwrap().matches("facet normal").count()
+    };
+    assert_eq!(4, facet_count(picked));
+    assert_eq!(12, facet_count(None));
+    let mut obj = Vec::new();
+    geoconverter::serialize_obj(&geometry, picked, &mut obj);
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(8, obj.lines().filter(|x| x.starts_with("v ")).count());
+    assert_eq!(2, obj.lines().filter(|x| x.starts_with("f ")).count());
+}
+
+#[test]
+fn group_fixture_roundtrip() {
+    // hand written in the layout houdini saves groups in, every encoding the reader knows about
+    let elem = geoconverter::parse(&mut File::open("./tests/boxgroups.geo").expect("failed to open test file"));
+
+    let check = |geometry: &Geometry| {
+        let members = |class, name| geometry.group(class, name).unwrap().iter().collect::<Vec<_>>();
+        assert_eq!(vec!["top", "back", "corners"], geometry.group_names(AttributeClass::Point).collect::<Vec<_>>());
+        assert_eq!(vec![2, 3, 6, 7], members(AttributeClass::Point, "top"));
+        assert_eq!(vec![4, 5, 6, 7], members(AttributeClass::Point, "back"));
+        assert_eq!(vec![6, 1, 3], members(AttributeClass::Point, "corners"));
+        assert!(geometry.group(AttributeClass::Point, "corners").unwrap().is_ordered());
+        assert_eq!(vec!["sides", "caps", "picked"], geometry.group_names(AttributeClass::Primitive).collect::<Vec<_>>());
+        assert_eq!(vec![2, 3, 4, 5], members(AttributeClass::Primitive, "sides"));
+        assert_eq!(vec![0, 1], members(AttributeClass::Primitive, "caps"));
+        assert_eq!(vec![2, 0], members(AttributeClass::Primitive, "picked"));
+        assert!(geometry.group(AttributeClass::Primitive, "picked").unwrap().is_ordered());
+        assert_eq!(vec!["seam", "rim"], geometry.edge_group_names().collect::<Vec<_>>());
+        assert_eq!(&[(0, 1), (1, 3)], geometry.edge_group("seam").unwrap().edges());
+        assert_eq!(&[(4, 5), (5, 7)], geometry.edge_group("rim").unwrap().edges());
+    };
+    let geometry = Geometry::from_structure(&elem).expect("failed to read geometry");
+    check(&geometry);
+
+    // membership is the same after writing geo and bgeo back
+    let written = geometry.to_structure();
+    let mut json = Vec::new();
+    geoconverter::geo_struct_serializer::to_json(&written, &mut json);
+    let mut bjson = Vec::new();
+    geoconverter::bgeo_struct_serializer::to_bjson(&written, &mut bjson);
+    for data in [json, bjson] {
+        let reparsed = geoconverter::parse(&mut &data[..]);
+        check(&Geometry::from_structure(&reparsed).expect("failed to read written geometry"));
+    }
+}
+
+fn detail_values(frame: Option<&GeoAttributeKind>, shot: Option<&GeoAttributeKind>) -> String {
+    match (frame, shot) {
+        (Some(GeoAttributeKind::Int64(frame)), Some(GeoAttributeKind::String(shot))) => {
+            format!("{} {}", frame.value(0)[0], shot.value(0))
+        }
+        _ => panic!("detail attributes are missing"),
+    }
+}
+
+fn same_attribute(a: &GeoAttributeKind, b: &GeoAttributeKind) -> bool {
+    a.tuple_size() == b.tuple_size()
+        && a.storage() == b.storage()
+        && match (a, b) {
+            (GeoAttributeKind::Float64(x), GeoAttributeKind::Float64(y)) => x.data() == y.data(),
+            (GeoAttributeKind::Int64(x), GeoAttributeKind::Int64(y)) => x.data() == y.data(),
+            (GeoAttributeKind::String(x), GeoAttributeKind::String(y)) => {
+                x.tokens() == y.tokens() && x.indices() == y.indices()
+            }
+            _ => false,
+        }
+}
+
+/// compare element values, not caring if arrays are uniform or not
+fn same_values(a: &ReaderElement, b: &ReaderElement) -> bool {
+    let expand = |x: &ReaderElement| match x {
+        ReaderElement::Array(arr) => Some(arr.clone()),
+        ReaderElement::UniformArray(uarr) => Some(uarr.to_elements()),
+        ReaderElement::LazyUniformArray(lazy) => Some(lazy.get().to_elements()),
+        ReaderElement::TupleArray(tuples) => Some(tuples.to_elements()),
+        _ => None,
+    };
+    match (a, b) {
+        (ReaderElement::Int(x), ReaderElement::Int(y)) => x == y,
+        (ReaderElement::Float(x), ReaderElement::Float(y)) => x == y,
+        (ReaderElement::Text(x), ReaderElement::Text(y)) => x == y,
+        (ReaderElement::Bool(x), ReaderElement::Bool(y)) => x == y,
+        (ReaderElement::None, ReaderElement::None) => true,
+        (ReaderElement::KeyValueObject(x), ReaderElement::KeyValueObject(y)) => {
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|((xk, xv), (yk, yv))| xk == yk && same_values(xv, yv))
+        }
+        _ => match (expand(a), expand(b)) {
+            (Some(x), Some(y)) => x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| same_values(x, y)),
+            _ => false,
+        },
+    }
+}
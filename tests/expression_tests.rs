@@ -5,8 +5,12 @@ use geoconverter::{
     houdini_geo_schema_manipulator,
     HoudiniGeoSchemaParser,
     GeoAttributeKind,
-    GeoAttribute
+    GeoAttribute,
+    AttributeStorage,
+    AttributeClass,
+    TupleGeoAttribute,
 };
+use geoconverter::houdini_geo_schema_manipulator::ManipulatorError;
 
 #[test]
 fn test_write_something() {
@@ -14,16 +18,16 @@ fn test_write_something() {
     let f = File::open(filepath).expect("failed to open test file");
     let geo_data = parse(&mut BufReader::new(f));
 
-    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data);
+    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data).expect("bad schema");
 
     manip.run_over_point_attributes("@foo+100.29", "foo").expect("failed!!");
 
     let result_elem = manip.into_result();
 
     // now test
-    let mut result_parser = HoudiniGeoSchemaParser::new(&result_elem);
+    let mut result_parser = HoudiniGeoSchemaParser::new(&result_elem).expect("bad schema");
 
-    result_parser.parse_point_attributes();
+    result_parser.parse_point_attributes().expect("bad schema");
 
     let attr = if let Some(GeoAttributeKind::Float64(attr)) = result_parser.point_attribute("foo") {
         attr
@@ -31,10 +35,103 @@ fn test_write_something() {
         panic!("no foo? wtf?")
     };
 
+    // attribute is stored as f32 in test file, and is written back as f32 too
+    assert_eq!(AttributeStorage::Fpreal32, attr.storage());
+    assert_eq!(100.29_f32 as f64, attr.value(0)[0]);
+    assert_eq!((100.29 + (1.23*1.0_f32) as f64) as f32 as f64, attr.value(1)[0]);
+    assert_eq!((100.29 + (1.23*4.0_f32) as f64) as f32 as f64, attr.value(4)[0]);
+}
+
+#[test]
+fn test_write_with_storage() {
+    let filepath = "tests/boxattr.bgeo";
+    let f = File::open(filepath).expect("failed to open test file");
+    let geo_data = parse(&mut BufReader::new(f));
+
+    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data).expect("bad schema");
+    manip.set_target_storage(AttributeStorage::Fpreal64);
+    manip.run_over_point_attributes("@foo+100.29", "foo").expect("failed!!");
+    manip.set_target_storage(AttributeStorage::Int32);
+    assert!(manip.run_over_point_attributes("@foo+1", "foo").is_err());
+
+    let result_elem = manip.into_result();
+    let mut result_parser = HoudiniGeoSchemaParser::new(&result_elem).expect("bad schema");
+    result_parser.parse_point_attributes().expect("bad schema");
+    let attr = if let Some(GeoAttributeKind::Float64(attr)) = result_parser.point_attribute("foo") {
+        attr
+    } else {
+        panic!("no foo? wtf?")
+    };
+
     // orig value in test file is stored as f32, then converted to f64, 
     // so we have to test the same way
+    assert_eq!(AttributeStorage::Fpreal64, attr.storage());
     assert_eq!(100.29, attr.value(0)[0]);
     assert_eq!(100.29 + (1.23*1.0_f32) as f64, attr.value(1)[0]);
     assert_eq!(100.29 + (1.23*2.0_f32) as f64, attr.value(2)[0]);
     assert_eq!(100.29 + (1.23*4.0_f32) as f64, attr.value(4)[0]);    
 }
+
+#[test]
+fn test_write_point_group() {
+    let text = std::fs::read_to_string("tests/box.geo").expect("failed to read test file");
+    let group = r#""pointgroups",[[["name","top"],["selection",["unordered",["i8",[0,0,1,1,0,0,1,1]]]]]],"primitives",["#;
+    let geo_data = parse(&mut text.replacen("\"primitives\",[", group, 1).as_bytes());
+
+    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data).expect("bad schema");
+    manip.run_over_point_group("@P*2", "P", "top").expect("failed!!");
+    assert!(manip.run_over_point_group("@P*2", "P", "bottom").is_err());
+
+    let result_elem = manip.into_result();
+    let mut result_parser = HoudiniGeoSchemaParser::new(&result_elem).expect("bad schema");
+    result_parser.parse_point_attributes().expect("bad schema");
+    let attr = if let Some(GeoAttributeKind::Float64(attr)) = result_parser.point_attribute("P") {
+        attr
+    } else {
+        panic!("no P")
+    };
+
+    // only points of the group are changed
+    assert_eq!(&[0.5, -0.5, 0.5], attr.value(0));
+    assert_eq!(&[1.0, 1.0, 1.0], attr.value(2));
+    assert_eq!(&[-1.0, 1.0, -1.0], attr.value(6));
+}
+
+#[test]
+fn test_write_new_attribute() {
+    let filepath = "tests/boxattr.bgeo";
+    let f = File::open(filepath).expect("failed to open test file");
+    let geo_data = parse(&mut BufReader::new(f));
+
+    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data).expect("bad schema");
+    manip.run_over_point_attributes("@P*2", "Pscaled").expect("failed!!");
+
+    let result_elem = manip.into_result();
+    let mut result_parser = HoudiniGeoSchemaParser::new(&result_elem).expect("bad schema");
+    result_parser.parse_point_attributes().expect("bad schema");
+    let (pos, scaled) = match (result_parser.point_attribute("P"), result_parser.point_attribute("Pscaled")) {
+        (Some(GeoAttributeKind::Float64(pos)), Some(GeoAttributeKind::Float64(scaled))) => (pos, scaled),
+        _ => panic!("no Pscaled"),
+    };
+    assert_eq!(3, scaled.tuple_size());
+    assert_eq!(pos.data().iter().map(|x| x * 2.0).collect::<Vec<_>>(), scaled.data());
+}
+
+#[test]
+fn test_write_errors() {
+    let filepath = "tests/boxattr.bgeo";
+    let f = File::open(filepath).expect("failed to open test file");
+    let mut geo_data = parse(&mut BufReader::new(f));
+    let ids = GeoAttributeKind::Int64(TupleGeoAttribute::new(1, (0..8).collect()));
+    HoudiniGeoSchemaParser::add_attribute(AttributeClass::Point, "id", ids, None, &mut geo_data).expect("bad schema");
+
+    let mut manip = houdini_geo_schema_manipulator::HoudiniGeoSchemaManipulator::new(&geo_data).expect("bad schema");
+    match manip.run_over_point_attributes("@nope*2", "foo") {
+        Err(ManipulatorError::SchemaError(e)) => assert_eq!("no point attribute 'nope' found", e.message),
+        _ => panic!("missing binding attribute is not an error"),
+    }
+    assert!(matches!(manip.run_over_point_attributes("@id*2", "foo"), Err(ManipulatorError::SchemaError(_))));
+    assert!(matches!(manip.run_over_point_attributes("@foo*2", "id"), Err(ManipulatorError::SchemaError(_))));
+    assert!(matches!(manip.run_over_point_attributes("@foo.x", "foo"), Err(ManipulatorError::ExpressionError(_))));
+    assert!(matches!(manip.run_over_point_attributes("@foo.x", "bar"), Err(ManipulatorError::ExpressionError(_))));
+}
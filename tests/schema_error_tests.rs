@@ -0,0 +1,53 @@
+use geoconverter::{parse, HoudiniGeoSchemaParser, ReaderElement};
+
+fn parse_patched_box(from: &str, to: &str) -> ReaderElement {
+    let text = std::fs::read_to_string("./tests/box.geo").expect("failed to read test file");
+    assert!(text.contains(from), "test file does not contain '{}'", from);
+    parse(&mut text.replacen(from, to, 1).as_bytes())
+}
+
+#[test]
+fn bad_attribute_storage() {
+    let elem = parse_patched_box("\"storage\",\"fpreal32\",\n\t\t\t\t\t\t\"tuples\"", "\"storage\",32,\n\t\t\t\t\t\t\"tuples\"");
+    let mut parser = HoudiniGeoSchemaParser::new(&elem).expect("header is fine");
+    let err = parser.parse_point_attributes().expect_err("storage is broken");
+    assert_eq!("attributes/pointattributes/0/1/values/storage", err.path.to_string());
+}
+
+#[test]
+fn bad_attribute_tuple() {
+    let elem = parse_patched_box("[0.5,0.5,-0.5]", "[0.5,0.5]");
+    let mut parser = HoudiniGeoSchemaParser::new(&elem).expect("header is fine");
+    let err = parser.parse_point_attributes().expect_err("tuple is broken");
+    assert_eq!("attributes/pointattributes/0/1/values/tuples/7", err.path.to_string());
+}
+
+#[test]
+fn bad_primitives() {
+    let elem = parse_patched_box("\"nvertices_rle\",[4,6]", "\"nvertices_rle\",[4,7]");
+    let mut parser = HoudiniGeoSchemaParser::new(&elem).expect("header is fine");
+    let err = parser.parse_primitives().expect_err("there are too many primitives");
+    assert_eq!("primitives/0/1/nvertices_rle", err.path.to_string());
+}
+
+#[test]
+fn bad_header() {
+    let elem = parse_patched_box("\"pointcount\"", "\"pointcuont\"");
+    let err = HoudiniGeoSchemaParser::new(&elem).err().expect("pointcount is missing");
+    assert_eq!("", err.path.to_string());
+    assert!(err.message.contains("pointcount"));
+
+    let elem = parse_patched_box("[0,1,3,2,", "[0,1,3,9,");
+    let err = HoudiniGeoSchemaParser::new(&elem).err().expect("point index out of range");
+    assert_eq!("topology/pointref/indices/3", err.path.to_string());
+}
+
+#[test]
+fn bad_group() {
+    let selection = r#"["unordered",["boolRLE",[2,true,4611686018427387904,false]]]"#;
+    let group = format!(r#""pointgroups",[[["name","top"],["selection",{}]]],"#, selection);
+    let elem = parse_patched_box("\"primitives\",[", &format!("{}\"primitives\",[", group));
+    let mut parser = HoudiniGeoSchemaParser::new(&elem).expect("header is fine");
+    let err = parser.parse_groups().expect_err("the run is longer than the point count");
+    assert_eq!("pointgroups/0/1/selection/unordered/1/2", err.path.to_string());
+}